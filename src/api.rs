@@ -1,2 +1,5 @@
+pub mod cloudflare;
+pub mod hetzner;
 pub mod ip;
 pub mod netcup;
+pub mod rfc2136;
@@ -0,0 +1,469 @@
+//! The [Cloudflare](https://developers.cloudflare.com/api/) DNS api.
+//!
+//! Cloudflare has no zone wide TTL and doesn't expose the SOA of a zone, the
+//! TTL is set on every record instead. The zone settings therefore report the
+//! TTL records are written with and the fixed SOA timers of Cloudflare.
+
+use async_trait::async_trait;
+use error_stack::{IntoReport, Report, ResultExt};
+use reqwest::{Method, RequestBuilder};
+use serde::de::DeserializeOwned;
+use tracing::{debug, info};
+
+use crate::{
+  api::netcup::models::{DnsRecord, RecordType},
+  cli::Cli,
+  errors::Errors,
+  provider::{Applied, DnsProvider, PartiallyApplied, ZoneSettings},
+};
+
+use self::models::{Envelope, Record, RecordParams, RecordPatch, Zone};
+
+pub mod models;
+
+const NAME: &str = "cloudflare";
+/// The TTL of records with the automatic TTL.
+const AUTO_TTL: u32 = 300;
+const REFRESH: u32 = 10_000;
+const RETRY: u32 = 2_400;
+const EXPIRE: u32 = 604_800;
+const PER_PAGE: &str = "100";
+
+pub struct Client {
+  client: reqwest::Client,
+  api_url: String,
+  token: String,
+  ttl: Option<u32>,
+}
+
+impl Client {
+  pub fn new(cli: &Cli) -> error_stack::Result<Self, Errors> {
+    let token = cli.cloudflare_token().ok_or_else(|| {
+      Report::new(Errors::ProviderConfig(
+        NAME,
+        "the API token is missing".into(),
+      ))
+    })?;
+
+    Ok(Self {
+      client: reqwest::Client::new(),
+      api_url: cli.cloudflare_api_url().trim_end_matches('/').into(),
+      token: token.into(),
+      ttl: cli.ttl(),
+    })
+  }
+
+  fn request(&self, method: Method, path: &str) -> RequestBuilder {
+    self
+      .client
+      .request(method, format!("{}{path}", self.api_url))
+      .bearer_auth(&self.token)
+  }
+
+  async fn send<T>(&self, request: RequestBuilder) -> error_stack::Result<Envelope<T>, Errors>
+  where
+    T: DeserializeOwned,
+  {
+    let response = request
+      .send()
+      .await
+      .into_report()
+      .change_context(Errors::ProviderRequest(NAME))?;
+
+    let status = response.status();
+    debug!(%status, "Recieved response");
+    let body = response
+      .text()
+      .await
+      .into_report()
+      .change_context(Errors::ProviderRequest(NAME))?;
+
+    let envelope = serde_json::from_str::<Envelope<T>>(&body)
+      .into_report()
+      .change_context(Errors::SerializeResponse)
+      .attach_printable_lazy(|| format!("Status {status}: {body}"))?;
+
+    if !status.is_success() || !envelope.success {
+      let messages = envelope
+        .errors
+        .iter()
+        .map(|error| format!("{}: {}", error.code, error.message))
+        .collect::<Vec<_>>();
+
+      return Err(
+        Report::new(Errors::ProviderRequest(NAME))
+          .attach(status)
+          .attach_printable(format!("Status {status}: {}", messages.join(", "))),
+      );
+    }
+
+    Ok(envelope)
+  }
+
+  async fn zone_id(&self, zone: &str) -> error_stack::Result<String, Errors> {
+    let zones = self
+      .send::<Vec<Zone>>(self.request(Method::GET, "/zones").query(&[("name", zone)]))
+      .await
+      .change_context_lazy(|| Errors::DNSZoneNotFound(zone.to_string()))?;
+
+    zones
+      .result
+      .unwrap_or_default()
+      .into_iter()
+      .find(|found| found.name.eq_ignore_ascii_case(zone))
+      .map(|found| found.id)
+      .ok_or_else(|| Report::new(Errors::DNSZoneNotFound(zone.to_string())))
+  }
+}
+
+#[async_trait]
+impl DnsProvider for Client {
+  fn name(&self) -> &'static str {
+    NAME
+  }
+
+  async fn records(&self, zone: &str) -> error_stack::Result<Vec<DnsRecord>, Errors> {
+    let zone_id = self.zone_id(zone).await?;
+
+    let mut records = vec![];
+    let mut page = 1;
+    loop {
+      let response = self
+        .send::<Vec<Record>>(
+          self
+            .request(Method::GET, &format!("/zones/{zone_id}/dns_records"))
+            .query(&[("page", page.to_string().as_str()), ("per_page", PER_PAGE)]),
+        )
+        .await?;
+
+      records.extend(response.result.unwrap_or_default());
+
+      match response.result_info {
+        Some(info) if info.page < info.total_pages => page += 1,
+        _ => break,
+      }
+    }
+
+    Ok(
+      records
+        .iter()
+        .filter_map(|record| to_dns_record(zone, record))
+        .collect(),
+    )
+  }
+
+  async fn apply(
+    &self,
+    zone: &str,
+    changes: Vec<DnsRecord>,
+  ) -> error_stack::Result<Applied, Errors> {
+    let zone_id = self.zone_id(zone).await?;
+    let path = format!("/zones/{zone_id}/dns_records");
+
+    let mut created = vec![];
+    for (index, change) in changes.iter().enumerate() {
+      let result = match (change.id(), change.delete_record()) {
+        (Some(id), true) => self
+          .send::<serde_json::Value>(self.request(Method::DELETE, &format!("{path}/{id}")))
+          .await
+          .map(|_| ()),
+        (None, true) => continue,
+        (id, false) => match to_record_params(zone, change, self.ttl) {
+          // A PUT would reset whatever it doesn't send, like the proxy
+          // status or a TTL set in the dashboard.
+          Ok(params) => match id {
+            Some(id) => self
+              .send::<serde_json::Value>(self.request(Method::PATCH, &format!("{path}/{id}")).json(
+                &RecordPatch {
+                  content: params.content,
+                  ttl: self.ttl,
+                  priority: params.priority,
+                },
+              ))
+              .await
+              .map(|_| ()),
+            None => self
+              .send::<Record>(self.request(Method::POST, &path).json(&params))
+              .await
+              .map(|envelope| {
+                created.extend(
+                  envelope
+                    .result
+                    .and_then(|record| to_dns_record(zone, &record)),
+                )
+              }),
+          },
+          Err(e) => Err(e),
+        },
+      };
+
+      // The changes before the failed one are applied already.
+      if let Err(e) = result {
+        return Err(
+          e.change_context(Errors::UpdateDNSRecords(zone.to_string()))
+            .attach_printable(format!("Could not apply {change:?}"))
+            .attach(PartiallyApplied::new(index, Applied::new(None, created))),
+        );
+      }
+    }
+
+    info!("Applied {} changes to {zone}", changes.len());
+
+    Ok(Applied::new(None, created))
+  }
+
+  async fn zone_settings(&self, zone: &str) -> error_stack::Result<ZoneSettings, Errors> {
+    self.zone_id(zone).await?;
+
+    Ok(ZoneSettings::new(
+      zone,
+      self.ttl.unwrap_or(AUTO_TTL),
+      0,
+      REFRESH,
+      RETRY,
+      EXPIRE,
+      false,
+    ))
+  }
+
+  async fn update_zone_settings(
+    &self,
+    zone: &str,
+    _settings: ZoneSettings,
+  ) -> error_stack::Result<(), Errors> {
+    Err(
+      Report::new(Errors::UpdateDNSZone(zone.to_string()))
+        .attach_printable("Cloudflare sets the TTL per record and has no zone settings"),
+    )
+  }
+}
+
+/// Cloudflare uses fully qualified names, the updater names relative to the
+/// zone with `@` for the apex.
+fn host_name(zone: &str, name: &str) -> String {
+  let name = name.to_lowercase();
+  let zone = zone.to_lowercase();
+
+  match name.strip_suffix(&zone) {
+    Some("") => "@".to_string(),
+    Some(host_name) if host_name.ends_with('.') => host_name.trim_end_matches('.').to_string(),
+    _ => name,
+  }
+}
+
+fn to_dns_record(zone: &str, record: &Record) -> Option<DnsRecord> {
+  let host_name = host_name(zone, &record.name);
+
+  let mut dns_record = match record.record_type.as_str() {
+    "NS" if host_name == "@" => return None,
+    "A" | "AAAA" => DnsRecord::new(host_name, record.content.parse().ok()?),
+    record_type => DnsRecord::other(
+      host_name,
+      RecordType::Other(record_type.into()),
+      &record.content,
+    ),
+  };
+
+  if let Some(priority) = record.priority {
+    dns_record.priority_mut(priority.to_string());
+  }
+  dns_record.id_mut(&record.id);
+
+  Some(dns_record)
+}
+
+fn to_record_params(
+  zone: &str,
+  record: &DnsRecord,
+  ttl: Option<u32>,
+) -> error_stack::Result<RecordParams, Errors> {
+  let priority = match (record.record_type(), record.priority()) {
+    (RecordType::Other(record_type), priority) if record_type.eq_ignore_ascii_case("MX") => Some(
+      priority
+        .unwrap_or("10")
+        .parse()
+        .into_report()
+        .change_context_lazy(|| Errors::ConvertRecord(format!("{record:?}")))?,
+    ),
+    _ => None,
+  };
+
+  Ok(RecordParams {
+    record_type: record.record_type().to_string(),
+    name: match record.host_name() {
+      "" | "@" => zone.to_string(),
+      host_name => format!("{host_name}.{zone}"),
+    },
+    content: record.destination().to_string(),
+    ttl: ttl.unwrap_or(1),
+    priority,
+  })
+}
+
+#[cfg(test)]
+mod test {
+  use serde_json::json;
+  use structopt::StructOpt;
+  use wiremock::{
+    matchers::{body_json, header, method, path, query_param},
+    Mock, MockServer, ResponseTemplate,
+  };
+
+  use super::*;
+
+  fn envelope(result: serde_json::Value, page: u32, total_pages: u32) -> serde_json::Value {
+    json!({
+      "success": true,
+      "errors": [],
+      "messages": [],
+      "result": result,
+      "result_info": { "page": page, "per_page": 100, "total_pages": total_pages }
+    })
+  }
+
+  async fn mock_server() -> (MockServer, Client) {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+      .and(path("/zones"))
+      .and(query_param("name", "example.com"))
+      .and(header("Authorization", "Bearer token"))
+      .respond_with(ResponseTemplate::new(200).set_body_json(envelope(
+        json!([{ "id": "zone1", "name": "example.com" }]),
+        1,
+        1,
+      )))
+      .mount(&server)
+      .await;
+
+    Mock::given(method("GET"))
+      .and(path("/zones/zone1/dns_records"))
+      .and(query_param("page", "1"))
+      .respond_with(ResponseTemplate::new(200).set_body_json(envelope(
+        json!([
+          { "id": "ns", "type": "NS", "name": "example.com", "content": "ns.cloudflare.com" },
+          { "id": "a", "type": "A", "name": "www.example.com", "content": "192.0.2.1" }
+        ]),
+        1,
+        2,
+      )))
+      .mount(&server)
+      .await;
+
+    Mock::given(method("GET"))
+      .and(path("/zones/zone1/dns_records"))
+      .and(query_param("page", "2"))
+      .respond_with(ResponseTemplate::new(200).set_body_json(envelope(
+        json!([
+          { "id": "mx", "type": "MX", "name": "example.com", "content": "mail.example.com", "priority": 20 }
+        ]),
+        2,
+        2,
+      )))
+      .mount(&server)
+      .await;
+
+    let cli = Cli::from_iter_safe([
+      "netcup-dns-updater",
+      "--cloudflare-token",
+      "token",
+      "--cloudflare-api-url",
+      &server.uri(),
+    ])
+    .unwrap();
+    let client = Client::new(&cli).unwrap();
+
+    (server, client)
+  }
+
+  #[tokio::test]
+  async fn read_paginated_records() {
+    let (_server, client) = mock_server().await;
+
+    let records = client.records("example.com").await.unwrap();
+    assert_eq!(2, records.len());
+    assert_eq!("www", records[0].host_name());
+    assert_eq!(Some("a"), records[0].id());
+    assert_eq!("@", records[1].host_name());
+    assert_eq!(Some("20"), records[1].priority());
+
+    let settings = client.zone_settings("example.com").await.unwrap();
+    assert_eq!(AUTO_TTL, settings.ttl());
+    assert!(settings.validate().is_ok());
+  }
+
+  #[tokio::test]
+  async fn apply_changes() {
+    let (server, client) = mock_server().await;
+
+    Mock::given(method("POST"))
+      .and(path("/zones/zone1/dns_records"))
+      .and(body_json(json!({
+        "type": "AAAA", "name": "www.example.com", "content": "2001:db8::1", "ttl": 1
+      })))
+      .respond_with(ResponseTemplate::new(200).set_body_json(envelope(
+        json!({
+          "id": "aaaa", "type": "AAAA", "name": "www.example.com", "content": "2001:db8::1"
+        }),
+        1,
+        1,
+      )))
+      .expect(1)
+      .mount(&server)
+      .await;
+    // The record stays proxied with its TTL, only the content is sent.
+    Mock::given(method("PATCH"))
+      .and(path("/zones/zone1/dns_records/a"))
+      .and(body_json(json!({ "content": "192.0.2.2" })))
+      .respond_with(ResponseTemplate::new(200).set_body_json(envelope(
+        json!({
+          "id": "a", "type": "A", "name": "www.example.com", "content": "192.0.2.2",
+          "proxied": true, "ttl": 1
+        }),
+        1,
+        1,
+      )))
+      .expect(1)
+      .mount(&server)
+      .await;
+
+    let mut update = DnsRecord::new("www", "192.0.2.2".parse().unwrap());
+    update.id_mut("a");
+
+    let applied = client
+      .apply(
+        "example.com",
+        vec![
+          DnsRecord::new("www", "2001:db8::1".parse().unwrap()),
+          update,
+        ],
+      )
+      .await
+      .unwrap();
+    assert_eq!(Some("aaaa"), applied.records()[0].id());
+    assert_eq!("www", applied.records()[0].host_name());
+  }
+
+  #[tokio::test]
+  async fn report_api_errors() {
+    let (server, client) = mock_server().await;
+
+    Mock::given(method("DELETE"))
+      .and(path("/zones/zone1/dns_records/a"))
+      .respond_with(ResponseTemplate::new(403).set_body_json(json!({
+        "success": false,
+        "errors": [{ "code": 10000, "message": "Authentication error" }],
+        "result": null
+      })))
+      .mount(&server)
+      .await;
+
+    let delete = serde_json::from_value(json!({
+      "id": "a", "hostname": "www", "type": "A", "priority": null,
+      "destination": "192.0.2.1", "deleterecord": true, "state": null
+    }))
+    .unwrap();
+
+    assert!(client.apply("example.com", vec![delete]).await.is_err());
+  }
+}
@@ -0,0 +1,63 @@
+use serde::{Deserialize, Serialize};
+
+/// Every response of the Cloudflare api is wrapped in an envelope.
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+pub struct Envelope<T> {
+  pub success: bool,
+  #[serde(default)]
+  pub errors: Vec<Message>,
+  pub result: Option<T>,
+  pub result_info: Option<ResultInfo>,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+pub struct Message {
+  pub code: u32,
+  pub message: String,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+pub struct ResultInfo {
+  pub page: u32,
+  pub total_pages: u32,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+pub struct Zone {
+  pub id: String,
+  pub name: String,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+pub struct Record {
+  pub id: String,
+  #[serde(rename = "type")]
+  pub record_type: String,
+  pub name: String,
+  pub content: String,
+  #[serde(default)]
+  pub priority: Option<u16>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct RecordParams {
+  #[serde(rename = "type")]
+  pub record_type: String,
+  pub name: String,
+  pub content: String,
+  /// `1` lets Cloudflare choose the TTL.
+  pub ttl: u32,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub priority: Option<u16>,
+}
+
+/// The fields an update changes, everything else of the record, e.g. whether
+/// it is proxied, stays as it is.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct RecordPatch {
+  pub content: String,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub ttl: Option<u32>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub priority: Option<u16>,
+}
@@ -0,0 +1,490 @@
+//! The [Hetzner DNS](https://dns.hetzner.com/api-docs) api.
+//!
+//! Hetzner stores the priority of MX records and the quotes of TXT records in
+//! the value, so they are split off and added again when converting records.
+//! Only the TTL of a zone can be changed, the SOA timers are read only.
+
+use async_trait::async_trait;
+use error_stack::{IntoReport, Report, ResultExt};
+use reqwest::{Method, RequestBuilder};
+use serde::de::DeserializeOwned;
+use tracing::{debug, info};
+
+use crate::{
+  api::netcup::models::{DnsRecord, RecordType},
+  cli::Cli,
+  errors::Errors,
+  provider::{Applied, DnsProvider, PartiallyApplied, ZoneSettings},
+};
+
+use self::models::{Record, RecordParams, RecordResponse, Records, UpdateZone, Zone, Zones};
+
+pub mod models;
+
+const NAME: &str = "hetzner";
+const DEFAULT_TTL: u32 = 86_400;
+const DEFAULT_REFRESH: u32 = 86_400;
+const DEFAULT_RETRY: u32 = 10_800;
+const DEFAULT_EXPIRE: u32 = 3_600_000;
+
+pub struct Client {
+  client: reqwest::Client,
+  api_url: String,
+  token: String,
+  ttl: Option<u32>,
+}
+
+impl Client {
+  pub fn new(cli: &Cli) -> error_stack::Result<Self, Errors> {
+    let token = cli.hetzner_token().ok_or_else(|| {
+      Report::new(Errors::ProviderConfig(
+        NAME,
+        "the API token is missing".into(),
+      ))
+    })?;
+
+    Ok(Self {
+      client: reqwest::Client::new(),
+      api_url: cli.hetzner_api_url().trim_end_matches('/').into(),
+      token: token.into(),
+      ttl: cli.ttl(),
+    })
+  }
+
+  fn request(&self, method: Method, path: &str) -> RequestBuilder {
+    self
+      .client
+      .request(method, format!("{}{path}", self.api_url))
+      .header("Auth-API-Token", &self.token)
+  }
+
+  async fn send<T>(&self, request: RequestBuilder) -> error_stack::Result<T, Errors>
+  where
+    T: DeserializeOwned,
+  {
+    let response = request
+      .send()
+      .await
+      .into_report()
+      .change_context(Errors::ProviderRequest(NAME))?;
+
+    let status = response.status();
+    debug!(%status, "Recieved response");
+    let body = response
+      .text()
+      .await
+      .into_report()
+      .change_context(Errors::ProviderRequest(NAME))?;
+
+    if !status.is_success() {
+      return Err(
+        Report::new(Errors::ProviderRequest(NAME))
+          .attach(status)
+          .attach_printable(format!("Status {status}: {body}")),
+      );
+    }
+
+    // DELETE answers with an empty body.
+    let body = if body.trim().is_empty() {
+      "null"
+    } else {
+      &body
+    };
+
+    serde_json::from_str(body)
+      .into_report()
+      .change_context(Errors::SerializeResponse)
+      .attach_printable_lazy(|| format!("Could not parse response {body}"))
+  }
+
+  async fn zone(&self, zone: &str) -> error_stack::Result<Zone, Errors> {
+    let zones: Zones = self
+      .send(self.request(Method::GET, "/zones").query(&[("name", zone)]))
+      .await
+      .change_context_lazy(|| Errors::DNSZoneNotFound(zone.to_string()))?;
+
+    zones
+      .zones
+      .into_iter()
+      .find(|found| found.name.eq_ignore_ascii_case(zone))
+      .ok_or_else(|| Report::new(Errors::DNSZoneNotFound(zone.to_string())))
+  }
+
+  async fn zone_records(&self, zone_id: &str) -> error_stack::Result<Vec<Record>, Errors> {
+    let records: Records = self
+      .send(
+        self
+          .request(Method::GET, "/records")
+          .query(&[("zone_id", zone_id)]),
+      )
+      .await?;
+
+    Ok(records.records)
+  }
+}
+
+#[async_trait]
+impl DnsProvider for Client {
+  fn name(&self) -> &'static str {
+    NAME
+  }
+
+  async fn records(&self, zone: &str) -> error_stack::Result<Vec<DnsRecord>, Errors> {
+    let zone_id = self.zone(zone).await?.id;
+
+    Ok(
+      self
+        .zone_records(&zone_id)
+        .await?
+        .iter()
+        .filter_map(to_dns_record)
+        .collect(),
+    )
+  }
+
+  async fn apply(
+    &self,
+    zone: &str,
+    changes: Vec<DnsRecord>,
+  ) -> error_stack::Result<Applied, Errors> {
+    let zone_id = self.zone(zone).await?.id;
+
+    let mut created = vec![];
+    for (index, change) in changes.iter().enumerate() {
+      let result = match (change.id(), change.delete_record()) {
+        (Some(id), true) => self
+          .send::<serde_json::Value>(self.request(Method::DELETE, &format!("/records/{id}")))
+          .await
+          .map(|_| ()),
+        (None, true) => continue,
+        (Some(id), false) => self
+          .send::<serde_json::Value>(
+            self
+              .request(Method::PUT, &format!("/records/{id}"))
+              .json(&to_record_params(&zone_id, change, self.ttl)),
+          )
+          .await
+          .map(|_| ()),
+        (None, false) => self
+          .send::<RecordResponse>(
+            self
+              .request(Method::POST, "/records")
+              .json(&to_record_params(&zone_id, change, self.ttl)),
+          )
+          .await
+          .map(|response| created.extend(to_dns_record(&response.record))),
+      };
+
+      // The changes before the failed one are applied already.
+      if let Err(e) = result {
+        return Err(
+          e.change_context(Errors::UpdateDNSRecords(zone.to_string()))
+            .attach_printable(format!("Could not apply {change:?}"))
+            .attach(PartiallyApplied::new(index, Applied::new(None, created))),
+        );
+      }
+    }
+
+    info!("Applied {} changes to {zone}", changes.len());
+
+    Ok(Applied::new(None, created))
+  }
+
+  async fn zone_settings(&self, zone: &str) -> error_stack::Result<ZoneSettings, Errors> {
+    let found = self.zone(zone).await?;
+    let records = self.zone_records(&found.id).await?;
+
+    let soa = records
+      .iter()
+      .find(|record| record.record_type == "SOA")
+      .map(|record| {
+        record
+          .value
+          .split_whitespace()
+          .filter_map(|value| value.parse::<u32>().ok())
+          .collect::<Vec<_>>()
+      })
+      .unwrap_or_default();
+
+    Ok(ZoneSettings::new(
+      zone,
+      found.ttl.unwrap_or(DEFAULT_TTL),
+      soa.first().copied().unwrap_or_default(),
+      soa.get(1).copied().unwrap_or(DEFAULT_REFRESH),
+      soa.get(2).copied().unwrap_or(DEFAULT_RETRY),
+      soa.get(3).copied().unwrap_or(DEFAULT_EXPIRE),
+      false,
+    ))
+  }
+
+  async fn update_zone_settings(
+    &self,
+    zone: &str,
+    settings: ZoneSettings,
+  ) -> error_stack::Result<(), Errors> {
+    let current = self.zone_settings(zone).await?;
+
+    if settings.dns_sec_status()
+      || settings.refresh() != current.refresh()
+      || settings.retry() != current.retry()
+      || settings.expire() != current.expire()
+    {
+      return Err(
+        Report::new(Errors::UpdateDNSZone(zone.to_string()))
+          .attach_printable("Hetzner only allows to change the TTL of a zone"),
+      );
+    }
+
+    let zone_id = self.zone(zone).await?.id;
+
+    self
+      .send::<serde_json::Value>(
+        self
+          .request(Method::PUT, &format!("/zones/{zone_id}"))
+          .json(&UpdateZone {
+            name: zone,
+            ttl: settings.ttl(),
+          }),
+      )
+      .await
+      .change_context(Errors::UpdateDNSZone(zone.to_string()))?;
+
+    Ok(())
+  }
+}
+
+fn to_dns_record(record: &Record) -> Option<DnsRecord> {
+  let mut dns_record = match record.record_type.as_str() {
+    // The SOA and the nameservers of the zone are managed by Hetzner.
+    "SOA" => return None,
+    "NS" if record.name == "@" => return None,
+    "A" | "AAAA" => DnsRecord::new(&record.name, record.value.parse().ok()?),
+    "TXT" => DnsRecord::other(
+      &record.name,
+      RecordType::Other("TXT".into()),
+      unquote(&record.value),
+    ),
+    "MX" => {
+      let (priority, exchange) = record.value.split_once(' ')?;
+      let mut dns_record = DnsRecord::other(&record.name, RecordType::Other("MX".into()), exchange);
+      dns_record.priority_mut(priority);
+      dns_record
+    }
+    record_type => DnsRecord::other(
+      &record.name,
+      RecordType::Other(record_type.into()),
+      &record.value,
+    ),
+  };
+
+  dns_record.id_mut(&record.id);
+
+  Some(dns_record)
+}
+
+fn to_record_params<'a>(
+  zone_id: &'a str,
+  record: &'a DnsRecord,
+  ttl: Option<u32>,
+) -> RecordParams<'a> {
+  let destination = record.destination().to_string();
+
+  let value = match record.record_type() {
+    RecordType::Other(record_type) if record_type.eq_ignore_ascii_case("TXT") => {
+      format!("\"{}\"", destination.replace('"', "\\\""))
+    }
+    RecordType::Other(record_type) if record_type.eq_ignore_ascii_case("MX") => {
+      format!("{} {destination}", record.priority().unwrap_or("10"))
+    }
+    _ => destination,
+  };
+
+  RecordParams {
+    zone_id,
+    record_type: record.record_type().to_string(),
+    name: match record.host_name() {
+      "" => "@",
+      host_name => host_name,
+    },
+    value,
+    ttl,
+  }
+}
+
+/// Joins the quoted strings of a TXT value, e.g. `"a" "b"` becomes `ab`.
+fn unquote(value: &str) -> String {
+  let value = value.trim();
+  if !value.starts_with('"') {
+    return value.to_string();
+  }
+
+  value
+    .split("\" \"")
+    .map(|part| part.trim_matches('"').replace("\\\"", "\""))
+    .collect()
+}
+
+#[cfg(test)]
+mod test {
+  use serde_json::json;
+  use structopt::StructOpt;
+  use wiremock::{
+    matchers::{body_json, header, method, path, query_param},
+    Mock, MockServer, ResponseTemplate,
+  };
+
+  use super::*;
+  use crate::api::netcup::models::IpType;
+
+  async fn mock_server() -> (MockServer, Client) {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+      .and(path("/zones"))
+      .and(query_param("name", "example.com"))
+      .and(header("Auth-API-Token", "token"))
+      .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+        "zones": [{ "id": "zone1", "name": "example.com", "ttl": 86400 }]
+      })))
+      .mount(&server)
+      .await;
+
+    Mock::given(method("GET"))
+      .and(path("/records"))
+      .and(query_param("zone_id", "zone1"))
+      .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+        "records": [
+          { "id": "soa", "type": "SOA", "name": "@", "value": "hydrogen.ns.hetzner.com. dns.hetzner.com. 2023010101 86400 10800 3600000 3600" },
+          { "id": "ns", "type": "NS", "name": "@", "value": "hydrogen.ns.hetzner.com." },
+          { "id": "a", "type": "A", "name": "www", "value": "192.0.2.1" },
+          { "id": "txt", "type": "TXT", "name": "_acme-challenge", "value": "\"token\"" },
+          { "id": "mx", "type": "MX", "name": "@", "value": "10 mail.example.com." }
+        ]
+      })))
+      .mount(&server)
+      .await;
+
+    let cli = Cli::from_iter_safe([
+      "netcup-dns-updater",
+      "--hetzner-token",
+      "token",
+      "--hetzner-api-url",
+      &server.uri(),
+    ])
+    .unwrap();
+    let client = Client::new(&cli).unwrap();
+
+    (server, client)
+  }
+
+  #[tokio::test]
+  async fn read_records_and_settings() {
+    let (_server, client) = mock_server().await;
+
+    let records = client.records("example.com").await.unwrap();
+    assert_eq!(3, records.len());
+    assert_eq!(Some("a"), records[0].id());
+    assert_eq!(&RecordType::A, records[0].record_type());
+    assert_eq!(&IpType::Other("token".into()), records[1].destination());
+    assert_eq!(Some("10"), records[2].priority());
+
+    let settings = client.zone_settings("example.com").await.unwrap();
+    assert_eq!(86_400, settings.ttl());
+    assert_eq!(10_800, settings.retry());
+    assert_eq!(3_600_000, settings.expire());
+  }
+
+  #[tokio::test]
+  async fn apply_changes() {
+    let (server, client) = mock_server().await;
+
+    Mock::given(method("POST"))
+      .and(path("/records"))
+      .and(body_json(json!({
+        "zone_id": "zone1", "type": "AAAA", "name": "www", "value": "2001:db8::1"
+      })))
+      .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "record": {
+        "id": "aaaa", "type": "AAAA", "name": "www", "value": "2001:db8::1"
+      } })))
+      .expect(1)
+      .mount(&server)
+      .await;
+    Mock::given(method("PUT"))
+      .and(path("/records/txt"))
+      .and(body_json(json!({
+        "zone_id": "zone1", "type": "TXT", "name": "_acme-challenge", "value": "\"new\""
+      })))
+      .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "record": {} })))
+      .expect(1)
+      .mount(&server)
+      .await;
+    Mock::given(method("DELETE"))
+      .and(path("/records/a"))
+      .respond_with(ResponseTemplate::new(200))
+      .expect(1)
+      .mount(&server)
+      .await;
+
+    let mut records = client.records("example.com").await.unwrap();
+    let delete = serde_json::from_value(json!({
+      "id": "a", "hostname": "www", "type": "A", "priority": null,
+      "destination": "192.0.2.1", "deleterecord": true, "state": null
+    }))
+    .unwrap();
+    records.remove(0);
+    let mut update = DnsRecord::other("_acme-challenge", RecordType::Other("TXT".into()), "new");
+    update.id_mut(records[0].id().unwrap());
+
+    let applied = client
+      .apply(
+        "example.com",
+        vec![
+          DnsRecord::new("www", "2001:db8::1".parse().unwrap()),
+          update,
+          delete,
+        ],
+      )
+      .await
+      .unwrap();
+    assert_eq!(1, applied.records().len());
+    assert_eq!(Some("aaaa"), applied.records()[0].id());
+  }
+
+  #[tokio::test]
+  async fn report_partially_applied_changes() {
+    let (server, client) = mock_server().await;
+
+    Mock::given(method("POST"))
+      .and(path("/records"))
+      .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "record": {
+        "id": "aaaa", "type": "AAAA", "name": "www", "value": "2001:db8::1"
+      } })))
+      .expect(1)
+      .mount(&server)
+      .await;
+    Mock::given(method("PUT"))
+      .and(path("/records/a"))
+      .respond_with(ResponseTemplate::new(500))
+      .expect(1)
+      .mount(&server)
+      .await;
+
+    let mut update = DnsRecord::new("www", "192.0.2.2".parse().unwrap());
+    update.id_mut("a");
+    let error = client
+      .apply(
+        "example.com",
+        vec![
+          DnsRecord::new("www", "2001:db8::1".parse().unwrap()),
+          update,
+          DnsRecord::new("mail", "192.0.2.2".parse().unwrap()),
+        ],
+      )
+      .await
+      .unwrap_err();
+
+    let partial = error.downcast_ref::<PartiallyApplied>().unwrap();
+    assert_eq!(1, partial.count());
+    assert_eq!(Some("aaaa"), partial.applied().records()[0].id());
+  }
+}
@@ -0,0 +1,54 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Zone {
+  pub id: String,
+  pub name: String,
+  #[serde(default)]
+  pub ttl: Option<u32>,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+pub struct Zones {
+  pub zones: Vec<Zone>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct UpdateZone<'a> {
+  pub name: &'a str,
+  pub ttl: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Record {
+  pub id: String,
+  #[serde(rename = "type")]
+  pub record_type: String,
+  pub name: String,
+  pub value: String,
+  #[serde(default)]
+  pub ttl: Option<u32>,
+}
+
+/// The answer to creating or updating a record.
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+pub struct RecordResponse {
+  pub record: Record,
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+pub struct Records {
+  #[serde(default)]
+  pub records: Vec<Record>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct RecordParams<'a> {
+  pub zone_id: &'a str,
+  #[serde(rename = "type")]
+  pub record_type: String,
+  pub name: &'a str,
+  pub value: String,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub ttl: Option<u32>,
+}
@@ -1,6 +1,14 @@
 use std::net::IpAddr;
+
+use crate::metrics::metrics;
+
+/// The source label of the addresses in the metrics.
+const SOURCE: &str = "public-ip";
+
 pub async fn external() -> Vec<IpAddr> {
   let (ip4, ip6) = tokio::join!(public_ip::addr_v4(), public_ip::addr_v6());
+  metrics().ip_detection(SOURCE, "ipv4", ip4.is_some());
+  metrics().ip_detection(SOURCE, "ipv6", ip6.is_some());
   let ips = vec![ip4.map(IpAddr::V4), ip6.map(IpAddr::V6)];
 
   ips.into_iter().flatten().collect()
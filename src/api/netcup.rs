@@ -1,23 +1,45 @@
 use error_stack::{IntoReport, Report, ResultExt};
-use log::{debug, error, info};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_repr::{Deserialize_repr, Serialize_repr};
+use serde_with::{DeserializeFromStr, SerializeDisplay};
+use tracing::{debug, error, field, info, info_span, Instrument, Span};
 
-use std::fmt::Debug;
+use std::{
+  fmt::{self, Debug},
+  str::FromStr,
+  time::Instant,
+};
 
-use crate::{cli::Cli, errors::Errors};
+use crate::{cli::Cli, errors::Errors, metrics::metrics};
+use budget::budget;
 use models::{Request, Response};
 
 use self::models::{NoApiSessionId, SessionCredentials};
 
+pub mod ack_poll;
+pub mod budget;
+pub mod create_handle;
+pub mod delete_handle;
 pub mod info_dns_records;
 pub mod info_dns_zone;
+pub mod info_domain;
+pub mod info_handle;
+pub mod listall_handle;
 pub mod login;
 pub mod logout;
+#[cfg(test)]
+pub mod mock;
 pub mod models;
+pub mod poll;
+pub mod provider;
+pub mod raw;
+pub mod session;
 pub mod update_dns_records;
 pub mod update_dns_zone;
+pub mod update_domain;
+pub mod update_handle;
 
+#[derive(Clone)]
 pub struct Client<T> {
   client: reqwest::Client,
   api_url: String,
@@ -25,16 +47,19 @@ pub struct Client<T> {
 }
 
 impl Client<NoApiSessionId> {
-  pub fn new(cli: &Cli) -> Self {
-    Self {
+  pub fn new(cli: &Cli) -> error_stack::Result<Self, Errors> {
+    let (Some(customer_number), Some(api_key), Some(api_password)) =
+      (cli.customer_number(), cli.api_key(), cli.api_password())
+    else {
+      return Err(Report::new(Errors::MissingCredentials));
+    };
+    budget().set_limit(cli.requests_per_minute());
+
+    Ok(Self {
       client: reqwest::Client::new(),
       api_url: cli.api_url().into(),
-      session_credentials: SessionCredentials::new(
-        cli.customer_number(),
-        cli.api_key(),
-        cli.api_password(),
-      ),
-    }
+      session_credentials: SessionCredentials::new(customer_number, api_key, api_password),
+    })
   }
 }
 
@@ -47,7 +72,55 @@ where
   Rq: Serialize + Sized + Debug,
   Rs: DeserializeOwned + Sized + Debug,
 {
-  info!("Performing request: {:#?} {request:#?}", request.action());
+  async { check(perform(url, client, request).await?) }
+    .instrument(span(request))
+    .await
+}
+
+/// Like [`request`], but returns the response whatever its status code.
+async fn request_unchecked<Rq, Rs>(
+  url: &str,
+  client: &reqwest::Client,
+  request: &Request<Rq>,
+) -> error_stack::Result<Response<Rs>, Errors>
+where
+  Rq: Serialize + Sized + Debug,
+  Rs: DeserializeOwned + Sized + Debug,
+{
+  perform(url, client, request)
+    .instrument(span(request))
+    .await
+}
+
+fn span<Rq>(request: &Request<Rq>) -> Span {
+  info_span!(
+    "request",
+    action = %request.action(),
+    serverrequestid = field::Empty,
+    statuscode = field::Empty,
+    duration_ms = field::Empty,
+  )
+}
+
+async fn perform<Rq, Rs>(
+  url: &str,
+  client: &reqwest::Client,
+  request: &Request<Rq>,
+) -> error_stack::Result<Response<Rs>, Errors>
+where
+  Rq: Serialize + Sized + Debug,
+  Rs: DeserializeOwned + Sized + Debug,
+{
+  budget().acquire().await;
+  info!("Performing request");
+  debug!(?request, "Request parameters");
+  let started = Instant::now();
+  let failed = || {
+    let duration = started.elapsed();
+    Span::current().record("duration_ms", duration.as_millis() as u64);
+    metrics().request(request.action(), None, duration);
+  };
+
   let resonse = client
     .post(url)
     .json(&request)
@@ -55,56 +128,125 @@ where
     .await
     .into_report()
     .change_context(Errors::SendRequest)
-    .attach_printable(format!("Could not send request {:#?}", request))?;
+    .attach_printable(format!("Could not send the {} request", request.action()))
+    .inspect_err(|_| failed())?;
 
-  debug!("Recieved response: {:#?}", resonse);
+  debug!(status = %resonse.status(), "Recieved response");
 
   if !resonse.status().is_success() {
+    failed();
     error!(
+      status = %resonse.status(),
       "Http status code {} while performing the request",
       resonse.status()
     );
-    return Err(Report::new(Errors::SendRequest));
+    return Err(Report::new(Errors::SendRequest).attach(resonse.status()));
   }
 
   let body = resonse
     .text()
     .await
     .into_report()
-    .change_context(Errors::SerializeResponse)?;
+    .change_context(Errors::SerializeResponse)
+    .inspect_err(|_| failed())?;
 
-  debug!("Recieved response body: {:#?}", body);
+  debug!(body = %models::redacted(&body), "Recieved response body");
 
   let response_object = serde_json::from_str::<Response<Rs>>(&body)
     .into_report()
-    .change_context(Errors::SerializeResponse)?;
+    .change_context(Errors::SerializeResponse)
+    .inspect_err(|_| failed())?;
 
-  debug!("Serialized response body: {:#?}", response_object);
+  let duration = started.elapsed();
+  let span = Span::current();
+  span.record("serverrequestid", response_object.server_request_id());
+  span.record("statuscode", response_object.status_code() as u32);
+  span.record("duration_ms", duration.as_millis() as u64);
+  metrics().request(
+    request.action(),
+    Some(response_object.status_code()),
+    duration,
+  );
 
+  Ok(response_object)
+}
+
+/// Turns a response Netcup didn't answer successfully into an error.
+fn check<Rs>(response_object: Response<Rs>) -> error_stack::Result<Response<Rs>, Errors>
+where
+  Rs: DeserializeOwned,
+{
   match response_object.status_code() {
     StatusCode::Success => {
-      info!("Request {:#?} was successful.", request.action());
+      info!("Request was successful.");
       Ok(response_object)
     }
     StatusCode::Error => {
       error!(
-        "Request {:#?} wasn't successful. Maybe the API key is not valid",
-        request.action()
+        message = %response_object.server_message(),
+        "Request wasn't successful. Maybe the API key is not valid"
       );
-      Err(Errors::SendRequest.into())
+      Err(Report::new(Errors::SendRequest).attach_printable(response_object.server_message()))
     }
     StatusCode::ValidationError => {
       error!(
-        "Request {:#?} wasn't successful. Maybe too many requests in one hour.",
-        request.action()
+        message = %response_object.server_message(),
+        "Request wasn't successful. Maybe too many requests in one hour."
       );
-      Err(Errors::ValidationError.into())
+      Err(Report::new(Errors::ValidationError).attach_printable(response_object.server_message()))
     }
   }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
-#[serde(rename_all = "camelCase")]
+/// The messages Netcup answered a failed request with, attached to its error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerMessage {
+  short: String,
+  long: Option<String>,
+}
+
+impl ServerMessage {
+  pub fn new(short: impl Into<String>, long: Option<String>) -> Self {
+    Self {
+      short: short.into(),
+      long: long.filter(|long| !long.is_empty()),
+    }
+  }
+}
+
+impl ServerMessage {
+  /// Whether the message is about an unknown or expired session. Netcup
+  /// answers both with a generic error, only the message tells them apart.
+  pub fn is_invalid_session(&self) -> bool {
+    [Some(&self.short), self.long.as_ref()]
+      .into_iter()
+      .flatten()
+      .any(|message| message.to_lowercase().contains("session"))
+  }
+
+  /// Whether Netcup refused the request because of too many requests. The
+  /// status code of a rate limit is the one of every other validation error.
+  pub fn is_rate_limit(&self) -> bool {
+    [Some(&self.short), self.long.as_ref()]
+      .into_iter()
+      .flatten()
+      .map(|message| message.to_lowercase())
+      .any(|message| message.contains("requests per") || message.contains("too many requests"))
+  }
+}
+
+impl fmt::Display for ServerMessage {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match &self.long {
+      Some(long) => write!(f, "Netcup: {}: {long}", self.short),
+      None => write!(f, "Netcup: {}", self.short),
+    }
+  }
+}
+
+/// Actions the client doesn't know yet are kept as [`Action::Other`] so they
+/// can still be sent with [`Client::call_raw`] and echoed back in responses.
+#[derive(Debug, Clone, PartialEq, Eq, SerializeDisplay, DeserializeFromStr)]
 pub enum Action {
   Login,
   Logout,
@@ -112,6 +254,70 @@ pub enum Action {
   UpdateDnsZone,
   InfoDnsRecords,
   UpdateDnsRecords,
+  InfoDomain,
+  UpdateDomain,
+  CreateHandle,
+  UpdateHandle,
+  InfoHandle,
+  ListallHandle,
+  DeleteHandle,
+  Poll,
+  AckPoll,
+  Other(String),
+}
+
+impl Action {
+  pub fn as_str(&self) -> &str {
+    match self {
+      Self::Login => "login",
+      Self::Logout => "logout",
+      Self::InfoDnsZone => "infoDnsZone",
+      Self::UpdateDnsZone => "updateDnsZone",
+      Self::InfoDnsRecords => "infoDnsRecords",
+      Self::UpdateDnsRecords => "updateDnsRecords",
+      Self::InfoDomain => "infoDomain",
+      Self::UpdateDomain => "updateDomain",
+      Self::CreateHandle => "createHandle",
+      Self::UpdateHandle => "updateHandle",
+      Self::InfoHandle => "infoHandle",
+      Self::ListallHandle => "listallHandle",
+      Self::DeleteHandle => "deleteHandle",
+      Self::Poll => "poll",
+      Self::AckPoll => "ackPoll",
+      Self::Other(action) => action,
+    }
+  }
+}
+
+impl FromStr for Action {
+  type Err = Errors;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "login" => Ok(Self::Login),
+      "logout" => Ok(Self::Logout),
+      "infoDnsZone" => Ok(Self::InfoDnsZone),
+      "updateDnsZone" => Ok(Self::UpdateDnsZone),
+      "infoDnsRecords" => Ok(Self::InfoDnsRecords),
+      "updateDnsRecords" => Ok(Self::UpdateDnsRecords),
+      "infoDomain" => Ok(Self::InfoDomain),
+      "updateDomain" => Ok(Self::UpdateDomain),
+      "createHandle" => Ok(Self::CreateHandle),
+      "updateHandle" => Ok(Self::UpdateHandle),
+      "infoHandle" => Ok(Self::InfoHandle),
+      "listallHandle" => Ok(Self::ListallHandle),
+      "deleteHandle" => Ok(Self::DeleteHandle),
+      "poll" => Ok(Self::Poll),
+      "ackPoll" => Ok(Self::AckPoll),
+      _ => Ok(Self::Other(s.to_string())),
+    }
+  }
+}
+
+impl fmt::Display for Action {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(self.as_str())
+  }
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
@@ -131,3 +337,122 @@ pub enum StatusCode {
   Error = 4001,
   ValidationError = 4013,
 }
+
+#[cfg(test)]
+mod test {
+  use std::{
+    io,
+    sync::{Arc, Mutex},
+  };
+
+  use serde_json::Value;
+
+  use super::*;
+
+  #[test]
+  fn serialize_action() {
+    assert_eq!(
+      "\"infoDnsRecords\"",
+      serde_json::to_string(&Action::InfoDnsRecords).unwrap()
+    );
+    assert_eq!(
+      Action::ListallHandle,
+      serde_json::from_str::<Action>("\"listallHandle\"").unwrap()
+    );
+    assert_eq!(
+      Action::Other("listallDomains".to_string()),
+      serde_json::from_str::<Action>("\"listallDomains\"").unwrap()
+    );
+    assert_eq!(
+      "\"listallDomains\"",
+      serde_json::to_string(&Action::Other("listallDomains".to_string())).unwrap()
+    );
+  }
+
+  #[derive(Clone, Default)]
+  struct Captured(Arc<Mutex<Vec<u8>>>);
+
+  impl io::Write for Captured {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+      self.0.lock().unwrap().extend_from_slice(buf);
+      Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+      Ok(())
+    }
+  }
+
+  #[tokio::test]
+  async fn log_request_fields() {
+    let server = mock::server().await;
+    let captured = Captured::default();
+    let writer = captured.clone();
+    let subscriber = tracing_subscriber::fmt()
+      .json()
+      .flatten_event(true)
+      .with_current_span(true)
+      .with_writer(move || writer.clone())
+      .finish();
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    Client::new(&mock::cli(&server, &[]))
+      .unwrap()
+      .login()
+      .await
+      .unwrap();
+
+    let output = String::from_utf8(captured.0.lock().unwrap().clone()).unwrap();
+    let event = output
+      .lines()
+      .map(|line| serde_json::from_str::<Value>(line).unwrap())
+      .find(|event| event["message"] == "Request was successful.")
+      .unwrap_or_else(|| panic!("no successful request in\n{output}"));
+    let span = &event["span"];
+    assert_eq!("login", span["action"]);
+    assert_eq!("SUPERSECRETSERVERREQUESTID", span["serverrequestid"]);
+    assert_eq!(2000, span["statuscode"]);
+    assert!(span["duration_ms"].is_u64());
+  }
+
+  #[tokio::test]
+  async fn send_names_as_punycode() {
+    let server = mock::server().await;
+    mock::action(
+      "updateDnsRecords",
+      serde_json::json!({
+        "domainname": "xn--mller-bau-q9a.de",
+        "dnsrecordset": { "dnsrecords": [{ "hostname": "xn--bro-hoa" }] },
+      }),
+    )
+    .respond_with(mock::response(
+      "updateDnsRecords",
+      serde_json::json!({ "dnsrecords": [] }),
+    ))
+    .expect(1)
+    .mount(&server)
+    .await;
+    let client = Client::new(&mock::cli(&server, &[]))
+      .unwrap()
+      .login()
+      .await
+      .unwrap();
+    let record = |host_name| models::DnsRecord::new(host_name, "192.0.2.1".parse().unwrap());
+
+    client
+      .update_dns_records("Müller-Bau.de", vec![record("Büro")])
+      .await
+      .unwrap();
+
+    let requests = server.received_requests().await.unwrap().len();
+    let error = client
+      .update_dns_records("müller-bau.de", vec![record("-büro")])
+      .await
+      .unwrap_err();
+    assert!(matches!(
+      error.current_context(),
+      Errors::InvalidDomainName(_, label) if label == "-büro"
+    ));
+    assert_eq!(requests, server.received_requests().await.unwrap().len());
+  }
+}
@@ -0,0 +1,29 @@
+use error_stack::ResultExt;
+
+use crate::{api, errors::Errors};
+
+use super::{
+  models::{
+    ack_poll::{Params, ResponseData},
+    ApiSessionId, Request, Response,
+  },
+  Action, Client,
+};
+
+impl Client<ApiSessionId> {
+  pub async fn ack_poll(
+    &self,
+    poll_id: u32,
+  ) -> error_stack::Result<Response<ResponseData>, Errors> {
+    api::netcup::request::<Params, ResponseData>(
+      &self.api_url,
+      &self.client,
+      &Request::new(
+        Action::AckPoll,
+        Params::new(poll_id, &self.session_credentials),
+      ),
+    )
+    .await
+    .change_context(Errors::AckPoll(poll_id))
+  }
+}
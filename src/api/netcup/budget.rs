@@ -0,0 +1,91 @@
+//! Spaces the requests to Netcup, so concurrent zones share the requests a
+//! minute allows instead of being throttled by Netcup.
+
+use std::{
+  collections::VecDeque,
+  sync::{
+    atomic::{AtomicUsize, Ordering},
+    Mutex, OnceLock,
+  },
+  time::Duration,
+};
+
+use tokio::time::{sleep, Instant};
+use tracing::debug;
+
+/// The budget of every client of the process.
+pub fn budget() -> &'static Budget {
+  static BUDGET: OnceLock<Budget> = OnceLock::new();
+  BUDGET.get_or_init(|| Budget::new(120, Duration::from_secs(60)))
+}
+
+/// A sliding window of the requests sent most recently.
+#[derive(Debug)]
+pub struct Budget {
+  limit: AtomicUsize,
+  window: Duration,
+  sent: Mutex<VecDeque<Instant>>,
+}
+
+impl Budget {
+  fn new(limit: usize, window: Duration) -> Self {
+    Self {
+      limit: AtomicUsize::new(limit.max(1)),
+      window,
+      sent: Mutex::default(),
+    }
+  }
+
+  pub fn set_limit(&self, limit: usize) {
+    self.limit.store(limit.max(1), Ordering::Relaxed);
+  }
+
+  /// Waits until another request fits into the window.
+  pub async fn acquire(&self) {
+    loop {
+      let wait = {
+        let Ok(mut sent) = self.sent.lock() else {
+          return;
+        };
+        let now = Instant::now();
+        while sent
+          .front()
+          .is_some_and(|sent| now.duration_since(*sent) >= self.window)
+        {
+          sent.pop_front();
+        }
+
+        if sent.len() < self.limit.load(Ordering::Relaxed) {
+          sent.push_back(now);
+          return;
+        }
+        sent
+          .front()
+          .map(|oldest| self.window.saturating_sub(now.duration_since(*oldest)))
+          .unwrap_or_default()
+      };
+
+      debug!(?wait, "The request budget is used up, waiting");
+      sleep(wait).await;
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[tokio::test]
+  async fn wait_for_the_window() {
+    let window = Duration::from_millis(200);
+    let budget = Budget::new(2, window);
+    let started = Instant::now();
+
+    budget.acquire().await;
+    budget.acquire().await;
+    assert!(started.elapsed() < window);
+
+    budget.acquire().await;
+    assert!(started.elapsed() >= window);
+  }
+}
@@ -0,0 +1,30 @@
+use error_stack::ResultExt;
+
+use crate::{api, errors::Errors};
+
+use super::{
+  models::{
+    create_handle::{Params, ResponseData},
+    info_handle::HandleData,
+    ApiSessionId, Request, Response,
+  },
+  Action, Client,
+};
+
+impl Client<ApiSessionId> {
+  pub async fn create_handle(
+    &self,
+    handle: HandleData,
+  ) -> error_stack::Result<Response<ResponseData>, Errors> {
+    api::netcup::request::<Params, ResponseData>(
+      &self.api_url,
+      &self.client,
+      &Request::new(
+        Action::CreateHandle,
+        Params::new(&self.session_credentials, handle),
+      ),
+    )
+    .await
+    .change_context(Errors::CreateHandle)
+  }
+}
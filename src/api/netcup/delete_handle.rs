@@ -0,0 +1,29 @@
+use error_stack::ResultExt;
+
+use crate::{api, errors::Errors};
+
+use super::{
+  models::{
+    delete_handle::{Params, ResponseData},
+    ApiSessionId, Request, Response,
+  },
+  Action, Client,
+};
+
+impl Client<ApiSessionId> {
+  pub async fn delete_handle(
+    &self,
+    handle_id: u32,
+  ) -> error_stack::Result<Response<ResponseData>, Errors> {
+    api::netcup::request::<Params, ResponseData>(
+      &self.api_url,
+      &self.client,
+      &Request::new(
+        Action::DeleteHandle,
+        Params::new(handle_id, &self.session_credentials),
+      ),
+    )
+    .await
+    .change_context(Errors::DeleteHandle(handle_id))
+  }
+}
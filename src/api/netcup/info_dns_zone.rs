@@ -1,6 +1,6 @@
-use error_stack::ResultExt;
+use error_stack::{IntoReport, ResultExt};
 
-use crate::{api, errors::Errors};
+use crate::{api, errors::Errors, hostname::to_ascii};
 
 use super::{
   models::{
@@ -15,15 +15,16 @@ impl Client<ApiSessionId> {
     &self,
     domain_name: impl Into<String>,
   ) -> error_stack::Result<Response<ResponseData>, Errors> {
+    let domain_name = to_ascii(&domain_name.into()).into_report()?;
     api::netcup::request::<Params, ResponseData>(
       &self.api_url,
       &self.client,
       &Request::new(
         Action::InfoDnsZone,
-        Params::new(domain_name.into(), &self.session_credentials),
+        Params::new(domain_name.clone(), &self.session_credentials),
       ),
     )
     .await
-    .change_context(Errors::Login)
+    .change_context(Errors::DNSZoneNotFound(domain_name))
   }
 }
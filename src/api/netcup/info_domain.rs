@@ -0,0 +1,30 @@
+use error_stack::{IntoReport, ResultExt};
+
+use crate::{api, errors::Errors, hostname::to_ascii};
+
+use super::{
+  models::{
+    info_domain::{Params, ResponseData},
+    ApiSessionId, Request, Response,
+  },
+  Action, Client,
+};
+
+impl Client<ApiSessionId> {
+  pub async fn info_domain(
+    &self,
+    domain_name: impl Into<String>,
+  ) -> error_stack::Result<Response<ResponseData>, Errors> {
+    let domain_name = to_ascii(&domain_name.into()).into_report()?;
+    api::netcup::request::<Params, ResponseData>(
+      &self.api_url,
+      &self.client,
+      &Request::new(
+        Action::InfoDomain,
+        Params::new(domain_name.clone(), &self.session_credentials),
+      ),
+    )
+    .await
+    .change_context(Errors::InfoDomain(domain_name))
+  }
+}
@@ -0,0 +1,26 @@
+use error_stack::ResultExt;
+
+use crate::{api, errors::Errors};
+
+use super::{
+  models::{
+    listall_handle::{Params, ResponseData},
+    ApiSessionId, Request, Response,
+  },
+  Action, Client,
+};
+
+impl Client<ApiSessionId> {
+  pub async fn listall_handle(&self) -> error_stack::Result<Response<ResponseData>, Errors> {
+    api::netcup::request::<Params, ResponseData>(
+      &self.api_url,
+      &self.client,
+      &Request::new(
+        Action::ListallHandle,
+        Params::new(&self.session_credentials),
+      ),
+    )
+    .await
+    .change_context(Errors::ListHandles)
+  }
+}
@@ -1,5 +1,5 @@
 use error_stack::{Report, ResultExt};
-use log::debug;
+use tracing::debug;
 
 use crate::{api, errors::Errors};
 
@@ -33,7 +33,7 @@ impl Client<NoApiSessionId> {
       .map(|data| data.api_session_id())
       .ok_or_else(|| Report::new(Errors::RetrieveAPISesionId))?;
 
-    debug!("API session id: {api_session_id:#?}");
+    debug!("Received an API session id");
 
     Ok(Client::<ApiSessionId> {
       client: self.client,
@@ -41,4 +41,13 @@ impl Client<NoApiSessionId> {
       session_credentials: self.session_credentials.api_session_id(api_session_id),
     })
   }
+
+  /// Continues the session of an earlier login without a request.
+  pub fn resume(self, api_session_id: impl Into<String>) -> Client<ApiSessionId> {
+    Client::<ApiSessionId> {
+      client: self.client,
+      api_url: self.api_url,
+      session_credentials: self.session_credentials.api_session_id(api_session_id),
+    }
+  }
 }
@@ -20,7 +20,7 @@ impl Client<ApiSessionId> {
         Params::new(
           self.session_credentials.customer_number(),
           self.session_credentials.api_key(),
-          self.session_credentials.api_password(),
+          self.session_credentials.api_session_id(),
         ),
       ),
     )
@@ -0,0 +1,73 @@
+//! A Netcup API on a local mock server for tests. Every action is sent to the
+//! same endpoint, so the mocks match on the action in the body.
+
+use serde_json::{json, Value};
+use structopt::StructOpt;
+use wiremock::{
+  matchers::{body_partial_json, method},
+  Mock, MockServer, ResponseTemplate,
+};
+
+use crate::cli::Cli;
+
+pub const API_SESSION_ID: &str = "SUPERSECRETAPISESSIONID";
+
+/// A successful response of `action`.
+pub fn response(action: &str, response_data: Value) -> ResponseTemplate {
+  ResponseTemplate::new(200).set_body_json(json!({
+    "serverrequestid": "SUPERSECRETSERVERREQUESTID",
+    "clientrequestid": "",
+    "action": action,
+    "status": "success",
+    "statuscode": 2000,
+    "shortmessage": format!("{action} successful"),
+    "longmessage": "",
+    "responsedata": response_data,
+  }))
+}
+
+/// A mock for `action`, its params have to contain `params`.
+pub fn action(action: &str, params: Value) -> wiremock::MockBuilder {
+  Mock::given(method("POST")).and(body_partial_json(json!({
+    "action": action,
+    "param": params,
+  })))
+}
+
+/// A server which accepts the login and the logout of its session.
+pub async fn server() -> MockServer {
+  let server = MockServer::start().await;
+
+  action("login", json!({ "customernumber": 12345 }))
+    .respond_with(response("login", json!({ "apisessionid": API_SESSION_ID })))
+    .mount(&server)
+    .await;
+  action("logout", json!({ "apisessionid": API_SESSION_ID }))
+    .respond_with(response("logout", json!("")))
+    .mount(&server)
+    .await;
+
+  server
+}
+
+/// The cli with the credentials of the mock server and `args`.
+pub fn cli(server: &MockServer, args: &[&str]) -> Cli {
+  let url = server.uri();
+
+  Cli::from_iter_safe(
+    [
+      "netcup-dns-updater",
+      "--customer-number",
+      "12345",
+      "--api-key",
+      "key",
+      "--api-password",
+      "password",
+      "--api-url",
+      url.as_str(),
+    ]
+    .into_iter()
+    .chain(args.iter().copied()),
+  )
+  .unwrap()
+}
@@ -1,23 +1,35 @@
-use std::{marker::PhantomData, net::IpAddr, str::FromStr};
+use std::{fmt, marker::PhantomData, net::IpAddr, str::FromStr};
 
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
-use serde_with::DeserializeFromStr;
+use serde_with::{DeserializeFromStr, SerializeDisplay};
 
 use crate::{
   errors::Errors,
   serialization::{empty_string_as_none, opt_string_or_struct},
 };
 
-use super::{Action, Status, StatusCode};
+use super::{Action, ServerMessage, Status, StatusCode};
 
+pub mod ack_poll;
+pub mod create_handle;
+pub mod delete_handle;
 pub mod info_dns_records;
 pub mod info_dns_zone;
+pub mod info_domain;
+pub mod info_handle;
+pub mod listall_handle;
 pub mod login;
 pub mod logout;
+pub mod poll;
 pub mod update_dns_records;
 pub mod update_dns_zone;
+pub mod update_domain;
+pub mod update_handle;
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// The parameters which must never show up in logs or error reports.
+const CREDENTIALS: [&str; 3] = ["apikey", "apipassword", "apisessionid"];
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
 pub struct Request<T> {
   action: Action,
   param: T,
@@ -33,6 +45,43 @@ impl<T> Request<T> {
   }
 }
 
+/// The request as it is sent, with the credentials redacted.
+impl<T> fmt::Debug for Request<T>
+where
+  T: Serialize,
+{
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let mut value = serde_json::to_value(self).map_err(|_| fmt::Error)?;
+    redact(&mut value);
+    write!(f, "{value}")
+  }
+}
+
+/// A response `body` for the logs, with the credentials redacted.
+pub fn redacted(body: &str) -> String {
+  match serde_json::from_str::<serde_json::Value>(body) {
+    Ok(mut value) => {
+      redact(&mut value);
+      value.to_string()
+    }
+    Err(_) => body.to_string(),
+  }
+}
+
+fn redact(value: &mut serde_json::Value) {
+  match value {
+    serde_json::Value::Object(object) => object.iter_mut().for_each(|(key, value)| {
+      if CREDENTIALS.contains(&key.as_str()) {
+        *value = "[redacted]".into();
+      } else {
+        redact(value);
+      }
+    }),
+    serde_json::Value::Array(values) => values.iter_mut().for_each(redact),
+    _ => {}
+  }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Response<T>
 where
@@ -63,6 +112,10 @@ where
     self.status_code
   }
 
+  pub fn server_request_id(&self) -> &str {
+    &self.server_request_id
+  }
+
   // pub fn status(&self) -> Status {
   //   self.status
   // }
@@ -70,12 +123,18 @@ where
   pub fn response_data(&self) -> Option<&T> {
     self.response_data.as_ref()
   }
+
+  pub fn server_message(&self) -> ServerMessage {
+    ServerMessage::new(&self.short_message, self.long_message.clone())
+  }
 }
 
+#[derive(Debug, Clone)]
 pub struct NoApiSessionId;
+#[derive(Debug, Clone)]
 pub struct ApiSessionId;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SessionCredentials<T> {
   #[serde(rename = "customernumber")]
   customer_number: u32,
@@ -135,13 +194,23 @@ impl<T> SessionCredentials<T> {
 }
 
 #[allow(clippy::upper_case_acronyms)]
-#[derive(Debug, Clone, Serialize, PartialEq, Eq, DeserializeFromStr)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, SerializeDisplay, DeserializeFromStr)]
 pub enum RecordType {
   A,
   AAAA,
   Other(String),
 }
 
+impl fmt::Display for RecordType {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::A => f.write_str("A"),
+      Self::AAAA => f.write_str("AAAA"),
+      Self::Other(record_type) => f.write_str(record_type),
+    }
+  }
+}
+
 impl FromStr for RecordType {
   type Err = Errors;
 
@@ -170,6 +239,15 @@ pub enum IpType {
   Other(String),
 }
 
+impl fmt::Display for IpType {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::Ip(ip) => write!(f, "{ip}"),
+      Self::Other(destination) => f.write_str(destination),
+    }
+  }
+}
+
 impl FromStr for IpType {
   type Err = Errors;
 
@@ -208,15 +286,62 @@ impl DnsRecord {
     }
   }
 
+  /// Creates a record of any type, e.g. a `TXT` record, whose destination is
+  /// not an address.
+  pub fn other(
+    host_name: impl Into<String>,
+    record_type: RecordType,
+    destination: impl Into<String>,
+  ) -> Self {
+    let destination = destination.into();
+    Self {
+      id: None,
+      host_name: host_name.into(),
+      _type: record_type,
+      priority: None,
+      destination: destination.parse().unwrap_or(IpType::Other(destination)),
+      delete_record: None,
+      state: None,
+    }
+  }
+
+  pub fn id(&self) -> Option<&str> {
+    self.id.as_deref()
+  }
+
+  pub fn id_mut(&mut self, id: impl Into<String>) {
+    self.id = Some(id.into())
+  }
+
   pub fn host_name(&self) -> &str {
     &self.host_name
   }
 
+  pub fn host_name_mut(&mut self, host_name: impl Into<String>) {
+    self.host_name = host_name.into()
+  }
+
   pub fn record_type(&self) -> &RecordType {
     &self._type
   }
 
-  // pub fn destination(&self) -> &IpType {
-  //   &self.destination
-  // }
+  pub fn priority(&self) -> Option<&str> {
+    self.priority.as_deref()
+  }
+
+  pub fn priority_mut(&mut self, priority: impl Into<String>) {
+    self.priority = Some(priority.into())
+  }
+
+  pub fn destination(&self) -> &IpType {
+    &self.destination
+  }
+
+  pub fn delete_record(&self) -> bool {
+    self.delete_record.unwrap_or_default()
+  }
+
+  pub fn delete_record_mut(&mut self, delete_record: bool) {
+    self.delete_record = Some(delete_record)
+  }
 }
@@ -0,0 +1,29 @@
+use serde::{Deserialize, Serialize};
+
+use super::{ApiSessionId, SessionCredentials};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ResponseData {}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Params {
+  #[serde(rename = "apollid")]
+  poll_id: u32,
+  #[serde(rename = "customernumber")]
+  customer_number: u32,
+  #[serde(rename = "apikey")]
+  api_key: String,
+  #[serde(rename = "apisessionid")]
+  api_session_id: String,
+}
+
+impl Params {
+  pub fn new(poll_id: u32, session_credentials: &SessionCredentials<ApiSessionId>) -> Self {
+    Self {
+      poll_id,
+      customer_number: session_credentials.customer_number(),
+      api_key: session_credentials.api_key().into(),
+      api_session_id: session_credentials.api_session_id().into(),
+    }
+  }
+}
@@ -0,0 +1,28 @@
+use serde::{Deserialize, Serialize};
+
+use super::{info_handle::HandleData, ApiSessionId, SessionCredentials};
+
+pub use super::info_handle::ResponseData;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Params {
+  #[serde(rename = "customernumber")]
+  customer_number: u32,
+  #[serde(rename = "apikey")]
+  api_key: String,
+  #[serde(rename = "apisessionid")]
+  api_session_id: String,
+  #[serde(flatten)]
+  handle: HandleData,
+}
+
+impl Params {
+  pub fn new(session_credentials: &SessionCredentials<ApiSessionId>, handle: HandleData) -> Self {
+    Self {
+      customer_number: session_credentials.customer_number(),
+      api_key: session_credentials.api_key().into(),
+      api_session_id: session_credentials.api_session_id().into(),
+      handle,
+    }
+  }
+}
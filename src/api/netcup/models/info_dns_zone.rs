@@ -1,8 +1,17 @@
+use std::{fmt, ops::RangeInclusive};
+
 use serde::{Deserialize, Serialize};
 use serde_aux::prelude::deserialize_number_from_string;
 
+use crate::errors::Errors;
+
 use super::{ApiSessionId, SessionCredentials};
 
+pub const TTL_LIMITS: RangeInclusive<u32> = 300..=2_592_000;
+pub const REFRESH_LIMITS: RangeInclusive<u32> = 3_600..=2_592_000;
+pub const RETRY_LIMITS: RangeInclusive<u32> = 600..=2_592_000;
+pub const EXPIRE_LIMITS: RangeInclusive<u32> = 604_800..=4_838_400;
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct ResponseData {
   name: String,
@@ -21,6 +30,38 @@ pub struct ResponseData {
 }
 
 impl ResponseData {
+  pub fn new(
+    name: impl Into<String>,
+    ttl: u32,
+    serial: u32,
+    refresh: u32,
+    retry: u32,
+    expire: u32,
+    dns_sec_status: bool,
+  ) -> Self {
+    Self {
+      name: name.into(),
+      ttl,
+      serial,
+      refresh,
+      retry,
+      expire,
+      dns_sec_status,
+    }
+  }
+
+  pub fn refresh(&self) -> u32 {
+    self.refresh
+  }
+
+  pub fn retry(&self) -> u32 {
+    self.retry
+  }
+
+  pub fn expire(&self) -> u32 {
+    self.expire
+  }
+
   pub fn ttl(&self) -> u32 {
     self.ttl
   }
@@ -28,6 +69,98 @@ impl ResponseData {
   pub fn ttl_mut(&mut self, ttl: u32) {
     self.ttl = ttl
   }
+
+  pub fn refresh_mut(&mut self, refresh: u32) {
+    self.refresh = refresh
+  }
+
+  pub fn retry_mut(&mut self, retry: u32) {
+    self.retry = retry
+  }
+
+  pub fn expire_mut(&mut self, expire: u32) {
+    self.expire = expire
+  }
+
+  pub fn dns_sec_status(&self) -> bool {
+    self.dns_sec_status
+  }
+
+  pub fn dns_sec_status_mut(&mut self, dns_sec_status: bool) {
+    self.dns_sec_status = dns_sec_status
+  }
+
+  /// Checks only the TTL, for changes which leave the SOA timers as they are.
+  pub fn validate_ttl(&self) -> Result<(), Errors> {
+    within("ttl", self.ttl, TTL_LIMITS)
+  }
+
+  /// Checks the SOA timers against the ranges Netcup accepts, so invalid
+  /// settings are rejected before they are sent.
+  pub fn validate(&self) -> Result<(), Errors> {
+    self.validate_ttl()?;
+    within("refresh", self.refresh, REFRESH_LIMITS)?;
+    within("retry", self.retry, RETRY_LIMITS)?;
+    within("expire", self.expire, EXPIRE_LIMITS)?;
+
+    if self.retry >= self.refresh {
+      return Err(Errors::InvalidZoneSetting(format!(
+        "retry {} has to be lower than refresh {}",
+        self.retry, self.refresh
+      )));
+    }
+
+    if self.expire <= self.refresh + self.retry {
+      return Err(Errors::InvalidZoneSetting(format!(
+        "expire {} has to be greater than refresh {} and retry {} combined",
+        self.expire, self.refresh, self.retry
+      )));
+    }
+
+    Ok(())
+  }
+
+  /// Lists every setting as `(name, before, after)`.
+  pub fn diff(&self, other: &Self) -> Vec<(&'static str, String, String)> {
+    vec![
+      ("ttl", self.ttl.to_string(), other.ttl.to_string()),
+      (
+        "refresh",
+        self.refresh.to_string(),
+        other.refresh.to_string(),
+      ),
+      ("retry", self.retry.to_string(), other.retry.to_string()),
+      ("expire", self.expire.to_string(), other.expire.to_string()),
+      (
+        "dnssec",
+        self.dns_sec_status.to_string(),
+        other.dns_sec_status.to_string(),
+      ),
+    ]
+  }
+}
+
+fn within(name: &str, value: u32, limit: RangeInclusive<u32>) -> Result<(), Errors> {
+  if limit.contains(&value) {
+    return Ok(());
+  }
+
+  Err(Errors::InvalidZoneSetting(format!(
+    "{name} {value} is not within {}..={}",
+    limit.start(),
+    limit.end()
+  )))
+}
+
+/// Every setting on a line of its own.
+impl fmt::Display for ResponseData {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    writeln!(f, "ttl: {}", self.ttl)?;
+    writeln!(f, "refresh: {}", self.refresh)?;
+    writeln!(f, "retry: {}", self.retry)?;
+    writeln!(f, "expire: {}", self.expire)?;
+    writeln!(f, "dnssec: {}", self.dns_sec_status)
+  }
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -133,6 +266,43 @@ mod test {
     Ok(())
   }
 
+  #[test]
+  fn validate_zone_settings() -> error_stack::Result<(), Errors> {
+    let mut zone = serde_json::from_str::<Response<ResponseData>>(SUCCESSFUL_REQUEST)
+      .into_report()
+      .change_context(Errors::SerializeResponse)?
+      .response_data
+      .unwrap();
+
+    assert!(zone.validate().is_ok());
+
+    zone.ttl_mut(60);
+    assert!(zone.validate().is_err());
+    zone.ttl_mut(300);
+    assert!(zone.validate().is_ok());
+
+    zone.retry_mut(28800);
+    assert!(zone.validate().is_err());
+    zone.retry_mut(7200);
+
+    zone.expire_mut(EXPIRE_LIMITS.end() + 1);
+    assert!(zone.validate().is_err());
+    assert!(zone.validate_ttl().is_ok());
+    zone.ttl_mut(60);
+    assert!(zone.validate_ttl().is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn display_zone_settings() {
+    let zone = ResponseData::new("example.com", 300, 1, 28800, 7200, 1209600, false);
+    assert_eq!(
+      "ttl: 300\nrefresh: 28800\nretry: 7200\nexpire: 1209600\ndnssec: false\n",
+      zone.to_string()
+    );
+  }
+
   #[test]
   fn serialize_invalid_api_key() -> error_stack::Result<(), Errors> {
     let ser = serde_json::from_str::<Response<ResponseData>>(INVALID_API_KEY)
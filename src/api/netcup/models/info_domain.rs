@@ -0,0 +1,333 @@
+use std::{
+  collections::BTreeMap,
+  fmt,
+  net::{Ipv4Addr, Ipv6Addr},
+  str::FromStr,
+};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{errors::Errors, serialization::empty_string_as_none};
+
+use super::{ApiSessionId, SessionCredentials};
+
+pub const NETCUP_NAMESERVER_SUFFIX: &str = ".netcup.net";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct NameserverEntry {
+  #[serde(rename = "hostname")]
+  host_name: String,
+  #[serde(
+    default,
+    deserialize_with = "empty_string_as_none",
+    skip_serializing_if = "Option::is_none"
+  )]
+  ipv4: Option<Ipv4Addr>,
+  #[serde(
+    default,
+    deserialize_with = "empty_string_as_none",
+    skip_serializing_if = "Option::is_none"
+  )]
+  ipv6: Option<Ipv6Addr>,
+}
+
+impl NameserverEntry {
+  pub fn new(host_name: impl Into<String>) -> Self {
+    Self {
+      host_name: host_name.into(),
+      ipv4: None,
+      ipv6: None,
+    }
+  }
+
+  pub fn is_netcup(&self) -> bool {
+    self
+      .host_name
+      .trim_end_matches('.')
+      .to_lowercase()
+      .ends_with(NETCUP_NAMESERVER_SUFFIX)
+  }
+}
+
+impl fmt::Display for NameserverEntry {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}", self.host_name)?;
+
+    let glue = self
+      .ipv4
+      .map(|ip| ip.to_string())
+      .into_iter()
+      .chain(self.ipv6.map(|ip| ip.to_string()))
+      .collect::<Vec<_>>();
+
+    if !glue.is_empty() {
+      write!(f, "={}", glue.join(","))?;
+    }
+
+    Ok(())
+  }
+}
+
+/// Parses `hostname` or `hostname=ip[,ip]` where the optional addresses are
+/// used as glue records.
+impl FromStr for NameserverEntry {
+  type Err = Errors;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let (host_name, glue) = match s.split_once('=') {
+      Some((host_name, glue)) => (host_name.trim(), Some(glue)),
+      None => (s.trim(), None),
+    };
+
+    if host_name.is_empty() {
+      return Err(Errors::ParseNameserver(s.to_string()));
+    }
+
+    let mut entry = Self::new(host_name);
+
+    for ip in glue.into_iter().flat_map(|glue| glue.split(',')) {
+      match ip.trim().parse() {
+        Ok(std::net::IpAddr::V4(ip)) if entry.ipv4.is_none() => entry.ipv4 = Some(ip),
+        Ok(std::net::IpAddr::V6(ip)) if entry.ipv6.is_none() => entry.ipv6 = Some(ip),
+        _ => return Err(Errors::ParseNameserver(s.to_string())),
+      }
+    }
+
+    Ok(entry)
+  }
+}
+
+/// Netcup expects the nameservers as an object with the keys `nameserver1`
+/// up to `nameserver8` instead of a list.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NameserverEntries(Vec<NameserverEntry>);
+
+impl NameserverEntries {
+  pub fn new(nameservers: Vec<NameserverEntry>) -> Self {
+    Self(nameservers)
+  }
+
+  pub fn nameservers(&self) -> &Vec<NameserverEntry> {
+    &self.0
+  }
+
+  pub fn is_netcup(&self) -> bool {
+    !self.0.is_empty() && self.0.iter().all(NameserverEntry::is_netcup)
+  }
+}
+
+impl Serialize for NameserverEntries {
+  fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+  where
+    S: Serializer,
+  {
+    serializer.collect_map(
+      self
+        .0
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| (format!("nameserver{}", i + 1), entry)),
+    )
+  }
+}
+
+impl<'de> Deserialize<'de> for NameserverEntries {
+  fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+  where
+    D: Deserializer<'de>,
+  {
+    let entries = BTreeMap::<String, Option<NameserverEntry>>::deserialize(deserializer)?;
+
+    let mut entries = entries
+      .into_iter()
+      .filter_map(|(key, entry)| {
+        let index = key.strip_prefix("nameserver")?.parse::<usize>().ok()?;
+        entry
+          .filter(|entry| !entry.host_name.is_empty())
+          .map(|entry| (index, entry))
+      })
+      .collect::<Vec<_>>();
+    entries.sort_by_key(|(index, _)| *index);
+
+    Ok(Self(entries.into_iter().map(|(_, entry)| entry).collect()))
+  }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ContactEntries {
+  #[serde(skip_serializing_if = "Option::is_none")]
+  ownerc: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  adminc: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  techc: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  zonec: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  billingc: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  onsitec: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  generalrequest: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  abusecontact: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ResponseData {
+  #[serde(rename = "domainname")]
+  domain_name: String,
+  #[serde(rename = "nameserverentry", default)]
+  nameservers: NameserverEntries,
+  #[serde(rename = "assignedcontacts", default)]
+  contacts: ContactEntries,
+}
+
+impl ResponseData {
+  pub fn nameservers(&self) -> &NameserverEntries {
+    &self.nameservers
+  }
+
+  pub fn contacts(&self) -> &ContactEntries {
+    &self.contacts
+  }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Params {
+  #[serde(rename = "domainname")]
+  domain_name: String,
+  #[serde(rename = "customernumber")]
+  customer_number: u32,
+  #[serde(rename = "apikey")]
+  api_key: String,
+  #[serde(rename = "apisessionid")]
+  api_session_id: String,
+}
+
+impl Params {
+  pub fn new(
+    domain_name: impl Into<String>,
+    session_credentials: &SessionCredentials<ApiSessionId>,
+  ) -> Self {
+    Self {
+      domain_name: domain_name.into(),
+      customer_number: session_credentials.customer_number(),
+      api_key: session_credentials.api_key().into(),
+      api_session_id: session_credentials.api_session_id().into(),
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use error_stack::{IntoReport, ResultExt};
+
+  use super::*;
+  use crate::{
+    api::netcup::{Action, Response, Status, StatusCode},
+    errors::Errors,
+  };
+
+  const SUCCESSFUL_REQUEST: &str = r#"{
+  "serverrequestid": "SUPERSECRETSERVERREQUESTID",
+  "clientrequestid": "",
+  "action": "infoDomain",
+  "status": "success",
+  "statuscode": 2000,
+  "shortmessage": "Domain found",
+  "longmessage": "Domain information was found.",
+  "responsedata": {
+    "domainname": "example.domain",
+    "nameserverentry": {
+      "nameserver2": { "hostname": "ns2.example.domain", "ipv4": "", "ipv6": "" },
+      "nameserver1": { "hostname": "ns1.example.domain", "ipv4": "192.0.2.1", "ipv6": "2001:db8::1" },
+      "nameserver3": null
+    },
+    "assignedcontacts": {
+      "ownerc": "1234",
+      "adminc": "1234",
+      "techc": "5678"
+    }
+  }
+}"#;
+
+  #[test]
+  fn serialize_successful_request() -> error_stack::Result<(), Errors> {
+    let ser = serde_json::from_str::<Response<ResponseData>>(SUCCESSFUL_REQUEST)
+      .into_report()
+      .change_context(Errors::SerializeResponse)?;
+
+    assert_eq!(Some(Action::InfoDomain), ser.action);
+    assert_eq!(Status::Success, ser.status);
+    assert_eq!(StatusCode::Success, ser.status_code);
+
+    let response_data = ser.response_data.unwrap();
+    assert_eq!("example.domain", response_data.domain_name);
+    assert_eq!(
+      &vec![
+        NameserverEntry {
+          host_name: "ns1.example.domain".to_string(),
+          ipv4: Some(Ipv4Addr::new(192, 0, 2, 1)),
+          ipv6: Some("2001:db8::1".parse().unwrap()),
+        },
+        NameserverEntry::new("ns2.example.domain"),
+      ],
+      response_data.nameservers().nameservers()
+    );
+    assert_eq!(Some("5678".to_string()), response_data.contacts().techc);
+    assert_eq!(None, response_data.contacts().zonec);
+
+    Ok(())
+  }
+
+  #[test]
+  fn serialize_nameserver_entries() {
+    let entries = NameserverEntries::new(vec![
+      NameserverEntry::new("root-dns.netcup.net"),
+      "ns.example.domain=192.0.2.1".parse().unwrap(),
+    ]);
+
+    assert_eq!(
+      serde_json::json!({
+        "nameserver1": { "hostname": "root-dns.netcup.net" },
+        "nameserver2": { "hostname": "ns.example.domain", "ipv4": "192.0.2.1" },
+      }),
+      serde_json::to_value(&entries).unwrap()
+    );
+  }
+
+  #[test]
+  fn parse_nameserver_entry() {
+    assert_eq!(
+      Some("ns1.example.domain=192.0.2.1,2001:db8::1".to_string()),
+      "ns1.example.domain = 192.0.2.1, 2001:db8::1"
+        .parse::<NameserverEntry>()
+        .ok()
+        .map(|entry| entry.to_string())
+    );
+    assert!("=192.0.2.1".parse::<NameserverEntry>().is_err());
+    assert!("ns1.example.domain=192.0.2.1,192.0.2.2"
+      .parse::<NameserverEntry>()
+      .is_err());
+    assert!("ns1.example.domain=not-an-ip"
+      .parse::<NameserverEntry>()
+      .is_err());
+  }
+
+  #[test]
+  fn detect_netcup_nameservers() {
+    let netcup = NameserverEntries::new(vec![
+      NameserverEntry::new("root-dns.netcup.net"),
+      NameserverEntry::new("SECOND-DNS.netcup.net."),
+    ]);
+    let mixed = NameserverEntries::new(vec![
+      NameserverEntry::new("root-dns.netcup.net"),
+      NameserverEntry::new("ns.example.domain"),
+    ]);
+
+    assert!(netcup.is_netcup());
+    assert!(!mixed.is_netcup());
+    assert!(!NameserverEntries::default().is_netcup());
+  }
+}
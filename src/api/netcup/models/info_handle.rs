@@ -0,0 +1,249 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+use serde_aux::prelude::deserialize_number_from_string;
+
+use crate::{cli::HandleArgs, errors::Errors};
+
+use super::{ApiSessionId, SessionCredentials};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum HandleType {
+  Person,
+  Organisation,
+}
+
+impl FromStr for HandleType {
+  type Err = Errors;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.trim().to_lowercase().as_str() {
+      "person" => Ok(Self::Person),
+      "organisation" | "organization" => Ok(Self::Organisation),
+      _ => Err(Errors::ParseHandleType(s.to_string())),
+    }
+  }
+}
+
+impl fmt::Display for HandleType {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::Person => write!(f, "person"),
+      Self::Organisation => write!(f, "organisation"),
+    }
+  }
+}
+
+/// The contact data of a handle. All fields are optional so the same type can
+/// be used to partially update an existing handle.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct HandleData {
+  #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+  handle_type: Option<HandleType>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  name: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  organisation: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  street: Option<String>,
+  #[serde(rename = "postalcode", skip_serializing_if = "Option::is_none")]
+  postal_code: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  city: Option<String>,
+  #[serde(rename = "countrycode", skip_serializing_if = "Option::is_none")]
+  country_code: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  telephone: Option<String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  email: Option<String>,
+}
+
+impl HandleData {
+  pub fn name(&self) -> Option<&str> {
+    self.name.as_deref()
+  }
+
+  pub fn email(&self) -> Option<&str> {
+    self.email.as_deref()
+  }
+
+  pub fn handle_type(&self) -> Option<HandleType> {
+    self.handle_type
+  }
+
+  /// Overwrites every field which is set in `other`.
+  pub fn merge(self, other: HandleData) -> Self {
+    Self {
+      handle_type: other.handle_type.or(self.handle_type),
+      name: other.name.or(self.name),
+      organisation: other.organisation.or(self.organisation),
+      street: other.street.or(self.street),
+      postal_code: other.postal_code.or(self.postal_code),
+      city: other.city.or(self.city),
+      country_code: other.country_code.or(self.country_code),
+      telephone: other.telephone.or(self.telephone),
+      email: other.email.or(self.email),
+    }
+  }
+
+  /// The fields Netcup requires to create a handle which are not set.
+  pub fn missing_fields(&self) -> Vec<&'static str> {
+    [
+      ("type", self.handle_type.is_none()),
+      ("name", self.name.is_none()),
+      (
+        "organisation",
+        self.handle_type == Some(HandleType::Organisation) && self.organisation.is_none(),
+      ),
+      ("street", self.street.is_none()),
+      ("postal-code", self.postal_code.is_none()),
+      ("city", self.city.is_none()),
+      ("country-code", self.country_code.is_none()),
+      ("telephone", self.telephone.is_none()),
+      ("email", self.email.is_none()),
+    ]
+    .into_iter()
+    .filter_map(|(field, missing)| missing.then_some(field))
+    .collect()
+  }
+}
+
+impl From<&HandleArgs> for HandleData {
+  fn from(args: &HandleArgs) -> Self {
+    Self {
+      handle_type: args.handle_type(),
+      name: args.name().map(Into::into),
+      organisation: args.organisation().map(Into::into),
+      street: args.street().map(Into::into),
+      postal_code: args.postal_code().map(Into::into),
+      city: args.city().map(Into::into),
+      country_code: args.country_code().map(Into::into),
+      telephone: args.telephone().map(Into::into),
+      email: args.email().map(Into::into),
+    }
+  }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Handle {
+  #[serde(deserialize_with = "deserialize_number_from_string")]
+  id: u32,
+  #[serde(flatten)]
+  data: HandleData,
+  #[serde(rename = "assignedtodomain", skip_serializing_if = "Option::is_none")]
+  assigned_to_domain: Option<bool>,
+}
+
+impl Handle {
+  pub fn id(&self) -> u32 {
+    self.id
+  }
+
+  pub fn data(&self) -> &HandleData {
+    &self.data
+  }
+}
+
+pub type ResponseData = Handle;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Params {
+  #[serde(rename = "handle_id")]
+  handle_id: u32,
+  #[serde(rename = "customernumber")]
+  customer_number: u32,
+  #[serde(rename = "apikey")]
+  api_key: String,
+  #[serde(rename = "apisessionid")]
+  api_session_id: String,
+}
+
+impl Params {
+  pub fn new(handle_id: u32, session_credentials: &SessionCredentials<ApiSessionId>) -> Self {
+    Self {
+      handle_id,
+      customer_number: session_credentials.customer_number(),
+      api_key: session_credentials.api_key().into(),
+      api_session_id: session_credentials.api_session_id().into(),
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use error_stack::{IntoReport, ResultExt};
+
+  use super::*;
+  use crate::{
+    api::netcup::{Action, Response, Status, StatusCode},
+    errors::Errors,
+  };
+
+  const SUCCESSFUL_REQUEST: &str = r#"{
+  "serverrequestid": "SUPERSECRETSERVERREQUESTID",
+  "clientrequestid": "",
+  "action": "infoHandle",
+  "status": "success",
+  "statuscode": 2000,
+  "shortmessage": "Handle found",
+  "longmessage": "Handle was found.",
+  "responsedata": {
+    "id": "12345",
+    "type": "organisation",
+    "name": "Max Mustermann",
+    "organisation": "Example GmbH",
+    "street": "Musterstraße 1",
+    "postalcode": "12345",
+    "city": "Musterstadt",
+    "countrycode": "DE",
+    "telephone": "+49.123456789",
+    "email": "max@example.domain",
+    "assignedtodomain": true
+  }
+}"#;
+
+  #[test]
+  fn serialize_successful_request() -> error_stack::Result<(), Errors> {
+    let ser = serde_json::from_str::<Response<ResponseData>>(SUCCESSFUL_REQUEST)
+      .into_report()
+      .change_context(Errors::SerializeResponse)?;
+
+    assert_eq!(Some(Action::InfoHandle), ser.action);
+    assert_eq!(Status::Success, ser.status);
+    assert_eq!(StatusCode::Success, ser.status_code);
+
+    let handle = ser.response_data.unwrap();
+    assert_eq!(12345, handle.id());
+    assert_eq!(Some(HandleType::Organisation), handle.data().handle_type());
+    assert_eq!(Some("Max Mustermann"), handle.data().name());
+    assert_eq!(Some("DE"), handle.data().country_code.as_deref());
+    assert_eq!(Some(true), handle.assigned_to_domain);
+    assert!(handle.data().missing_fields().is_empty());
+
+    Ok(())
+  }
+
+  #[test]
+  fn merge_handle_data() {
+    let existing = HandleData {
+      handle_type: Some(HandleType::Person),
+      name: Some("Max Mustermann".to_string()),
+      email: Some("max@example.domain".to_string()),
+      ..Default::default()
+    };
+    let update = HandleData {
+      email: Some("info@example.domain".to_string()),
+      ..Default::default()
+    };
+
+    let merged = existing.merge(update);
+
+    assert_eq!(Some("Max Mustermann"), merged.name());
+    assert_eq!(Some("info@example.domain"), merged.email());
+    assert_eq!(
+      vec!["street", "postal-code", "city", "country-code", "telephone"],
+      merged.missing_fields()
+    );
+  }
+}
@@ -0,0 +1,71 @@
+use serde::{Deserialize, Serialize};
+
+use super::{info_handle::Handle, ApiSessionId, SessionCredentials};
+
+pub type ResponseData = Vec<Handle>;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Params {
+  #[serde(rename = "customernumber")]
+  customer_number: u32,
+  #[serde(rename = "apikey")]
+  api_key: String,
+  #[serde(rename = "apisessionid")]
+  api_session_id: String,
+}
+
+impl Params {
+  pub fn new(session_credentials: &SessionCredentials<ApiSessionId>) -> Self {
+    Self {
+      customer_number: session_credentials.customer_number(),
+      api_key: session_credentials.api_key().into(),
+      api_session_id: session_credentials.api_session_id().into(),
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use error_stack::{IntoReport, ResultExt};
+
+  use super::*;
+  use crate::{
+    api::netcup::{Action, Response, StatusCode},
+    errors::Errors,
+  };
+
+  const SUCCESSFUL_REQUEST: &str = r#"{
+  "serverrequestid": "SUPERSECRETSERVERREQUESTID",
+  "clientrequestid": "",
+  "action": "listallHandle",
+  "status": "success",
+  "statuscode": 2000,
+  "shortmessage": "Handles found",
+  "longmessage": "Handles were found.",
+  "responsedata": [
+    { "id": "1", "type": "person", "name": "Max Mustermann", "email": "max@example.domain" },
+    { "id": 2, "type": "organisation", "name": "Erika Musterfrau", "organisation": "Example GmbH" }
+  ]
+}"#;
+
+  #[test]
+  fn serialize_successful_request() -> error_stack::Result<(), Errors> {
+    let ser = serde_json::from_str::<Response<ResponseData>>(SUCCESSFUL_REQUEST)
+      .into_report()
+      .change_context(Errors::SerializeResponse)?;
+
+    assert_eq!(Some(Action::ListallHandle), ser.action);
+    assert_eq!(StatusCode::Success, ser.status_code);
+    assert_eq!(
+      vec![1, 2],
+      ser
+        .response_data
+        .unwrap()
+        .iter()
+        .map(Handle::id)
+        .collect::<Vec<_>>()
+    );
+
+    Ok(())
+  }
+}
@@ -42,7 +42,7 @@ mod tests {
   use error_stack::{IntoReport, Result, ResultExt};
 
   use crate::{
-    api::netcup::{Action, Response, Status, StatusCode},
+    api::netcup::{models::Request, Action, Response, Status, StatusCode},
     errors::Errors,
   };
 
@@ -115,4 +115,20 @@ mod tests {
 
     Ok(())
   }
+
+  #[test]
+  fn redact_credentials() {
+    let request = Request::new(
+      Action::Login,
+      Params::new(12345, "SUPERSECRETAPIKEY", "SUPERSECRETAPIPASSWORD"),
+    );
+    let debug = format!("{request:?}");
+
+    assert!(debug.contains("12345"), "{debug}");
+    assert!(!debug.contains("SUPERSECRET"), "{debug}");
+
+    let body = crate::api::netcup::models::redacted(SUCCESSFUL_LOGIN);
+    assert!(body.contains("SUPERSECRETSERVERREQUESTID"), "{body}");
+    assert!(!body.contains("SUPERSECRETAPISESSIONID"), "{body}");
+  }
 }
@@ -0,0 +1,123 @@
+use serde::{Deserialize, Serialize};
+use serde_aux::prelude::deserialize_number_from_string;
+
+use super::{ApiSessionId, SessionCredentials};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PollMessage {
+  #[serde(deserialize_with = "deserialize_number_from_string")]
+  id: u32,
+  #[serde(default)]
+  message: String,
+  #[serde(
+    rename = "domainname",
+    default,
+    skip_serializing_if = "Option::is_none"
+  )]
+  domain_name: Option<String>,
+  #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+  message_type: Option<String>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  date: Option<String>,
+}
+
+impl PollMessage {
+  pub fn id(&self) -> u32 {
+    self.id
+  }
+
+  pub fn message(&self) -> &str {
+    &self.message
+  }
+}
+
+pub type ResponseData = Vec<PollMessage>;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Params {
+  #[serde(rename = "messagecount")]
+  message_count: u32,
+  #[serde(rename = "customernumber")]
+  customer_number: u32,
+  #[serde(rename = "apikey")]
+  api_key: String,
+  #[serde(rename = "apisessionid")]
+  api_session_id: String,
+}
+
+impl Params {
+  pub fn new(message_count: u32, session_credentials: &SessionCredentials<ApiSessionId>) -> Self {
+    Self {
+      message_count,
+      customer_number: session_credentials.customer_number(),
+      api_key: session_credentials.api_key().into(),
+      api_session_id: session_credentials.api_session_id().into(),
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use error_stack::{IntoReport, ResultExt};
+
+  use super::*;
+  use crate::{
+    api::netcup::{Action, Response, StatusCode},
+    errors::Errors,
+  };
+
+  const SUCCESSFUL_REQUEST: &str = r#"{
+  "serverrequestid": "SUPERSECRETSERVERREQUESTID",
+  "clientrequestid": "",
+  "action": "poll",
+  "status": "success",
+  "statuscode": 2000,
+  "shortmessage": "Messages found",
+  "longmessage": "Unread messages were found.",
+  "responsedata": [
+    { "id": "42", "message": "Transfer of example.domain completed.", "domainname": "example.domain" },
+    { "id": 43, "message": "Renewal failed." }
+  ]
+}"#;
+
+  const NO_MESSAGES: &str = r#"{
+  "serverrequestid": "SUPERSECRETSERVERREQUESTID",
+  "clientrequestid": "",
+  "action": "poll",
+  "status": "success",
+  "statuscode": 2000,
+  "shortmessage": "No messages found",
+  "longmessage": "There are no unread messages.",
+  "responsedata": ""
+}"#;
+
+  #[test]
+  fn serialize_successful_request() -> error_stack::Result<(), Errors> {
+    let ser = serde_json::from_str::<Response<ResponseData>>(SUCCESSFUL_REQUEST)
+      .into_report()
+      .change_context(Errors::SerializeResponse)?;
+
+    assert_eq!(Some(Action::Poll), ser.action);
+    assert_eq!(StatusCode::Success, ser.status_code);
+
+    let messages = ser.response_data.unwrap();
+    assert_eq!(2, messages.len());
+    assert_eq!(42, messages[0].id());
+    assert_eq!(Some("example.domain".to_string()), messages[0].domain_name);
+    assert_eq!("Renewal failed.", messages[1].message());
+    assert_eq!(None, messages[1].domain_name);
+
+    Ok(())
+  }
+
+  #[test]
+  fn serialize_no_messages() -> error_stack::Result<(), Errors> {
+    let ser = serde_json::from_str::<Response<ResponseData>>(NO_MESSAGES)
+      .into_report()
+      .change_context(Errors::SerializeResponse)?;
+
+    assert_eq!(None, ser.response_data);
+
+    Ok(())
+  }
+}
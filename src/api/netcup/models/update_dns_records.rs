@@ -3,7 +3,16 @@ use serde::{Deserialize, Serialize};
 use super::{ApiSessionId, DnsRecord, SessionCredentials};
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
-pub struct ResponseData {}
+pub struct ResponseData {
+  #[serde(rename = "dnsrecords", default)]
+  dns_records: Vec<DnsRecord>,
+}
+
+impl ResponseData {
+  pub fn dns_records(&self) -> &Vec<DnsRecord> {
+    &self.dns_records
+  }
+}
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 
@@ -0,0 +1,44 @@
+use serde::{Deserialize, Serialize};
+
+use super::{
+  info_domain::{ContactEntries, NameserverEntries},
+  ApiSessionId, SessionCredentials,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ResponseData {}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Params {
+  #[serde(rename = "domainname")]
+  domain_name: String,
+  #[serde(rename = "customernumber")]
+  customer_number: u32,
+  #[serde(rename = "apikey")]
+  api_key: String,
+  #[serde(rename = "apisessionid")]
+  api_session_id: String,
+  contacts: ContactEntries,
+  nameservers: NameserverEntries,
+  #[serde(rename = "keepdnssecrecords")]
+  keep_dns_sec_records: bool,
+}
+
+impl Params {
+  pub fn new(
+    domain_name: impl Into<String>,
+    session_credentials: &SessionCredentials<ApiSessionId>,
+    contacts: ContactEntries,
+    nameservers: NameserverEntries,
+  ) -> Self {
+    Self {
+      domain_name: domain_name.into(),
+      customer_number: session_credentials.customer_number(),
+      api_key: session_credentials.api_key().into(),
+      api_session_id: session_credentials.api_session_id().into(),
+      contacts,
+      nameservers,
+      keep_dns_sec_records: true,
+    }
+  }
+}
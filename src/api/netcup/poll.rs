@@ -0,0 +1,29 @@
+use error_stack::ResultExt;
+
+use crate::{api, errors::Errors};
+
+use super::{
+  models::{
+    poll::{Params, ResponseData},
+    ApiSessionId, Request, Response,
+  },
+  Action, Client,
+};
+
+impl Client<ApiSessionId> {
+  pub async fn poll(
+    &self,
+    message_count: u32,
+  ) -> error_stack::Result<Response<ResponseData>, Errors> {
+    api::netcup::request::<Params, ResponseData>(
+      &self.api_url,
+      &self.client,
+      &Request::new(
+        Action::Poll,
+        Params::new(message_count, &self.session_credentials),
+      ),
+    )
+    .await
+    .change_context(Errors::Poll)
+  }
+}
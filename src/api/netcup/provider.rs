@@ -0,0 +1,76 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use error_stack::Report;
+use tokio::time::sleep;
+
+use crate::{
+  errors::Errors,
+  provider::{Applied, DnsProvider, ZoneSettings},
+};
+
+use super::{
+  models::{ApiSessionId, DnsRecord},
+  Client, StatusCode,
+};
+
+#[async_trait]
+impl DnsProvider for Client<ApiSessionId> {
+  fn name(&self) -> &'static str {
+    "netcup"
+  }
+
+  async fn records(&self, zone: &str) -> error_stack::Result<Vec<DnsRecord>, Errors> {
+    self
+      .info_dns_records(zone)
+      .await?
+      .response_data()
+      .map(|data| data.dns_records().clone())
+      .ok_or_else(|| Report::new(Errors::DNSZoneNotFound(zone.to_string())))
+  }
+
+  async fn apply(
+    &self,
+    zone: &str,
+    changes: Vec<DnsRecord>,
+  ) -> error_stack::Result<Applied, Errors> {
+    let response = self.update_dns_records(zone, changes).await?;
+
+    Ok(Applied::new(
+      Some(response.server_request_id().to_string()),
+      response
+        .response_data()
+        .map(|data| data.dns_records().clone())
+        .unwrap_or_default(),
+    ))
+  }
+
+  async fn zone_settings(&self, zone: &str) -> error_stack::Result<ZoneSettings, Errors> {
+    self
+      .info_dns_zone(zone)
+      .await?
+      .response_data()
+      .cloned()
+      .ok_or_else(|| Report::new(Errors::DNSZoneNotFound(zone.to_string())))
+  }
+
+  async fn update_zone_settings(
+    &self,
+    zone: &str,
+    settings: ZoneSettings,
+  ) -> error_stack::Result<(), Errors> {
+    let update_dns_zone_response = self.update_dns_zone(zone, settings).await?;
+
+    if update_dns_zone_response.status_code() != StatusCode::Success {
+      return Err(Report::new(Errors::UpdateDNSZone(zone.to_string())));
+    }
+
+    Ok(())
+  }
+
+  async fn close(self: Box<Self>) -> error_stack::Result<(), Errors> {
+    sleep(Duration::from_secs(2)).await;
+
+    self.logout().await
+  }
+}
@@ -0,0 +1,81 @@
+use error_stack::{Report, ResultExt};
+use serde_json::Value;
+
+use crate::{api, errors::Errors};
+
+use super::{
+  models::{ApiSessionId, Request, Response},
+  Action, Client,
+};
+
+impl Client<ApiSessionId> {
+  /// Performs an action the client has no typed models for. The session
+  /// fields are added to `params`, which therefore has to be a JSON object.
+  /// The response is returned whatever its status, so failures can be
+  /// inspected as well.
+  pub async fn call_raw(
+    &self,
+    action: &str,
+    params: Value,
+  ) -> error_stack::Result<Response<Value>, Errors> {
+    let mut params = match params {
+      Value::Object(params) => params,
+      _ => return Err(Report::new(Errors::RawParams)),
+    };
+
+    params.insert(
+      "customernumber".into(),
+      self.session_credentials.customer_number().into(),
+    );
+    params.insert("apikey".into(), self.session_credentials.api_key().into());
+    params.insert(
+      "apisessionid".into(),
+      self.session_credentials.api_session_id().into(),
+    );
+
+    api::netcup::request_unchecked::<Value, Value>(
+      &self.api_url,
+      &self.client,
+      &Request::new(action.parse::<Action>()?, Value::Object(params)),
+    )
+    .await
+    .change_context(Errors::CallRaw(action.to_string()))
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use serde_json::json;
+  use wiremock::ResponseTemplate;
+
+  use crate::api::netcup::{mock, StatusCode};
+
+  use super::*;
+
+  #[tokio::test]
+  async fn return_failed_responses() {
+    let netcup = mock::server().await;
+    let cli = mock::cli(&netcup, &[]);
+
+    mock::action("listallDomains", json!({}))
+      .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+        "serverrequestid": "SUPERSECRETSERVERREQUESTID",
+        "clientrequestid": "",
+        "action": "listallDomains",
+        "status": "error",
+        "statuscode": 4013,
+        "shortmessage": "Validation Error.",
+        "longmessage": "Value in field domainname does not match requirements.",
+        "responsedata": ""
+      })))
+      .mount(&netcup)
+      .await;
+
+    let client = Client::new(&cli).unwrap().login().await.unwrap();
+    let response = client.call_raw("listallDomains", json!({})).await.unwrap();
+    client.logout().await.unwrap();
+
+    assert_eq!(StatusCode::ValidationError, response.status_code());
+    assert!(response.server_message().to_string().contains("domainname"));
+  }
+}
@@ -0,0 +1,364 @@
+//! Keeps the API session across runs, so a run in cron mode neither logs in
+//! nor out while the cached session is fresh.
+
+use std::{
+  path::PathBuf,
+  time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use async_trait::async_trait;
+use error_stack::{IntoReport, ResultExt};
+use serde::{Deserialize, Serialize};
+use tokio::{fs, io::AsyncWriteExt, sync::RwLock};
+use tracing::{debug, info, warn};
+
+use crate::{
+  cli::Cli,
+  errors::Errors,
+  provider::{Applied, DnsProvider, ZoneSettings},
+};
+
+use super::{
+  models::{ApiSessionId, DnsRecord, NoApiSessionId},
+  Client, ServerMessage,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct CachedSession {
+  customer_number: u32,
+  api_session_id: String,
+  /// Seconds since the Unix epoch.
+  created: u64,
+}
+
+/// A file only the owner can read, which holds the session of the last login.
+pub struct SessionCache {
+  path: PathBuf,
+  lifetime: Duration,
+}
+
+impl SessionCache {
+  pub fn new(path: impl Into<PathBuf>, lifetime: Duration) -> Self {
+    Self {
+      path: path.into(),
+      lifetime,
+    }
+  }
+
+  pub fn from_cli(cli: &Cli) -> Option<Self> {
+    cli
+      .session_cache()
+      .map(|path| Self::new(path, Duration::from_secs(cli.session_lifetime())))
+  }
+
+  /// The cached session of `customer_number` if it did not expire yet.
+  async fn load(&self, customer_number: u32) -> Option<String> {
+    let content = fs::read_to_string(&self.path).await.ok()?;
+    let session = serde_json::from_str::<CachedSession>(&content)
+      .map_err(|e| warn!("Ignoring the session cache {}: {e}", self.path.display()))
+      .ok()?;
+    let age = now().saturating_sub(session.created);
+
+    (session.customer_number == customer_number && age < self.lifetime.as_secs())
+      .then_some(session.api_session_id)
+  }
+
+  async fn store(
+    &self,
+    customer_number: u32,
+    api_session_id: &str,
+  ) -> error_stack::Result<(), Errors> {
+    let session = CachedSession {
+      customer_number,
+      api_session_id: api_session_id.to_string(),
+      created: now(),
+    };
+    let content = serde_json::to_vec(&session)
+      .into_report()
+      .change_context_lazy(|| self.error())?;
+
+    let mut options = fs::OpenOptions::new();
+    options.create(true).write(true).truncate(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    let mut file = options
+      .open(&self.path)
+      .await
+      .into_report()
+      .change_context_lazy(|| self.error())?;
+    // The mode only applies to new files.
+    #[cfg(unix)]
+    {
+      use std::os::unix::fs::PermissionsExt;
+
+      file
+        .set_permissions(std::fs::Permissions::from_mode(0o600))
+        .await
+        .into_report()
+        .change_context_lazy(|| self.error())?;
+    }
+    file
+      .write_all(&content)
+      .await
+      .into_report()
+      .change_context_lazy(|| self.error())
+  }
+
+  async fn clear(&self) {
+    if let Err(e) = fs::remove_file(&self.path).await {
+      if e.kind() != std::io::ErrorKind::NotFound {
+        warn!(
+          "Could not remove the session cache {}: {e}",
+          self.path.display()
+        );
+      }
+    }
+  }
+
+  fn error(&self) -> Errors {
+    Errors::SessionCache(self.path.display().to_string())
+  }
+}
+
+fn now() -> u64 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .unwrap_or_default()
+    .as_secs()
+}
+
+/// Whether Netcup rejected the request because the session is unknown or
+/// expired.
+fn rejected<T>(result: &error_stack::Result<T, Errors>) -> bool {
+  let Err(error) = result else {
+    return false;
+  };
+
+  error
+    .frames()
+    .filter_map(|frame| frame.downcast_ref::<ServerMessage>())
+    .any(ServerMessage::is_invalid_session)
+}
+
+/// The Netcup provider with a cached session. A rejected session is replaced
+/// by a new login once and the session is kept instead of logging out.
+pub struct CachedClient {
+  credentials: Client<NoApiSessionId>,
+  client: RwLock<Client<ApiSessionId>>,
+  cache: SessionCache,
+}
+
+impl CachedClient {
+  pub async fn connect(cli: &Cli, cache: SessionCache) -> error_stack::Result<Self, Errors> {
+    let credentials = Client::new(cli)?;
+    let customer_number = credentials.session_credentials.customer_number();
+
+    let client = match cache.load(customer_number).await {
+      Some(api_session_id) => {
+        info!("Reusing the cached API session");
+        credentials.clone().resume(api_session_id)
+      }
+      None => Self::login(&credentials, &cache).await?,
+    };
+
+    Ok(Self {
+      credentials,
+      client: RwLock::new(client),
+      cache,
+    })
+  }
+
+  async fn login(
+    credentials: &Client<NoApiSessionId>,
+    cache: &SessionCache,
+  ) -> error_stack::Result<Client<ApiSessionId>, Errors> {
+    let client = credentials.clone().login().await?;
+    let session_credentials = &client.session_credentials;
+    cache
+      .store(
+        session_credentials.customer_number(),
+        session_credentials.api_session_id(),
+      )
+      .await?;
+
+    Ok(client)
+  }
+
+  /// The client with the current session.
+  async fn current(&self) -> Client<ApiSessionId> {
+    self.client.read().await.clone()
+  }
+
+  /// Replaces the session of `rejected` with a new login, unless a concurrent
+  /// request replaced it already.
+  async fn renew(
+    &self,
+    rejected: &Client<ApiSessionId>,
+  ) -> error_stack::Result<Client<ApiSessionId>, Errors> {
+    let mut client = self.client.write().await;
+    let session_id =
+      |client: &Client<ApiSessionId>| client.session_credentials.api_session_id().to_string();
+
+    if session_id(&client) == session_id(rejected) {
+      info!("The cached API session was rejected, logging in again");
+      self.cache.clear().await;
+      *client = Self::login(&self.credentials, &self.cache).await?;
+    }
+
+    Ok(client.clone())
+  }
+}
+
+#[async_trait]
+impl DnsProvider for CachedClient {
+  fn name(&self) -> &'static str {
+    "netcup"
+  }
+
+  async fn records(&self, zone: &str) -> error_stack::Result<Vec<DnsRecord>, Errors> {
+    let client = self.current().await;
+    let result = client.records(zone).await;
+    if !rejected(&result) {
+      return result;
+    }
+    self.renew(&client).await?.records(zone).await
+  }
+
+  async fn apply(
+    &self,
+    zone: &str,
+    changes: Vec<DnsRecord>,
+  ) -> error_stack::Result<Applied, Errors> {
+    let client = self.current().await;
+    let result = client.apply(zone, changes.clone()).await;
+    if !rejected(&result) {
+      return result;
+    }
+    self.renew(&client).await?.apply(zone, changes).await
+  }
+
+  async fn zone_settings(&self, zone: &str) -> error_stack::Result<ZoneSettings, Errors> {
+    let client = self.current().await;
+    let result = client.zone_settings(zone).await;
+    if !rejected(&result) {
+      return result;
+    }
+    self.renew(&client).await?.zone_settings(zone).await
+  }
+
+  async fn update_zone_settings(
+    &self,
+    zone: &str,
+    settings: ZoneSettings,
+  ) -> error_stack::Result<(), Errors> {
+    let client = self.current().await;
+    let result = client.update_zone_settings(zone, settings.clone()).await;
+    if !rejected(&result) {
+      return result;
+    }
+    self
+      .renew(&client)
+      .await?
+      .update_zone_settings(zone, settings)
+      .await
+  }
+
+  /// Keeps the session for the next run while it is fresh.
+  async fn close(self: Box<Self>) -> error_stack::Result<(), Errors> {
+    let client = self.client.into_inner();
+    let customer_number = client.session_credentials.customer_number();
+    if self.cache.load(customer_number).await.as_deref()
+      == Some(client.session_credentials.api_session_id())
+    {
+      debug!("Keeping the API session for the next run");
+      return Ok(());
+    }
+
+    self.cache.clear().await;
+    Box::new(client).close().await
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use serde_json::{json, Value};
+  use wiremock::ResponseTemplate;
+
+  use super::*;
+  use crate::api::netcup::mock::{self, API_SESSION_ID};
+
+  #[tokio::test]
+  async fn reuse_and_renew_sessions() {
+    let netcup = mock::server().await;
+    let path = std::env::temp_dir().join(format!("session-{}.json", std::process::id()));
+    let cli = mock::cli(&netcup, &["--session-cache", path.to_str().unwrap()]);
+    let cache = || SessionCache::from_cli(&cli).unwrap();
+    let actions = || async {
+      netcup
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .map(|request| request.body_json::<Value>().unwrap()["action"].clone())
+        .collect::<Vec<_>>()
+    };
+
+    // An expired session of an earlier login is rejected.
+    cache().store(12345, "EXPIRED").await.unwrap();
+    #[cfg(unix)]
+    {
+      use std::os::unix::fs::PermissionsExt;
+
+      let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+      assert_eq!(0o600, mode & 0o777);
+    }
+    mock::action("infoDnsRecords", json!({ "apisessionid": "EXPIRED" }))
+      .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+        "serverrequestid": "", "clientrequestid": "", "action": "infoDnsRecords",
+        "status": "error", "statuscode": 4001,
+        "shortmessage": "The session id is not in a valid format.", "longmessage": "",
+        "responsedata": ""
+      })))
+      .mount(&netcup)
+      .await;
+    mock::action("infoDnsRecords", json!({ "apisessionid": API_SESSION_ID }))
+      .respond_with(mock::response(
+        "infoDnsRecords",
+        json!({ "dnsrecords": [] }),
+      ))
+      .mount(&netcup)
+      .await;
+
+    let provider = Box::new(CachedClient::connect(&cli, cache()).await.unwrap());
+    assert!(provider.records("example.com").await.unwrap().is_empty());
+    provider.close().await.unwrap();
+    assert_eq!(
+      vec!["infoDnsRecords", "login", "infoDnsRecords"],
+      actions().await
+    );
+
+    // The next run reuses the new session without logging in or out.
+    let provider = Box::new(CachedClient::connect(&cli, cache()).await.unwrap());
+    provider.records("example.com").await.unwrap();
+    provider.close().await.unwrap();
+    assert_eq!(
+      vec![
+        "infoDnsRecords",
+        "login",
+        "infoDnsRecords",
+        "infoDnsRecords"
+      ],
+      actions().await
+    );
+
+    // An expired cache is logged out and removed.
+    let provider = Box::new(
+      CachedClient::connect(&cli, SessionCache::new(&path, Duration::ZERO))
+        .await
+        .unwrap(),
+    );
+    provider.close().await.unwrap();
+    assert_eq!(Some(&json!("logout")), actions().await.last());
+    assert!(!path.exists());
+  }
+}
@@ -1,6 +1,6 @@
-use error_stack::ResultExt;
+use error_stack::{IntoReport, ResultExt};
 
-use crate::{api, errors::Errors};
+use crate::{api, errors::Errors, hostname::to_ascii};
 
 use super::{
   models::{
@@ -14,9 +14,13 @@ impl Client<ApiSessionId> {
   pub async fn update_dns_records(
     &self,
     domain_name: impl Into<String>,
-    dns_records: Vec<DnsRecord>,
+    mut dns_records: Vec<DnsRecord>,
   ) -> error_stack::Result<Response<ResponseData>, Errors> {
-    let domain_name = domain_name.into();
+    let domain_name = to_ascii(&domain_name.into()).into_report()?;
+    for record in &mut dns_records {
+      let host_name = to_ascii(record.host_name()).into_report()?;
+      record.host_name_mut(host_name);
+    }
     api::netcup::request(
       &self.api_url,
       &self.client,
@@ -1,6 +1,6 @@
-use error_stack::ResultExt;
+use error_stack::{IntoReport, ResultExt};
 
-use crate::{api, errors::Errors};
+use crate::{api, errors::Errors, hostname::to_ascii};
 
 use super::{
   models::{
@@ -17,15 +17,16 @@ impl Client<ApiSessionId> {
     domain_name: impl Into<String>,
     dns_zone: info_dns_zone::ResponseData,
   ) -> error_stack::Result<Response<ResponseData>, Errors> {
+    let domain_name = to_ascii(&domain_name.into()).into_report()?;
     api::netcup::request::<Params, ResponseData>(
       &self.api_url,
       &self.client,
       &Request::new(
-        Action::UpdateDnsRecords,
-        Params::new(domain_name, &self.session_credentials, dns_zone),
+        Action::UpdateDnsZone,
+        Params::new(domain_name.clone(), &self.session_credentials, dns_zone),
       ),
     )
     .await
-    .change_context(Errors::Login)
+    .change_context(Errors::UpdateDNSZone(domain_name))
   }
 }
@@ -0,0 +1,38 @@
+use error_stack::{IntoReport, ResultExt};
+
+use crate::{api, errors::Errors, hostname::to_ascii};
+
+use super::{
+  models::{
+    info_domain::{ContactEntries, NameserverEntries},
+    update_domain::{Params, ResponseData},
+    ApiSessionId, Request, Response,
+  },
+  Action, Client,
+};
+
+impl Client<ApiSessionId> {
+  pub async fn update_domain(
+    &self,
+    domain_name: impl Into<String>,
+    contacts: ContactEntries,
+    nameservers: NameserverEntries,
+  ) -> error_stack::Result<Response<ResponseData>, Errors> {
+    let domain_name = to_ascii(&domain_name.into()).into_report()?;
+    api::netcup::request::<Params, ResponseData>(
+      &self.api_url,
+      &self.client,
+      &Request::new(
+        Action::UpdateDomain,
+        Params::new(
+          domain_name.clone(),
+          &self.session_credentials,
+          contacts,
+          nameservers,
+        ),
+      ),
+    )
+    .await
+    .change_context(Errors::UpdateDomain(domain_name))
+  }
+}
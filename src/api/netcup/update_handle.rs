@@ -0,0 +1,31 @@
+use error_stack::ResultExt;
+
+use crate::{api, errors::Errors};
+
+use super::{
+  models::{
+    info_handle::HandleData,
+    update_handle::{Params, ResponseData},
+    ApiSessionId, Request, Response,
+  },
+  Action, Client,
+};
+
+impl Client<ApiSessionId> {
+  pub async fn update_handle(
+    &self,
+    handle_id: u32,
+    handle: HandleData,
+  ) -> error_stack::Result<Response<ResponseData>, Errors> {
+    api::netcup::request::<Params, ResponseData>(
+      &self.api_url,
+      &self.client,
+      &Request::new(
+        Action::UpdateHandle,
+        Params::new(handle_id, &self.session_credentials, handle),
+      ),
+    )
+    .await
+    .change_context(Errors::UpdateHandle(handle_id))
+  }
+}
@@ -0,0 +1,545 @@
+//! Dynamic DNS updates ([RFC 2136](https://tools.ietf.org/html/rfc2136))
+//! signed with TSIG, e.g. for zones on a self-hosted BIND or Knot.
+//!
+//! Records are read with a zone transfer, so the TSIG key has to be allowed to
+//! transfer the zone as well. Plain DNS has no record ids, so the id of a
+//! record is its current value and updating a record removes the old value
+//! and adds the new one in the same update message.
+
+use std::{
+  net::{IpAddr, SocketAddr},
+  sync::Arc,
+  time::Duration,
+};
+
+use async_trait::async_trait;
+use base64::Engine;
+use error_stack::{IntoReport, Report, ResultExt};
+use futures::TryStreamExt;
+use hickory_client::{
+  client::{AsyncClient, ClientHandle, Signer},
+  op::{Message, MessageType, OpCode, Query, ResponseCode, UpdateMessage},
+  proto::{
+    iocompat::AsyncIoTokioAsStd,
+    rr::dnssec::{rdata::tsig::TsigAlgorithm, tsig::TSigner},
+    xfer::{DnsHandle, FirstAnswer},
+  },
+  rr::{
+    rdata::{CNAME, MX, SOA, TXT},
+    DNSClass, Name, RData, Record, RecordType as HickoryRecordType,
+  },
+  tcp::TcpClientStream,
+};
+use tokio::net::TcpStream;
+use tracing::{debug, info};
+
+use crate::{
+  api::netcup::models::{DnsRecord, IpType, RecordType},
+  cli::Cli,
+  errors::Errors,
+  provider::{Applied, DnsProvider, ZoneSettings},
+};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+const TSIG_FUDGE: u16 = 300;
+
+pub struct Client {
+  server: String,
+  signer: Option<Arc<Signer>>,
+  ttl: u32,
+}
+
+impl Client {
+  pub fn new(cli: &Cli) -> error_stack::Result<Self, Errors> {
+    let server = cli
+      .rfc2136_server()
+      .ok_or_else(|| Report::new(Errors::Rfc2136Config("the server is missing".into())))?;
+
+    let signer = match (cli.tsig_key_name(), cli.tsig_secret()) {
+      (Some(key_name), Some(secret)) => Some(Arc::new(Signer::from(tsig_signer(
+        key_name,
+        secret,
+        cli.tsig_algorithm(),
+      )?))),
+      (None, None) => None,
+      _ => {
+        return Err(Report::new(Errors::Rfc2136Config(
+          "the TSIG key name and secret have to be set together".into(),
+        )))
+      }
+    };
+
+    Ok(Self {
+      server: server.to_string(),
+      signer,
+      ttl: cli.ttl().unwrap_or(300),
+    })
+  }
+
+  async fn connect(&self) -> error_stack::Result<AsyncClient, Errors> {
+    let server = resolve(&self.server).await?;
+
+    let (stream, sender) = TcpClientStream::<AsyncIoTokioAsStd<TcpStream>>::new(server);
+    let (client, background) =
+      AsyncClient::with_timeout(stream, sender, TIMEOUT, self.signer.clone())
+        .await
+        .into_report()
+        .change_context(Errors::Rfc2136Connect(self.server.clone()))?;
+    tokio::spawn(background);
+
+    Ok(client)
+  }
+
+  async fn soa(
+    &self,
+    client: &mut AsyncClient,
+    origin: &Name,
+  ) -> error_stack::Result<(SOA, u32), Errors> {
+    let response = client
+      .query(origin.clone(), DNSClass::IN, HickoryRecordType::SOA)
+      .await
+      .into_report()
+      .change_context(Errors::DNSZoneNotFound(origin.to_string()))?;
+
+    response
+      .answers()
+      .iter()
+      .find_map(|record| match record.data() {
+        Some(RData::SOA(soa)) => Some((soa.clone(), record.ttl())),
+        _ => None,
+      })
+      .ok_or_else(|| Report::new(Errors::DNSZoneNotFound(origin.to_string())))
+  }
+
+  async fn update(&self, origin: &Name, updates: Vec<Record>) -> error_stack::Result<(), Errors> {
+    let mut zone = Query::new();
+    zone
+      .set_name(origin.clone())
+      .set_query_class(DNSClass::IN)
+      .set_query_type(HickoryRecordType::SOA);
+
+    let mut message = Message::new();
+    message
+      .set_message_type(MessageType::Query)
+      .set_op_code(OpCode::Update)
+      .set_recursion_desired(false);
+    message.add_zone(zone);
+    message.add_updates(updates);
+
+    debug!(zone = %origin, updates = message.updates().len(), "Sending update");
+
+    let response = self
+      .connect()
+      .await?
+      .send(message)
+      .first_answer()
+      .await
+      .into_report()
+      .change_context(Errors::Rfc2136Update(origin.to_string()))?;
+
+    match response.response_code() {
+      ResponseCode::NoError => Ok(()),
+      code => Err(
+        Report::new(Errors::Rfc2136Update(origin.to_string()))
+          .attach_printable(format!("Response code {code}")),
+      ),
+    }
+  }
+}
+
+#[async_trait]
+impl DnsProvider for Client {
+  fn name(&self) -> &'static str {
+    "rfc2136"
+  }
+
+  async fn records(&self, zone: &str) -> error_stack::Result<Vec<DnsRecord>, Errors> {
+    let origin = zone_name(zone)?;
+
+    let responses = self
+      .connect()
+      .await?
+      .zone_transfer(origin.clone(), None)
+      .try_collect::<Vec<_>>()
+      .await
+      .into_report()
+      .change_context(Errors::Rfc2136Transfer(zone.to_string()))?;
+
+    Ok(
+      responses
+        .iter()
+        .flat_map(|response| response.answers())
+        .filter_map(|record| to_dns_record(&origin, record))
+        .collect(),
+    )
+  }
+
+  async fn apply(
+    &self,
+    zone: &str,
+    changes: Vec<DnsRecord>,
+  ) -> error_stack::Result<Applied, Errors> {
+    let origin = zone_name(zone)?;
+
+    let mut updates = vec![];
+    for change in &changes {
+      if change.delete_record() || change.id().is_some() {
+        let mut previous = to_record(&origin, change, change.id(), 0)?;
+        previous.set_dns_class(DNSClass::NONE);
+        updates.push(previous);
+      }
+
+      if !change.delete_record() {
+        updates.push(to_record(&origin, change, None, self.ttl)?);
+      }
+    }
+
+    self.update(&origin, updates).await?;
+    info!("Applied {} changes to {zone}", changes.len());
+
+    Ok(Applied::default())
+  }
+
+  async fn zone_settings(&self, zone: &str) -> error_stack::Result<ZoneSettings, Errors> {
+    let origin = zone_name(zone)?;
+    let (soa, ttl) = self.soa(&mut self.connect().await?, &origin).await?;
+
+    Ok(ZoneSettings::new(
+      zone,
+      ttl,
+      soa.serial(),
+      soa.refresh().unsigned_abs(),
+      soa.retry().unsigned_abs(),
+      soa.expire().unsigned_abs(),
+      false,
+    ))
+  }
+
+  async fn update_zone_settings(
+    &self,
+    zone: &str,
+    settings: ZoneSettings,
+  ) -> error_stack::Result<(), Errors> {
+    if settings.dns_sec_status() {
+      return Err(
+        Report::new(Errors::UpdateDNSZone(zone.to_string()))
+          .attach_printable("DNSSEC can't be turned on with dynamic updates"),
+      );
+    }
+
+    let origin = zone_name(zone)?;
+    let (soa, _) = self.soa(&mut self.connect().await?, &origin).await?;
+
+    let soa = SOA::new(
+      soa.mname().clone(),
+      soa.rname().clone(),
+      soa.serial().wrapping_add(1),
+      settings.refresh() as i32,
+      settings.retry() as i32,
+      settings.expire() as i32,
+      soa.minimum(),
+    );
+
+    self
+      .update(
+        &origin,
+        vec![Record::from_rdata(
+          origin.clone(),
+          settings.ttl(),
+          RData::SOA(soa),
+        )],
+      )
+      .await
+      .change_context(Errors::UpdateDNSZone(zone.to_string()))
+  }
+}
+
+fn tsig_signer(
+  key_name: &str,
+  secret: &str,
+  algorithm: &str,
+) -> error_stack::Result<TSigner, Errors> {
+  let algorithm = match algorithm.trim().to_lowercase().trim_end_matches('.') {
+    "hmac-sha256" => TsigAlgorithm::HmacSha256,
+    "hmac-sha384" => TsigAlgorithm::HmacSha384,
+    "hmac-sha512" => TsigAlgorithm::HmacSha512,
+    algorithm => {
+      return Err(Report::new(Errors::Rfc2136Config(format!(
+        "the TSIG algorithm {algorithm} is not supported"
+      ))))
+    }
+  };
+
+  let key = base64::engine::general_purpose::STANDARD
+    .decode(secret.trim())
+    .into_report()
+    .change_context(Errors::Rfc2136Config(
+      "the TSIG secret is not valid base64".into(),
+    ))?;
+
+  let key_name = Name::from_str_relaxed(key_name)
+    .into_report()
+    .change_context(Errors::Rfc2136Config(format!(
+      "the TSIG key name {key_name} is invalid"
+    )))?;
+
+  TSigner::new(key, algorithm, key_name, TSIG_FUDGE)
+    .into_report()
+    .change_context(Errors::Rfc2136Config("the TSIG key is invalid".into()))
+}
+
+/// Accepts `host`, `host:port`, `ip` and `[ipv6]:port`, the port defaults to 53.
+async fn resolve(server: &str) -> error_stack::Result<SocketAddr, Errors> {
+  if let Ok(ip) = server.parse::<IpAddr>() {
+    return Ok(SocketAddr::new(ip, 53));
+  }
+
+  let server = if server.rsplit_once(':').is_some() {
+    server.to_string()
+  } else {
+    format!("{server}:53")
+  };
+
+  let mut addresses = tokio::net::lookup_host(server.as_str())
+    .await
+    .into_report()
+    .change_context_lazy(|| Errors::Rfc2136Connect(server.clone()))?;
+
+  addresses
+    .next()
+    .ok_or_else(|| Report::new(Errors::Rfc2136Connect(server.clone())))
+}
+
+fn zone_name(zone: &str) -> error_stack::Result<Name, Errors> {
+  let mut name = Name::from_str_relaxed(zone)
+    .into_report()
+    .change_context(Errors::DNSZoneNotFound(zone.to_string()))?;
+  name.set_fqdn(true);
+
+  Ok(name)
+}
+
+fn host_name(origin: &Name, name: &Name) -> String {
+  let name = name.to_lowercase().to_ascii();
+  let origin = origin.to_lowercase().to_ascii();
+
+  match name.strip_suffix(&origin) {
+    Some("") => "@".to_string(),
+    Some(host_name) => host_name.trim_end_matches('.').to_string(),
+    None => name.trim_end_matches('.').to_string(),
+  }
+}
+
+fn to_dns_record(origin: &Name, record: &Record) -> Option<DnsRecord> {
+  let host_name = host_name(origin, record.name());
+
+  let mut dns_record = match record.data()? {
+    RData::A(a) => DnsRecord::new(host_name, IpAddr::V4(a.0)),
+    RData::AAAA(aaaa) => DnsRecord::new(host_name, IpAddr::V6(aaaa.0)),
+    RData::TXT(txt) => DnsRecord::other(
+      host_name,
+      RecordType::Other("TXT".into()),
+      txt
+        .txt_data()
+        .iter()
+        .map(|data| String::from_utf8_lossy(data))
+        .collect::<String>(),
+    ),
+    RData::CNAME(cname) => DnsRecord::other(
+      host_name,
+      RecordType::Other("CNAME".into()),
+      cname.0.to_ascii(),
+    ),
+    RData::MX(mx) => {
+      let mut dns_record = DnsRecord::other(
+        host_name,
+        RecordType::Other("MX".into()),
+        mx.exchange().to_ascii(),
+      );
+      dns_record.priority_mut(mx.preference().to_string());
+      dns_record
+    }
+    // The SOA is part of the zone settings and the delegation is managed by the
+    // registrar, just like Netcup doesn't list them as records.
+    RData::SOA(_) => return None,
+    RData::NS(_) if record.name() == origin => return None,
+    rdata => DnsRecord::other(
+      host_name,
+      RecordType::Other(record.record_type().to_string()),
+      rdata.to_string(),
+    ),
+  };
+
+  let id = dns_record.destination().to_string();
+  dns_record.id_mut(id);
+
+  Some(dns_record)
+}
+
+/// Builds the record with the destination of `record`, or with `value` which
+/// is used to address the previous value of an updated record.
+fn to_record(
+  origin: &Name,
+  record: &DnsRecord,
+  value: Option<&str>,
+  ttl: u32,
+) -> error_stack::Result<Record, Errors> {
+  let convert_error = || Errors::ConvertRecord(format!("{record:?}"));
+
+  let name = match record.host_name() {
+    "" | "@" => origin.clone(),
+    host_name => Name::from_str_relaxed(host_name)
+      .and_then(|name| name.append_domain(origin))
+      .into_report()
+      .change_context_lazy(convert_error)?,
+  };
+
+  let destination = match value {
+    Some(value) => value
+      .parse()
+      .unwrap_or_else(|_| IpType::Other(value.to_string())),
+    None => record.destination().clone(),
+  };
+
+  let rdata = match (record.record_type(), destination) {
+    (RecordType::A, IpType::Ip(IpAddr::V4(ip))) => RData::A(ip.into()),
+    (RecordType::AAAA, IpType::Ip(IpAddr::V6(ip))) => RData::AAAA(ip.into()),
+    (RecordType::Other(record_type), destination) => {
+      let destination = destination.to_string();
+      match record_type.to_uppercase().as_str() {
+        "TXT" => RData::TXT(TXT::new(vec![destination])),
+        "CNAME" => RData::CNAME(CNAME(
+          Name::from_str_relaxed(&destination)
+            .into_report()
+            .change_context_lazy(convert_error)?,
+        )),
+        "MX" => RData::MX(MX::new(
+          record
+            .priority()
+            .unwrap_or("10")
+            .parse()
+            .into_report()
+            .change_context_lazy(convert_error)?,
+          Name::from_str_relaxed(&destination)
+            .into_report()
+            .change_context_lazy(convert_error)?,
+        )),
+        _ => {
+          return Err(
+            Report::new(convert_error())
+              .attach_printable(format!("Record type {record_type} is not supported")),
+          )
+        }
+      }
+    }
+    _ => return Err(Report::new(convert_error())),
+  };
+
+  Ok(Record::from_rdata(name, ttl, rdata))
+}
+
+#[cfg(test)]
+mod test {
+  use std::{net::Ipv4Addr, str::FromStr};
+
+  use hickory_client::rr::rdata::A;
+
+  use super::*;
+
+  #[test]
+  fn convert_records() {
+    let origin = zone_name("Example.Domain").unwrap();
+
+    let apex = Record::from_rdata(origin.clone(), 300, RData::A(A::new(192, 0, 2, 1)));
+    let dns_record = to_dns_record(&origin, &apex).unwrap();
+    assert_eq!("@", dns_record.host_name());
+    assert_eq!(&RecordType::A, dns_record.record_type());
+    assert_eq!(Some("192.0.2.1"), dns_record.id());
+    assert_eq!(apex, to_record(&origin, &dns_record, None, 300).unwrap());
+
+    let txt = Record::from_rdata(
+      Name::from_str("_acme-challenge.www.example.domain.").unwrap(),
+      60,
+      RData::TXT(TXT::new(vec!["token".into()])),
+    );
+    let dns_record = to_dns_record(&origin, &txt).unwrap();
+    assert_eq!("_acme-challenge.www", dns_record.host_name());
+    assert_eq!(&RecordType::Other("TXT".into()), dns_record.record_type());
+    assert_eq!(&IpType::Other("token".into()), dns_record.destination());
+    assert_eq!(txt, to_record(&origin, &dns_record, None, 60).unwrap());
+
+    let soa = Record::from_rdata(
+      origin.clone(),
+      300,
+      RData::SOA(SOA::new(
+        origin.clone(),
+        origin.clone(),
+        1,
+        28800,
+        7200,
+        1209600,
+        300,
+      )),
+    );
+    assert_eq!(None, to_dns_record(&origin, &soa));
+  }
+
+  #[test]
+  fn previous_value_of_updated_record() {
+    let origin = zone_name("example.domain").unwrap();
+    let mut dns_record = DnsRecord::new("nas", IpAddr::V4(Ipv4Addr::new(192, 0, 2, 2)));
+    dns_record.id_mut("192.0.2.1");
+
+    let previous = to_record(&origin, &dns_record, dns_record.id(), 0).unwrap();
+    assert_eq!(
+      Name::from_str("nas.example.domain.").unwrap(),
+      *previous.name()
+    );
+    assert_eq!(Some(&RData::A(A::new(192, 0, 2, 1))), previous.data());
+
+    let mut mismatched = DnsRecord::new("nas", IpAddr::V4(Ipv4Addr::new(192, 0, 2, 2)));
+    mismatched.id_mut("2001:db8::1");
+    assert!(to_record(&origin, &mismatched, mismatched.id(), 0).is_err());
+  }
+
+  /// Runs against a local BIND or Knot which accepts dynamic updates and zone
+  /// transfers for the TSIG key, e.g.
+  /// `RFC2136_SERVER=127.0.0.1:5353 RFC2136_ZONE=example.test TSIG_KEY_NAME=ddns
+  /// TSIG_SECRET=... cargo test -- --ignored rfc2136`
+  #[tokio::test]
+  #[ignore]
+  async fn rfc2136_roundtrip() {
+    use structopt::StructOpt;
+
+    let zone = std::env::var("RFC2136_ZONE").expect("RFC2136_ZONE has to be set");
+    let cli = Cli::from_iter(["netcup-dns-updater", "--provider", "rfc2136"]);
+    let client = Client::new(&cli).unwrap();
+
+    let host_name = "netcup-dns-updater-test";
+    let find = |records: Vec<DnsRecord>| {
+      records
+        .into_iter()
+        .find(|record| record.host_name() == host_name)
+    };
+
+    let record = DnsRecord::new(host_name, IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)));
+    client.apply(&zone, vec![record]).await.unwrap();
+
+    let record = find(client.records(&zone).await.unwrap()).expect("the record was not created");
+    assert_eq!(Some("192.0.2.1"), record.id());
+
+    let mut update = DnsRecord::new(host_name, IpAddr::V4(Ipv4Addr::new(192, 0, 2, 2)));
+    update.id_mut(record.id().unwrap());
+    client.apply(&zone, vec![update]).await.unwrap();
+
+    let record = find(client.records(&zone).await.unwrap()).expect("the record was removed");
+    assert_eq!(Some("192.0.2.2"), record.id());
+
+    let origin = zone_name(&zone).unwrap();
+    let mut delete = to_record(&origin, &record, None, 0).unwrap();
+    delete.set_dns_class(DNSClass::NONE);
+    client.update(&origin, vec![delete]).await.unwrap();
+
+    assert_eq!(None, find(client.records(&zone).await.unwrap()));
+  }
+}
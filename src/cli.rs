@@ -1,16 +1,36 @@
-use std::str::FromStr;
+use std::{
+  net::SocketAddr,
+  path::{Path, PathBuf},
+  str::FromStr,
+};
 
 use serde::{Deserialize, Serialize};
 use structopt::StructOpt;
 
-use crate::errors::Errors;
+use crate::{
+  api::netcup::models::{info_domain::NameserverEntry, info_handle::HandleType},
+  errors::Errors,
+  history::{Format, Time},
+  hostname::{to_ascii, HostName},
+  logging::LogFormat,
+  notify::{email::Security, Target},
+  provider::ProviderKind,
+  report::ReportFormat,
+};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct DNSEntry {
   domain: String,
-  sub_domains: Vec<String>,
+  sub_domains: Vec<HostName>,
+  provider: Option<ProviderKind>,
 }
 
+/// Parses `[provider/]domain[: sub_domain, ...]`, e.g.
+/// `hetzner/example.com: @, www`. Without a provider the one given by
+/// `--provider` is used. The sub domains may be relative or fully qualified,
+/// an entry without sub domains is either a zone or the FQDN of a host, e.g.
+/// `nas.home.example.com`, whose zone is looked up when updating. Unicode
+/// names are converted to punycode.
 impl FromStr for DNSEntry {
   type Err = Errors;
 
@@ -19,20 +39,27 @@ impl FromStr for DNSEntry {
       return Err(Errors::SerializeDomains);
     }
 
+    let (provider, s) = match s.split_once('/') {
+      Some((provider, s)) => (Some(provider.parse()?), s),
+      None => (None, s),
+    };
+
     let (domain, sub_domains) = match s.split_once(':') {
-      Some((domain, sub_domains)) => (
-        domain.trim().to_owned(),
-        sub_domains
+      Some((domain, sub_domains)) => {
+        let domain = to_ascii(domain)?;
+        let sub_domains = sub_domains
           .split(',')
-          .map(|s| s.trim().to_owned())
-          .collect(),
-      ),
-      None => (s.trim().to_owned(), vec![]),
+          .map(|s| Ok(HostName::relative(&to_ascii(s)?, &domain)))
+          .collect::<Result<_, Errors>>()?;
+        (domain, sub_domains)
+      }
+      None => (to_ascii(s)?, vec![]),
     };
 
     Ok(Self {
       domain,
       sub_domains,
+      provider,
     })
   }
 }
@@ -42,9 +69,456 @@ impl DNSEntry {
     &self.domain
   }
 
-  pub fn sub_domains(&self) -> &Vec<String> {
+  pub fn sub_domains(&self) -> &Vec<HostName> {
     &self.sub_domains
   }
+
+  pub fn provider(&self) -> Option<ProviderKind> {
+    self.provider
+  }
+
+  /// The host this entry names as an entry of `zone`.
+  pub fn in_zone(&self, zone: &str) -> Self {
+    Self {
+      domain: zone.to_string(),
+      sub_domains: vec![HostName::relative(&self.domain, zone)],
+      provider: self.provider,
+    }
+  }
+
+  /// Adds the sub domains of `other`, an entry of the same zone.
+  pub fn merge(&mut self, other: Self) {
+    for sub_domain in other.sub_domains {
+      if !self.sub_domains.contains(&sub_domain) {
+        self.sub_domains.push(sub_domain);
+      }
+    }
+  }
+}
+
+/// Where the records of a zone are mirrored to, either a provider other than
+/// Netcup or a zone file given as `file:path`. `{zone}` in the path is replaced
+/// by the zone.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyncTarget {
+  Provider(ProviderKind),
+  ZoneFile(PathBuf),
+}
+
+impl SyncTarget {
+  pub fn provider(&self) -> Option<ProviderKind> {
+    match self {
+      Self::Provider(kind) => Some(*kind),
+      Self::ZoneFile(_) => None,
+    }
+  }
+}
+
+impl FromStr for SyncTarget {
+  type Err = Errors;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.trim().strip_prefix("file:") {
+      Some(path) if !path.is_empty() => Ok(Self::ZoneFile(path.into())),
+      Some(_) => Err(Errors::ParseSyncTarget(s.to_string())),
+      // The Netcup zones are the master, they can't be mirrored to themselves.
+      None => match s.parse() {
+        Ok(ProviderKind::Netcup) | Err(_) => Err(Errors::ParseSyncTarget(s.to_string())),
+        Ok(kind) => Ok(Self::Provider(kind)),
+      },
+    }
+  }
+}
+
+/// A `key=value` param of a raw action. Values which are valid JSON are used
+/// as is, everything else is sent as a string.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawParam {
+  key: String,
+  value: serde_json::Value,
+}
+
+impl FromStr for RawParam {
+  type Err = Errors;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let (key, value) = s
+      .split_once('=')
+      .filter(|(key, _)| !key.trim().is_empty())
+      .ok_or_else(|| Errors::ParseRawParam(s.to_string()))?;
+
+    Ok(Self {
+      key: key.trim().to_owned(),
+      value: serde_json::from_str(value)
+        .unwrap_or_else(|_| serde_json::Value::String(value.to_owned())),
+    })
+  }
+}
+
+impl RawParam {
+  pub fn key(&self) -> &str {
+    &self.key
+  }
+
+  pub fn value(&self) -> &serde_json::Value {
+    &self.value
+  }
+}
+
+fn parse_toggle(s: &str) -> Result<bool, Errors> {
+  match s.trim().to_lowercase().as_str() {
+    "on" | "true" | "yes" | "1" => Ok(true),
+    "off" | "false" | "no" | "0" => Ok(false),
+    _ => Err(Errors::ParseToggle(s.to_string())),
+  }
+}
+
+/// The directory state files are kept in unless a path is given: the
+/// `StateDirectory=` of a systemd unit, else the XDG state directory of the
+/// user.
+pub fn state_dir() -> PathBuf {
+  if let Some(directory) = std::env::var_os("STATE_DIRECTORY")
+    .as_deref()
+    .and_then(|directories| std::env::split_paths(directories).next())
+    .filter(|directory| !directory.as_os_str().is_empty())
+  {
+    return directory;
+  }
+
+  std::env::var_os("XDG_STATE_HOME")
+    .filter(|directory| !directory.is_empty())
+    .map(PathBuf::from)
+    .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".local/state")))
+    .map(|directory| directory.join(env!("CARGO_PKG_NAME")))
+    .unwrap_or_default()
+}
+
+/// `path`, or the file `name` in the state directory if it is missing or
+/// empty.
+pub fn state_file(path: Option<&Path>, name: &str) -> PathBuf {
+  match path {
+    Some(path) if !path.as_os_str().is_empty() => path.to_path_buf(),
+    _ => state_dir().join(name),
+  }
+}
+
+#[derive(Debug, StructOpt)]
+pub struct ZoneSettingsArgs {
+  #[structopt(long, help = "The default TTL of the records in seconds.")]
+  ttl: Option<u32>,
+  #[structopt(long, help = "The SOA refresh timer in seconds.")]
+  refresh: Option<u32>,
+  #[structopt(long, help = "The SOA retry timer in seconds.")]
+  retry: Option<u32>,
+  #[structopt(long, help = "The SOA expire timer in seconds.")]
+  expire: Option<u32>,
+  #[structopt(
+    long,
+    parse(try_from_str = parse_toggle),
+    help = "Turns DNSSEC on or off."
+  )]
+  dnssec: Option<bool>,
+}
+
+impl ZoneSettingsArgs {
+  pub(crate) fn ttl(&self) -> Option<u32> {
+    self.ttl
+  }
+
+  pub(crate) fn refresh(&self) -> Option<u32> {
+    self.refresh
+  }
+
+  pub(crate) fn retry(&self) -> Option<u32> {
+    self.retry
+  }
+
+  pub(crate) fn expire(&self) -> Option<u32> {
+    self.expire
+  }
+
+  pub(crate) fn dnssec(&self) -> Option<bool> {
+    self.dnssec
+  }
+}
+
+#[derive(Debug, StructOpt)]
+pub enum ZoneCommand {
+  #[structopt(about = "Shows the TTL, SOA timers and DNSSEC status of a zone.")]
+  Show {
+    #[structopt(help = "The domain of the zone.")]
+    domain: String,
+  },
+  #[structopt(about = "Changes the TTL, SOA timers or DNSSEC status of a zone.")]
+  Set {
+    #[structopt(help = "The domain of the zone.")]
+    domain: String,
+    #[structopt(flatten)]
+    settings: ZoneSettingsArgs,
+    #[structopt(long, help = "Only show the changes without applying them.")]
+    dry_run: bool,
+  },
+}
+
+#[derive(Debug, StructOpt)]
+pub enum NameserversCommand {
+  #[structopt(about = "Shows the nameservers of a domain.")]
+  Show {
+    #[structopt(help = "The domain to show the nameservers of.")]
+    domain: String,
+  },
+  #[structopt(about = "Replaces the nameservers of a domain.")]
+  Set {
+    #[structopt(help = "The domain to change the nameservers of.")]
+    domain: String,
+    #[structopt(
+      required = true,
+      max_values = 8,
+      help = "The new nameservers as hostname or hostname=ipv4,ipv6 with glue records."
+    )]
+    nameservers: Vec<NameserverEntry>,
+    #[structopt(
+      long,
+      help = "Move away from the Netcup nameservers even if DDNS records are managed for the domain."
+    )]
+    force: bool,
+  },
+}
+
+#[derive(Debug, StructOpt)]
+pub struct HandleArgs {
+  #[structopt(long = "type", help = "Either person or organisation.")]
+  handle_type: Option<HandleType>,
+  #[structopt(long, help = "The name of the contact.")]
+  name: Option<String>,
+  #[structopt(long, help = "The organisation of the contact.")]
+  organisation: Option<String>,
+  #[structopt(long, help = "The street including the house number.")]
+  street: Option<String>,
+  #[structopt(long, help = "The postal code.")]
+  postal_code: Option<String>,
+  #[structopt(long, help = "The city.")]
+  city: Option<String>,
+  #[structopt(long, help = "The two letter ISO country code.")]
+  country_code: Option<String>,
+  #[structopt(long, help = "The telephone number in the format +49.123456789.")]
+  telephone: Option<String>,
+  #[structopt(long, help = "The email address.")]
+  email: Option<String>,
+}
+
+impl HandleArgs {
+  pub(crate) fn handle_type(&self) -> Option<HandleType> {
+    self.handle_type
+  }
+
+  pub(crate) fn name(&self) -> Option<&str> {
+    self.name.as_deref()
+  }
+
+  pub(crate) fn organisation(&self) -> Option<&str> {
+    self.organisation.as_deref()
+  }
+
+  pub(crate) fn street(&self) -> Option<&str> {
+    self.street.as_deref()
+  }
+
+  pub(crate) fn postal_code(&self) -> Option<&str> {
+    self.postal_code.as_deref()
+  }
+
+  pub(crate) fn city(&self) -> Option<&str> {
+    self.city.as_deref()
+  }
+
+  pub(crate) fn country_code(&self) -> Option<&str> {
+    self.country_code.as_deref()
+  }
+
+  pub(crate) fn telephone(&self) -> Option<&str> {
+    self.telephone.as_deref()
+  }
+
+  pub(crate) fn email(&self) -> Option<&str> {
+    self.email.as_deref()
+  }
+}
+
+#[derive(Debug, StructOpt)]
+pub enum HandlesCommand {
+  #[structopt(about = "Lists all handles of the account.")]
+  List,
+  #[structopt(about = "Shows a handle as JSON.")]
+  Show {
+    #[structopt(help = "The id of the handle.")]
+    id: u32,
+  },
+  #[structopt(about = "Creates a new handle.")]
+  Create {
+    #[structopt(flatten)]
+    handle: HandleArgs,
+  },
+  #[structopt(about = "Updates the given fields of a handle.")]
+  Update {
+    #[structopt(help = "The id of the handle.")]
+    id: u32,
+    #[structopt(flatten)]
+    handle: HandleArgs,
+  },
+  #[structopt(about = "Deletes a handle.")]
+  Delete {
+    #[structopt(help = "The id of the handle.")]
+    id: u32,
+  },
+}
+
+/// The challenge of the ACME hooks. certbot passes it in the environment and
+/// lego's exec provider as `present <fqdn> <value>`.
+#[derive(Debug, StructOpt)]
+pub struct AcmeChallenge {
+  #[structopt(
+    env = "CERTBOT_DOMAIN",
+    help = "The domain or the _acme-challenge FQDN which is validated."
+  )]
+  fqdn: String,
+  #[structopt(env = "CERTBOT_VALIDATION", help = "The value of the TXT record.")]
+  token: String,
+  #[structopt(
+    long,
+    env = "ACME_ZONE",
+    help = "The Netcup zone of the domain, detected from the FQDN if not given."
+  )]
+  zone: Option<String>,
+}
+
+impl AcmeChallenge {
+  pub(crate) fn fqdn(&self) -> &str {
+    &self.fqdn
+  }
+
+  pub(crate) fn token(&self) -> &str {
+    &self.token
+  }
+
+  pub(crate) fn zone(&self) -> Option<&str> {
+    self.zone.as_deref()
+  }
+}
+
+#[derive(Debug, StructOpt)]
+pub enum AcmeCommand {
+  #[structopt(about = "Creates the _acme-challenge TXT record.")]
+  Present(AcmeChallenge),
+  #[structopt(about = "Deletes the _acme-challenge TXT record again.")]
+  Cleanup(AcmeChallenge),
+}
+
+#[derive(Debug, StructOpt)]
+pub enum Command {
+  #[structopt(about = "Shows or changes the nameserver delegation of a domain.")]
+  Nameservers(NameserversCommand),
+  #[structopt(about = "Manages the contact handles of the account.")]
+  Handles(HandlesCommand),
+  #[structopt(about = "Shows or changes the settings of a DNS zone.")]
+  Zone(ZoneCommand),
+  #[structopt(about = "Fetches, logs and acknowledges the queued Netcup messages.")]
+  Poll {
+    #[structopt(
+      short = "n",
+      long,
+      default_value = "10",
+      help = "How many messages should be fetched at most."
+    )]
+    count: u32,
+    #[structopt(
+      long,
+      env = "POLL_FORWARD_URL",
+      help = "An URL every message is posted to as JSON before it is acknowledged."
+    )]
+    forward_url: Option<String>,
+    #[structopt(long, help = "Only show the messages without acknowledging them.")]
+    no_ack: bool,
+  },
+  #[structopt(about = "Performs any API action and prints the response as JSON.")]
+  Raw {
+    #[structopt(help = "The name of the action, e.g. listallDomains.")]
+    action: String,
+    #[structopt(
+      long = "param",
+      number_of_values = 1,
+      help = "A param of the action as key=value. JSON values like numbers or objects are sent as is."
+    )]
+    params: Vec<RawParam>,
+  },
+  #[structopt(
+    about = "DNS-01 challenge hooks for certbot's manual hooks and lego's exec provider."
+  )]
+  Acme(AcmeCommand),
+  #[structopt(
+    about = "Serves an acme-dns compatible API which writes the challenges to a Netcup zone."
+  )]
+  AcmeDns {
+    #[structopt(
+      long,
+      env = "ACME_DNS_LISTEN",
+      default_value = "127.0.0.1:8053",
+      help = "The address the API listens on."
+    )]
+    listen: SocketAddr,
+    #[structopt(
+      long,
+      env = "ACME_DNS_ZONE",
+      help = "The Netcup zone the challenge records are created in."
+    )]
+    zone: String,
+    #[structopt(
+      long,
+      env = "ACME_DNS_STORAGE",
+      help = "The file the registrations are stored in, acme-dns.json in the state directory by default."
+    )]
+    storage: Option<PathBuf>,
+    #[structopt(
+      long,
+      help = "Accept new registrations, which are rejected by default."
+    )]
+    enable_registration: bool,
+  },
+  #[structopt(about = "Shows the recorded record changes.")]
+  History {
+    #[structopt(
+      long,
+      help = "Only show the changes of a host, e.g. www or www.example.com."
+    )]
+    host: Option<String>,
+    #[structopt(long, help = "Only show the changes of a zone.")]
+    zone: Option<String>,
+    #[structopt(
+      long,
+      help = "Only show the changes since a time, e.g. 2026-10-01, 2026-10-01T12:00:00Z or 7d for seven days ago."
+    )]
+    since: Option<Time>,
+    #[structopt(long, help = "Only show the changes until a time, like --since.")]
+    until: Option<Time>,
+    #[structopt(long, default_value = "json", help = "Export as json or csv.")]
+    format: Format,
+  },
+  #[structopt(
+    about = "Replicates Netcup zones to other providers or zone files and reports the drift."
+  )]
+  Sync {
+    #[structopt(
+      long = "to",
+      required = true,
+      number_of_values = 1,
+      help = "A provider or file:path the zones are mirrored to, can be given multiple times."
+    )]
+    targets: Vec<SyncTarget>,
+    #[structopt(help = "The zones to mirror, defaults to the Netcup zones of the domains.")]
+    zones: Vec<String>,
+    #[structopt(long, help = "Only report the drift without changing the targets.")]
+    dry_run: bool,
+  },
 }
 
 #[derive(Debug, StructOpt)]
@@ -59,7 +533,7 @@ pub struct Cli {
     env = "CUSTOMER_NUMBER",
     help = "The customer number which identifies your Netcup account."
   )]
-  customer_number: u32,
+  customer_number: Option<u32>,
   #[structopt(
     short = "u",
     long,
@@ -74,27 +548,301 @@ pub struct Cli {
     env = "API_KEY",
     help = "The API key generated by Netcup in the CCP."
   )]
-  api_key: String,
+  api_key: Option<String>,
   #[structopt(
     short = "p",
     long,
     env = "API_PASSWORD",
     help = "The API password generated by Netcup in the CCP."
   )]
-  api_password: String,
+  api_password: Option<String>,
   #[structopt(
     short,
     long,
     env = "TTL",
-    help = "Should the TTL be reduced to a certain time in ms, which could be better for ddns."
+    help = "Should the TTL be reduced to a certain time in seconds, which could be better for ddns."
   )]
   ttl: Option<u32>,
-  #[structopt(env = "DOMAINS", value_delimiter = ";")]
+  #[structopt(
+    long,
+    env = "PROVIDER",
+    default_value = "netcup",
+    help = "The default DNS provider of the domains, one of netcup, rfc2136, hetzner or cloudflare."
+  )]
+  provider: ProviderKind,
+  #[structopt(
+    long,
+    env = "RFC2136_SERVER",
+    help = "The primary nameserver accepting dynamic updates as host or host:port."
+  )]
+  rfc2136_server: Option<String>,
+  #[structopt(
+    long,
+    env = "TSIG_KEY_NAME",
+    help = "The name of the TSIG key used to sign dynamic updates."
+  )]
+  tsig_key_name: Option<String>,
+  #[structopt(
+    long,
+    env = "TSIG_SECRET",
+    hide_env_values = true,
+    help = "The base64 encoded secret of the TSIG key."
+  )]
+  tsig_secret: Option<String>,
+  #[structopt(
+    long,
+    env = "TSIG_ALGORITHM",
+    default_value = "hmac-sha256",
+    help = "The algorithm of the TSIG key, one of hmac-sha256, hmac-sha384 or hmac-sha512."
+  )]
+  tsig_algorithm: String,
+  #[structopt(
+    long,
+    env = "HETZNER_DNS_TOKEN",
+    hide_env_values = true,
+    help = "The API token of the Hetzner DNS console."
+  )]
+  hetzner_token: Option<String>,
+  #[structopt(
+    long,
+    env = "HETZNER_DNS_API_URL",
+    default_value = "https://dns.hetzner.com/api/v1",
+    help = "The URL of the Hetzner DNS api."
+  )]
+  hetzner_api_url: String,
+  #[structopt(
+    long,
+    env = "CLOUDFLARE_API_TOKEN",
+    hide_env_values = true,
+    help = "A Cloudflare API token with the DNS edit permission."
+  )]
+  cloudflare_token: Option<String>,
+  #[structopt(
+    long,
+    env = "CLOUDFLARE_API_URL",
+    default_value = "https://api.cloudflare.com/client/v4",
+    help = "The URL of the Cloudflare api."
+  )]
+  cloudflare_api_url: String,
+  #[structopt(
+    long = "mirror",
+    env = "MIRROR",
+    value_delimiter = ";",
+    number_of_values = 1,
+    help = "Providers or file:path zone files the Netcup zones are mirrored to after every update."
+  )]
+  mirrors: Vec<SyncTarget>,
+  #[structopt(
+    long,
+    env = "VERIFY_PROPAGATION",
+    default_value = "off",
+    parse(try_from_str = parse_toggle),
+    help = "Wait until the authoritative nameservers serve the changed records, on or off."
+  )]
+  verify_propagation: bool,
+  #[structopt(
+    long,
+    env = "PROPAGATION_TIMEOUT",
+    default_value = "300",
+    help = "How many seconds to wait for the changes to propagate."
+  )]
+  propagation_timeout: u64,
+  #[structopt(
+    long,
+    env = "PROPAGATION_INTERVAL",
+    default_value = "10",
+    help = "How many seconds to wait between checking the nameservers."
+  )]
+  propagation_interval: u64,
+  #[structopt(
+    long = "nameserver",
+    env = "AUTHORITATIVE_NAMESERVERS",
+    value_delimiter = ";",
+    number_of_values = 1,
+    help = "The authoritative nameservers to check instead of the NS records of the zone."
+  )]
+  nameservers: Vec<String>,
+  #[structopt(
+    long,
+    env = "RESOLVER",
+    default_value = "1.1.1.1",
+    help = "The resolver used to look up the NS records of a zone."
+  )]
+  resolver: String,
+  #[structopt(
+    long = "webhook",
+    env = "WEBHOOKS",
+    value_delimiter = ";",
+    number_of_values = 1,
+    help = "URLs every record change and failed run is posted to as JSON."
+  )]
+  webhooks: Vec<String>,
+  #[structopt(
+    long,
+    env = "WEBHOOK_TEMPLATE",
+    help = "The JSON body of the webhooks, {{severity}}, {{zone}}, {{hostname}}, {{old_ip}}, {{new_ip}}, {{error}} and {{message}} are replaced."
+  )]
+  webhook_template: Option<String>,
+  #[structopt(
+    long,
+    env = "NOTIFY",
+    value_delimiter = ";",
+    number_of_values = 1,
+    help = "Push notifiers as ntfy://, gotify:// or matrix:// URLs, optionally filtered with ?severity=change,warning,error&zone=example.com."
+  )]
+  notify: Vec<Target>,
+  #[structopt(
+    long,
+    env = "NOTIFY_RETRIES",
+    default_value = "3",
+    help = "How often a failed notification is retried."
+  )]
+  notify_retries: u32,
+  #[structopt(
+    long,
+    env = "SMTP_SERVER",
+    help = "The host[:port] of the SMTP server the digest of a run is mailed with."
+  )]
+  smtp_server: Option<String>,
+  #[structopt(
+    long,
+    env = "SMTP_SECURITY",
+    default_value = "starttls",
+    help = "How the connection to the SMTP server is secured, none, starttls or tls."
+  )]
+  smtp_security: Security,
+  #[structopt(long, env = "SMTP_USERNAME", help = "The username of the SMTP server.")]
+  smtp_username: Option<String>,
+  #[structopt(
+    long,
+    env = "SMTP_PASSWORD",
+    hide_env_values = true,
+    help = "The password of the SMTP server."
+  )]
+  smtp_password: Option<String>,
+  #[structopt(long, env = "SMTP_FROM", help = "The sender of the digest.")]
+  smtp_from: Option<String>,
+  #[structopt(
+    long,
+    env = "SMTP_TO",
+    value_delimiter = ";",
+    number_of_values = 1,
+    help = "The recipients of the digest."
+  )]
+  smtp_to: Vec<String>,
+  #[structopt(
+    long,
+    env = "SMTP_INTERVAL",
+    default_value = "3600",
+    help = "How many seconds have to pass before the next digest is mailed."
+  )]
+  smtp_interval: u64,
+  #[structopt(
+    long,
+    env = "SMTP_STATE",
+    help = "The file remembering when the last digest was mailed, smtp-state.json in the state directory by default."
+  )]
+  smtp_state: Option<PathBuf>,
+  #[structopt(
+    long,
+    env = "MQTT_BROKER",
+    help = "The host[:port] of the MQTT broker the state of the updater is published to."
+  )]
+  mqtt_broker: Option<String>,
+  #[structopt(long, env = "MQTT_USERNAME", help = "The username of the MQTT broker.")]
+  mqtt_username: Option<String>,
+  #[structopt(
+    long,
+    env = "MQTT_PASSWORD",
+    hide_env_values = true,
+    help = "The password of the MQTT broker."
+  )]
+  mqtt_password: Option<String>,
+  #[structopt(
+    long,
+    env = "MQTT_TOPIC",
+    default_value = "netcup-ddns",
+    help = "The topic the state is published below."
+  )]
+  mqtt_topic: String,
+  #[structopt(
+    long,
+    env = "MQTT_DISCOVERY_PREFIX",
+    default_value = "homeassistant",
+    help = "The discovery prefix of Home Assistant."
+  )]
+  mqtt_discovery_prefix: String,
+  #[structopt(
+    long,
+    env = "INTERVAL",
+    help = "Keep running and update every this many seconds instead of only once."
+  )]
+  interval: Option<u64>,
+  #[structopt(
+    long,
+    env = "METRICS_LISTEN",
+    help = "The address the Prometheus metrics are served on at /metrics."
+  )]
+  metrics_listen: Option<SocketAddr>,
+  #[structopt(
+    long,
+    env = "LOG_FORMAT",
+    default_value = "text",
+    help = "Log as text or as JSON lines with the fields of every span."
+  )]
+  log_format: LogFormat,
+  #[structopt(
+    long,
+    env = "HISTORY_FILE",
+    help = "The file every record change is appended to, history.jsonl in the state directory by default."
+  )]
+  history_file: Option<PathBuf>,
+  #[structopt(
+    long,
+    env = "REPORT_FORMAT",
+    default_value = "table",
+    help = "Print the outcome of every zone and host after a run as table, json or none."
+  )]
+  report_format: ReportFormat,
+  #[structopt(
+    long,
+    env = "SESSION_CACHE",
+    help = "A file only the owner can read, which keeps the Netcup session for the next runs instead of logging out."
+  )]
+  session_cache: Option<PathBuf>,
+  #[structopt(
+    long,
+    env = "SESSION_LIFETIME",
+    default_value = "900",
+    help = "For how many seconds after the login a cached session is reused."
+  )]
+  session_lifetime: u64,
+  #[structopt(
+    long,
+    env = "CONCURRENCY",
+    default_value = "4",
+    help = "How many zones are updated at the same time."
+  )]
+  concurrency: usize,
+  #[structopt(
+    long,
+    env = "REQUESTS_PER_MINUTE",
+    default_value = "120",
+    help = "How many requests are sent to Netcup per minute at most, shared by all zones."
+  )]
+  requests_per_minute: usize,
+  #[structopt(
+    env = "DOMAINS",
+    value_delimiter = ";",
+    help = "The domains and their sub domains as [provider/]domain: sub_domain, ... or the FQDNs of hosts, separated by ;"
+  )]
   domains: Vec<DNSEntry>,
+  #[structopt(subcommand)]
+  command: Option<Command>,
 }
 
 impl Cli {
-  pub(crate) fn customer_number(&self) -> u32 {
+  pub(crate) fn customer_number(&self) -> Option<u32> {
     self.customer_number
   }
 
@@ -102,12 +850,12 @@ impl Cli {
     &self.api_url
   }
 
-  pub(crate) fn api_key(&self) -> &str {
-    &self.api_key
+  pub(crate) fn api_key(&self) -> Option<&str> {
+    self.api_key.as_deref()
   }
 
-  pub(crate) fn api_password(&self) -> &str {
-    &self.api_password
+  pub(crate) fn api_password(&self) -> Option<&str> {
+    self.api_password.as_deref()
   }
 
   pub(crate) fn domains(&self) -> &Vec<DNSEntry> {
@@ -117,6 +865,174 @@ impl Cli {
   pub(crate) fn ttl(&self) -> Option<u32> {
     self.ttl
   }
+
+  pub(crate) fn provider(&self) -> ProviderKind {
+    self.provider
+  }
+
+  pub(crate) fn rfc2136_server(&self) -> Option<&str> {
+    self.rfc2136_server.as_deref()
+  }
+
+  pub(crate) fn tsig_key_name(&self) -> Option<&str> {
+    self.tsig_key_name.as_deref()
+  }
+
+  pub(crate) fn tsig_secret(&self) -> Option<&str> {
+    self.tsig_secret.as_deref()
+  }
+
+  pub(crate) fn tsig_algorithm(&self) -> &str {
+    &self.tsig_algorithm
+  }
+
+  pub(crate) fn hetzner_token(&self) -> Option<&str> {
+    self.hetzner_token.as_deref()
+  }
+
+  pub(crate) fn hetzner_api_url(&self) -> &str {
+    &self.hetzner_api_url
+  }
+
+  pub(crate) fn cloudflare_token(&self) -> Option<&str> {
+    self.cloudflare_token.as_deref()
+  }
+
+  pub(crate) fn cloudflare_api_url(&self) -> &str {
+    &self.cloudflare_api_url
+  }
+
+  pub(crate) fn mirrors(&self) -> &Vec<SyncTarget> {
+    &self.mirrors
+  }
+
+  pub(crate) fn verify_propagation(&self) -> bool {
+    self.verify_propagation
+  }
+
+  pub(crate) fn propagation_timeout(&self) -> u64 {
+    self.propagation_timeout
+  }
+
+  pub(crate) fn propagation_interval(&self) -> u64 {
+    self.propagation_interval
+  }
+
+  pub(crate) fn nameservers(&self) -> &Vec<String> {
+    &self.nameservers
+  }
+
+  pub(crate) fn resolver(&self) -> &str {
+    &self.resolver
+  }
+
+  pub(crate) fn webhooks(&self) -> &Vec<String> {
+    &self.webhooks
+  }
+
+  pub(crate) fn webhook_template(&self) -> Option<&str> {
+    self.webhook_template.as_deref()
+  }
+
+  pub(crate) fn notify(&self) -> &Vec<Target> {
+    &self.notify
+  }
+
+  pub(crate) fn notify_retries(&self) -> u32 {
+    self.notify_retries
+  }
+
+  pub(crate) fn smtp_server(&self) -> Option<&str> {
+    self.smtp_server.as_deref()
+  }
+
+  pub(crate) fn smtp_security(&self) -> Security {
+    self.smtp_security
+  }
+
+  pub(crate) fn smtp_username(&self) -> Option<&str> {
+    self.smtp_username.as_deref()
+  }
+
+  pub(crate) fn smtp_password(&self) -> Option<&str> {
+    self.smtp_password.as_deref()
+  }
+
+  pub(crate) fn smtp_from(&self) -> Option<&str> {
+    self.smtp_from.as_deref()
+  }
+
+  pub(crate) fn smtp_to(&self) -> &Vec<String> {
+    &self.smtp_to
+  }
+
+  pub(crate) fn smtp_interval(&self) -> u64 {
+    self.smtp_interval
+  }
+
+  pub(crate) fn smtp_state(&self) -> PathBuf {
+    state_file(self.smtp_state.as_deref(), "smtp-state.json")
+  }
+
+  pub(crate) fn mqtt_broker(&self) -> Option<&str> {
+    self.mqtt_broker.as_deref()
+  }
+
+  pub(crate) fn mqtt_username(&self) -> Option<&str> {
+    self.mqtt_username.as_deref()
+  }
+
+  pub(crate) fn mqtt_password(&self) -> Option<&str> {
+    self.mqtt_password.as_deref()
+  }
+
+  pub(crate) fn mqtt_topic(&self) -> &str {
+    &self.mqtt_topic
+  }
+
+  pub(crate) fn mqtt_discovery_prefix(&self) -> &str {
+    &self.mqtt_discovery_prefix
+  }
+
+  pub(crate) fn interval(&self) -> Option<u64> {
+    self.interval
+  }
+
+  pub(crate) fn metrics_listen(&self) -> Option<SocketAddr> {
+    self.metrics_listen
+  }
+
+  pub(crate) fn log_format(&self) -> LogFormat {
+    self.log_format
+  }
+
+  pub(crate) fn history_file(&self) -> PathBuf {
+    state_file(self.history_file.as_deref(), "history.jsonl")
+  }
+
+  pub(crate) fn report_format(&self) -> ReportFormat {
+    self.report_format
+  }
+
+  pub(crate) fn session_cache(&self) -> Option<&Path> {
+    self.session_cache.as_deref()
+  }
+
+  pub(crate) fn session_lifetime(&self) -> u64 {
+    self.session_lifetime
+  }
+
+  pub(crate) fn concurrency(&self) -> usize {
+    self.concurrency.max(1)
+  }
+
+  pub(crate) fn requests_per_minute(&self) -> usize {
+    self.requests_per_minute
+  }
+
+  pub(crate) fn command(&self) -> Option<&Command> {
+    self.command.as_ref()
+  }
 }
 
 #[cfg(test)]
@@ -135,4 +1051,143 @@ mod test {
 
     assert_eq!(domains.len(), 3);
   }
+
+  #[test]
+  fn serialize_domain_providers() {
+    let domain = DNSEntry::from_str("hetzner/example.com: @, www").unwrap();
+    assert_eq!(Some(ProviderKind::Hetzner), domain.provider());
+    assert_eq!("example.com", domain.domain());
+    assert_eq!(
+      vec!["@", "www"],
+      domain
+        .sub_domains()
+        .iter()
+        .map(HostName::as_str)
+        .collect::<Vec<_>>()
+    );
+
+    let domain = DNSEntry::from_str("example.com: www").unwrap();
+    assert_eq!(None, domain.provider());
+
+    assert!(DNSEntry::from_str("unknown/example.com: www").is_err());
+  }
+
+  #[test]
+  fn normalize_domains() {
+    let domain = DNSEntry::from_str("Example.COM.: WWW, mail.example.com., , *").unwrap();
+    assert_eq!("example.com", domain.domain());
+    assert_eq!(
+      vec!["www", "mail", "@", "*"],
+      domain
+        .sub_domains()
+        .iter()
+        .map(HostName::as_str)
+        .collect::<Vec<_>>()
+    );
+
+    let host = DNSEntry::from_str("NAS.home.example.co.uk.").unwrap();
+    assert_eq!("nas.home.example.co.uk", host.domain());
+    assert!(host.sub_domains().is_empty());
+
+    let domain = DNSEntry::from_str("Müller-Bau.de: Büro, büro.müller-bau.de").unwrap();
+    assert_eq!("xn--mller-bau-q9a.de", domain.domain());
+    assert_eq!(
+      vec!["xn--bro-hoa", "xn--bro-hoa"],
+      domain
+        .sub_domains()
+        .iter()
+        .map(HostName::as_str)
+        .collect::<Vec<_>>()
+    );
+    assert!(matches!(
+      DNSEntry::from_str("example.com: www, -invalid"),
+      Err(Errors::InvalidDomainName(..))
+    ));
+
+    let mut zone = host.in_zone("example.co.uk");
+    assert_eq!("example.co.uk", zone.domain());
+    zone.merge(DNSEntry::from_str("example.co.uk: nas.home, www").unwrap());
+    assert_eq!(
+      vec!["nas.home", "www"],
+      zone
+        .sub_domains()
+        .iter()
+        .map(HostName::as_str)
+        .collect::<Vec<_>>()
+    );
+  }
+
+  #[test]
+  fn parse_lego_exec_arguments() {
+    let cli = Cli::from_iter_safe([
+      "netcup-dns-updater",
+      "acme",
+      "present",
+      "_acme-challenge.example.com.",
+      "token",
+    ])
+    .unwrap();
+
+    match cli.command() {
+      Some(Command::Acme(AcmeCommand::Present(challenge))) => {
+        assert_eq!("_acme-challenge.example.com.", challenge.fqdn());
+        assert_eq!("token", challenge.token());
+        assert_eq!(None, challenge.zone());
+      }
+      command => panic!("Unexpected command {command:?}"),
+    }
+  }
+
+  #[test]
+  fn parse_sync_targets() {
+    assert_eq!(
+      Some(SyncTarget::Provider(ProviderKind::Hetzner)),
+      "hetzner".parse().ok()
+    );
+    assert_eq!(
+      Some(SyncTarget::ZoneFile("/var/lib/bind/{zone}.db".into())),
+      "file:/var/lib/bind/{zone}.db".parse().ok()
+    );
+    assert!("file:".parse::<SyncTarget>().is_err());
+    assert!("unknown".parse::<SyncTarget>().is_err());
+    assert!("netcup".parse::<SyncTarget>().is_err());
+  }
+
+  #[test]
+  fn parse_raw_params() {
+    let param = RawParam::from_str("messagecount=5").unwrap();
+    assert_eq!("messagecount", param.key());
+    assert_eq!(&serde_json::json!(5), param.value());
+
+    let param = RawParam::from_str("domainname=example.domain").unwrap();
+    assert_eq!(&serde_json::json!("example.domain"), param.value());
+
+    let param = RawParam::from_str(r#"dnsrecordset={"dnsrecords": []}"#).unwrap();
+    assert_eq!(&serde_json::json!({ "dnsrecords": [] }), param.value());
+
+    let param = RawParam::from_str("keepdnssecrecords=").unwrap();
+    assert_eq!(&serde_json::json!(""), param.value());
+
+    assert!(RawParam::from_str("domainname").is_err());
+    assert!(RawParam::from_str("=example.domain").is_err());
+  }
+
+  #[test]
+  fn state_files() {
+    assert_eq!(
+      PathBuf::from("/var/lib/ddns/history.jsonl"),
+      state_file(
+        Some(Path::new("/var/lib/ddns/history.jsonl")),
+        "history.jsonl"
+      )
+    );
+    assert_eq!(
+      state_dir().join("history.jsonl"),
+      state_file(None, "history.jsonl")
+    );
+    assert_eq!(
+      state_dir().join("smtp-state.json"),
+      state_file(Some(Path::new("")), "smtp-state.json")
+    );
+  }
 }
@@ -0,0 +1,10 @@
+pub mod acme;
+pub mod acme_dns;
+pub mod handles;
+pub mod history;
+pub mod nameservers;
+pub mod poll;
+pub mod raw;
+pub mod sync;
+pub mod update;
+pub mod zone;
@@ -0,0 +1,137 @@
+use error_stack::{IntoReport, Report};
+use tracing::info;
+
+use crate::{
+  api::netcup::{
+    models::{ApiSessionId, DnsRecord, RecordType},
+    Client, StatusCode,
+  },
+  cli::{AcmeChallenge, AcmeCommand, Cli},
+  errors::Errors,
+  hostname::to_ascii,
+  propagation::Verifier,
+  provider::{ProviderKind, Providers},
+};
+
+const CHALLENGE_LABEL: &str = "_acme-challenge";
+
+pub async fn run(
+  client: &Client<ApiSessionId>,
+  cli: &Cli,
+  command: &AcmeCommand,
+) -> error_stack::Result<(), Errors> {
+  let (challenge, present) = match command {
+    AcmeCommand::Present(challenge) => (challenge, true),
+    AcmeCommand::Cleanup(challenge) => (challenge, false),
+  };
+
+  let name = to_ascii(&challenge_name(challenge.fqdn())).into_report()?;
+  let zone = match challenge.zone() {
+    Some(zone) => to_ascii(zone).into_report()?,
+    None => {
+      // The zone of the validated domain, which might be a zone itself.
+      let domain = name
+        .strip_prefix(&format!("{CHALLENGE_LABEL}."))
+        .unwrap_or(&name);
+      Providers::from_client(cli, client)
+        .zone_of(ProviderKind::Netcup, domain, cli.domains())
+        .await
+        .ok_or_else(|| Report::new(Errors::DNSZoneNotFound(name.clone())))?
+    }
+  };
+  let host_name = match name.strip_suffix(&format!(".{zone}")) {
+    Some(host_name) => host_name,
+    None => return Err(Report::new(Errors::DNSZoneNotFound(name.clone()))),
+  };
+
+  let existing = client
+    .info_dns_records(zone.as_str())
+    .await?
+    .response_data()
+    .map(|data| data.dns_records().clone())
+    .unwrap_or_default()
+    .into_iter()
+    .filter(|record| is_challenge(record, host_name, challenge))
+    .collect::<Vec<_>>();
+
+  let changes = if present {
+    if !existing.is_empty() {
+      info!("The challenge {name} is already present");
+      return Ok(());
+    }
+
+    info!("Creating the challenge {name} in zone {zone}");
+    vec![DnsRecord::other(
+      host_name,
+      RecordType::Other("TXT".into()),
+      challenge.token(),
+    )]
+  } else {
+    if existing.is_empty() {
+      info!("The challenge {name} is already cleaned up");
+      return Ok(());
+    }
+
+    info!("Deleting the challenge {name} in zone {zone}");
+    existing
+      .into_iter()
+      .map(|mut record| {
+        record.delete_record_mut(true);
+        record
+      })
+      .collect()
+  };
+
+  let update_dns_records_response = client
+    .update_dns_records(zone.as_str(), changes.clone())
+    .await?;
+  if update_dns_records_response.status_code() != StatusCode::Success {
+    return Err(Report::new(Errors::UpdateDNSRecords(zone)));
+  }
+
+  if let Some(verifier) = Verifier::from_cli(cli) {
+    verifier.wait(&zone, &changes).await?;
+  }
+
+  Ok(())
+}
+
+/// certbot passes the validated domain, which might be a wildcard, and lego
+/// the FQDN of the challenge with a trailing dot.
+pub fn challenge_name(fqdn: &str) -> String {
+  let fqdn = fqdn.trim().trim_end_matches('.').to_lowercase();
+  let fqdn = fqdn.strip_prefix("*.").unwrap_or(&fqdn);
+
+  if fqdn.starts_with(&format!("{CHALLENGE_LABEL}.")) {
+    fqdn.to_string()
+  } else {
+    format!("{CHALLENGE_LABEL}.{fqdn}")
+  }
+}
+
+fn is_challenge(record: &DnsRecord, host_name: &str, challenge: &AcmeChallenge) -> bool {
+  record.host_name().eq_ignore_ascii_case(host_name)
+    && matches!(record.record_type(), RecordType::Other(record_type) if record_type.eq_ignore_ascii_case("TXT"))
+    && record.destination().to_string().trim_matches('"') == challenge.token()
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn challenge_names() {
+    assert_eq!(
+      "_acme-challenge.example.com",
+      challenge_name("_acme-challenge.example.com.")
+    );
+    assert_eq!(
+      "_acme-challenge.example.com",
+      challenge_name("*.example.com")
+    );
+    assert_eq!(
+      "_acme-challenge.www.example.com",
+      challenge_name("WWW.example.com")
+    );
+  }
+}
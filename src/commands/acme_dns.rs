@@ -0,0 +1,28 @@
+use std::{net::SocketAddr, path::Path};
+
+use crate::{
+  api::netcup,
+  cli::Cli,
+  errors::Errors,
+  server::{
+    self,
+    acme_dns::{registrations::Registrations, AcmeDns},
+  },
+};
+
+pub async fn run(
+  cli: &Cli,
+  listen: SocketAddr,
+  zone: &str,
+  storage: &Path,
+  registration: bool,
+) -> error_stack::Result<(), Errors> {
+  let acme_dns = AcmeDns::new(
+    netcup::Client::new(cli)?,
+    zone,
+    registration,
+    Registrations::load(storage).await?,
+  );
+
+  server::serve(listen, acme_dns).await
+}
@@ -0,0 +1,90 @@
+use error_stack::{IntoReport, Report, ResultExt};
+use tracing::info;
+
+use crate::{
+  api::netcup::{
+    models::{info_handle::HandleData, ApiSessionId},
+    Client,
+  },
+  cli::HandlesCommand,
+  errors::Errors,
+};
+
+pub async fn run(
+  client: &Client<ApiSessionId>,
+  command: &HandlesCommand,
+) -> error_stack::Result<(), Errors> {
+  match command {
+    HandlesCommand::List => {
+      let listall_handle_response = client.listall_handle().await?;
+
+      for handle in listall_handle_response
+        .response_data()
+        .into_iter()
+        .flatten()
+      {
+        println!(
+          "{}\t{}\t{}\t{}",
+          handle.id(),
+          handle
+            .data()
+            .handle_type()
+            .map(|handle_type| handle_type.to_string())
+            .unwrap_or_default(),
+          handle.data().name().unwrap_or_default(),
+          handle.data().email().unwrap_or_default()
+        );
+      }
+    }
+    HandlesCommand::Show { id } => {
+      let info_handle_response = client.info_handle(*id).await?;
+      let handle = info_handle_response
+        .response_data()
+        .ok_or_else(|| Report::new(Errors::InfoHandle(*id)))?;
+
+      println!(
+        "{}",
+        serde_json::to_string_pretty(handle)
+          .into_report()
+          .change_context(Errors::SerializeResponse)?
+      );
+    }
+    HandlesCommand::Create { handle } => {
+      let handle = HandleData::from(handle);
+
+      let missing_fields = handle.missing_fields();
+      if !missing_fields.is_empty() {
+        return Err(Report::new(Errors::MissingHandleFields(
+          missing_fields.join(", "),
+        )));
+      }
+
+      let create_handle_response = client.create_handle(handle).await?;
+      let handle = create_handle_response
+        .response_data()
+        .ok_or_else(|| Report::new(Errors::CreateHandle))?;
+
+      info!("Created handle {}", handle.id());
+      println!("{}", handle.id());
+    }
+    HandlesCommand::Update { id, handle } => {
+      let info_handle_response = client.info_handle(*id).await?;
+      let existing = info_handle_response
+        .response_data()
+        .ok_or_else(|| Report::new(Errors::InfoHandle(*id)))?;
+
+      client
+        .update_handle(*id, existing.data().clone().merge(handle.into()))
+        .await?;
+
+      info!("Updated handle {id}");
+    }
+    HandlesCommand::Delete { id } => {
+      client.delete_handle(*id).await?;
+
+      info!("Deleted handle {id}");
+    }
+  }
+
+  Ok(())
+}
@@ -0,0 +1,15 @@
+use crate::{
+  errors::Errors,
+  history::{self, Format, History, Query},
+};
+
+pub async fn run(
+  history: &History,
+  query: &Query,
+  format: Format,
+) -> error_stack::Result<(), Errors> {
+  let changes = history.query(query).await?;
+  print!("{}", history::export(&changes, format)?);
+
+  Ok(())
+}
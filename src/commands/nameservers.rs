@@ -0,0 +1,175 @@
+use error_stack::{IntoReport, Report, ResultExt};
+use tracing::{info, warn};
+
+use crate::{
+  api::netcup::{
+    models::{info_domain::NameserverEntries, ApiSessionId},
+    Client,
+  },
+  cli::{Cli, DNSEntry, NameserversCommand},
+  errors::Errors,
+  hostname::to_ascii,
+  provider::{ProviderKind, Providers},
+};
+
+pub async fn run(
+  client: &Client<ApiSessionId>,
+  cli: &Cli,
+  command: &NameserversCommand,
+) -> error_stack::Result<(), Errors> {
+  match command {
+    NameserversCommand::Show { domain } => {
+      let info_domain_response = client.info_domain(domain).await?;
+      let domain_info = info_domain_response
+        .response_data()
+        .ok_or_else(|| Report::new(Errors::InfoDomain(domain.clone())))?;
+
+      domain_info
+        .nameservers()
+        .nameservers()
+        .iter()
+        .for_each(|nameserver| println!("{nameserver}"));
+    }
+    NameserversCommand::Set {
+      domain,
+      nameservers,
+      force,
+    } => {
+      // The configured zones are punycode without a trailing dot.
+      let domain = &to_ascii(domain).into_report()?;
+      let nameservers = NameserverEntries::new(nameservers.clone());
+      let zones = managed_zones(cli, client).await;
+      check_managed_records(&zones, domain, &nameservers, *force)?;
+
+      let info_domain_response = client.info_domain(domain).await?;
+      let domain_info = info_domain_response
+        .response_data()
+        .ok_or_else(|| Report::new(Errors::InfoDomain(domain.clone())))?;
+
+      info!(
+        "Changing nameservers of {domain} from {:?} to {:?}",
+        domain_info
+          .nameservers()
+          .nameservers()
+          .iter()
+          .map(|nameserver| nameserver.to_string())
+          .collect::<Vec<_>>(),
+        nameservers
+          .nameservers()
+          .iter()
+          .map(|nameserver| nameserver.to_string())
+          .collect::<Vec<_>>()
+      );
+
+      client
+        .update_domain(domain, domain_info.contacts().clone(), nameservers)
+        .await
+        .attach_printable(format!("Could not change the nameservers of {domain}"))?;
+
+      info!("Updated nameservers of {domain}!");
+    }
+  }
+
+  Ok(())
+}
+
+/// The zones updated through Netcup, including the zones of host entries.
+/// Zones hosted by other providers don't depend on the Netcup nameservers.
+async fn managed_zones(cli: &Cli, client: &Client<ApiSessionId>) -> Vec<DNSEntry> {
+  let providers = Providers::from_client(cli, client);
+  providers
+    .zones(cli.domains())
+    .await
+    .into_iter()
+    .filter(|zone| providers.kind_of(zone) == ProviderKind::Netcup)
+    .collect()
+}
+
+/// DDNS records can only be updated through the Netcup API as long as the
+/// domain is delegated to the Netcup nameservers, so moving a managed zone
+/// away would silently stop the updates from having any effect.
+fn check_managed_records(
+  domains: &[DNSEntry],
+  domain: &str,
+  nameservers: &NameserverEntries,
+  force: bool,
+) -> error_stack::Result<(), Errors> {
+  if nameservers.is_netcup() {
+    return Ok(());
+  }
+
+  let managed = domains
+    .iter()
+    .any(|entry| entry.domain().eq_ignore_ascii_case(domain) && !entry.sub_domains().is_empty());
+
+  if !managed {
+    return Ok(());
+  }
+
+  if force {
+    warn!(
+      "Moving {domain} away from the Netcup nameservers although DDNS records are managed for it"
+    );
+    return Ok(());
+  }
+
+  Err(Report::new(Errors::NameserverChangeRefused(
+    domain.to_string(),
+  )))
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::api::netcup::mock;
+
+  fn nameservers(host_names: &[&str]) -> NameserverEntries {
+    NameserverEntries::new(
+      host_names
+        .iter()
+        .map(|host_name| host_name.parse().unwrap())
+        .collect(),
+    )
+  }
+
+  #[test]
+  fn refuse_moving_managed_zone() {
+    let domains = vec![
+      "example.domain: @, www".parse::<DNSEntry>().unwrap(),
+      "unmanaged.domain".parse::<DNSEntry>().unwrap(),
+      "müller-bau.de: @".parse::<DNSEntry>().unwrap(),
+      "nas.host.domain"
+        .parse::<DNSEntry>()
+        .unwrap()
+        .in_zone("host.domain"),
+    ];
+    let external = nameservers(&["ns1.example.domain=192.0.2.1", "ns2.example.domain"]);
+    let netcup = nameservers(&["root-dns.netcup.net", "second-dns.netcup.net"]);
+
+    assert!(check_managed_records(&domains, "EXAMPLE.domain", &external, false).is_err());
+    assert!(check_managed_records(&domains, "example.domain", &external, true).is_ok());
+    assert!(check_managed_records(&domains, "example.domain", &netcup, false).is_ok());
+    assert!(check_managed_records(&domains, "unmanaged.domain", &external, false).is_ok());
+    assert!(check_managed_records(&domains, "other.domain", &external, false).is_ok());
+    assert!(check_managed_records(&domains, "host.domain", &external, false).is_err());
+    let unicode = to_ascii("Müller-Bau.de.").unwrap();
+    assert!(check_managed_records(&domains, &unicode, &external, false).is_err());
+  }
+
+  #[tokio::test]
+  async fn ignore_zones_of_other_providers() {
+    let netcup = mock::server().await;
+    let cli = mock::cli(
+      &netcup,
+      &["example.domain: @", "cloudflare/moved.domain: @, www"],
+    );
+    let client = Client::new(&cli).unwrap().login().await.unwrap();
+
+    let zones = managed_zones(&cli, &client).await;
+    let external = nameservers(&["ns1.example.net", "ns2.example.net"]);
+    assert!(check_managed_records(&zones, "example.domain", &external, false).is_err());
+    assert!(check_managed_records(&zones, "moved.domain", &external, false).is_ok());
+
+    client.logout().await.unwrap();
+  }
+}
@@ -0,0 +1,149 @@
+use error_stack::{IntoReport, Report, ResultExt};
+use tracing::{error, info};
+
+use crate::{
+  api::netcup::{
+    models::{poll::PollMessage, ApiSessionId},
+    Client,
+  },
+  errors::Errors,
+};
+
+pub async fn run(
+  client: &Client<ApiSessionId>,
+  count: u32,
+  forward_url: Option<&str>,
+  no_ack: bool,
+) -> error_stack::Result<(), Errors> {
+  let poll_response = client.poll(count).await?;
+  let messages = poll_response.response_data().cloned().unwrap_or_default();
+
+  if messages.is_empty() {
+    info!("No unread messages");
+    return Ok(());
+  }
+
+  let http_client = reqwest::Client::new();
+  let mut failures: Option<Report<Errors>> = None;
+
+  for message in messages {
+    info!("Message {}: {}", message.id(), message.message());
+    println!(
+      "{}",
+      serde_json::to_string(&message)
+        .into_report()
+        .change_context(Errors::SerializeResponse)?
+    );
+
+    if let Some(forward_url) = forward_url {
+      // A message which couldn't be forwarded stays unacknowledged so it is
+      // delivered again on the next poll.
+      if let Err(e) = forward(&http_client, forward_url, &message).await {
+        error!("{e:?}");
+        match failures.as_mut() {
+          Some(failures) => failures.extend_one(e),
+          None => failures = Some(e),
+        }
+        continue;
+      }
+    }
+
+    if !no_ack {
+      client.ack_poll(message.id()).await?;
+      info!("Acknowledged message {}", message.id());
+    }
+  }
+
+  match failures {
+    Some(failures) => Err(failures),
+    None => Ok(()),
+  }
+}
+
+async fn forward(
+  http_client: &reqwest::Client,
+  url: &str,
+  message: &PollMessage,
+) -> error_stack::Result<(), Errors> {
+  let response = http_client
+    .post(url)
+    .json(message)
+    .send()
+    .await
+    .into_report()
+    .change_context(Errors::ForwardPollMessage(message.id()))?;
+
+  if !response.status().is_success() {
+    return Err(
+      Report::new(Errors::ForwardPollMessage(message.id()))
+        .attach_printable(format!("Http status code {}", response.status())),
+    );
+  }
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod test {
+  use serde_json::json;
+  use wiremock::{
+    matchers::{body_partial_json, method},
+    Mock, MockServer, ResponseTemplate,
+  };
+
+  use crate::api::netcup::mock;
+
+  use super::*;
+
+  #[tokio::test]
+  async fn keep_messages_which_could_not_be_forwarded() {
+    let netcup = mock::server().await;
+    let cli = mock::cli(&netcup, &[]);
+
+    mock::action("poll", json!({ "messagecount": 10 }))
+      .respond_with(mock::response(
+        "poll",
+        json!([
+          { "id": "1", "message": "Domain example.com transferred" },
+          { "id": "2", "message": "Domain example.org transferred" }
+        ]),
+      ))
+      .mount(&netcup)
+      .await;
+    mock::action("ackPoll", json!({ "apollid": 1 }))
+      .respond_with(mock::response("ackPoll", json!({})))
+      .expect(1)
+      .mount(&netcup)
+      .await;
+    mock::action("ackPoll", json!({ "apollid": 2 }))
+      .respond_with(mock::response("ackPoll", json!({})))
+      .expect(0)
+      .mount(&netcup)
+      .await;
+
+    let receiver = MockServer::start().await;
+    Mock::given(method("POST"))
+      .and(body_partial_json(json!({ "id": 1 })))
+      .respond_with(ResponseTemplate::new(200))
+      .expect(1)
+      .mount(&receiver)
+      .await;
+    Mock::given(method("POST"))
+      .and(body_partial_json(json!({ "id": 2 })))
+      .respond_with(ResponseTemplate::new(503))
+      .expect(1)
+      .mount(&receiver)
+      .await;
+
+    let client = Client::new(&cli).unwrap().login().await.unwrap();
+    let error = run(&client, 10, Some(&receiver.uri()), false)
+      .await
+      .unwrap_err();
+    client.logout().await.unwrap();
+
+    assert!(matches!(
+      error.current_context(),
+      Errors::ForwardPollMessage(2)
+    ));
+  }
+}
@@ -0,0 +1,90 @@
+use error_stack::{IntoReport, Report, ResultExt};
+use serde_json::{Map, Value};
+
+use crate::{
+  api::netcup::{models::ApiSessionId, Client, StatusCode},
+  cli::RawParam,
+  errors::Errors,
+};
+
+pub async fn run(
+  client: &Client<ApiSessionId>,
+  action: &str,
+  params: &[RawParam],
+) -> error_stack::Result<(), Errors> {
+  let params = params
+    .iter()
+    .map(|param| (param.key().to_owned(), param.value().clone()))
+    .collect::<Map<_, _>>();
+
+  let response = client.call_raw(action, Value::Object(params)).await?;
+
+  println!(
+    "{}",
+    serde_json::to_string_pretty(&response)
+      .into_report()
+      .change_context(Errors::SerializeResponse)?
+  );
+
+  // The response is printed either way, a failed action still fails the call.
+  if response.status_code() != StatusCode::Success {
+    return Err(
+      Report::new(Errors::CallRaw(action.to_string())).attach_printable(response.server_message()),
+    );
+  }
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod test {
+  use serde_json::json;
+  use wiremock::ResponseTemplate;
+
+  use crate::api::netcup::mock;
+
+  use super::*;
+
+  #[tokio::test]
+  async fn fail_on_failed_actions() {
+    let netcup = mock::server().await;
+    let cli = mock::cli(&netcup, &[]);
+
+    mock::action("listallDomains", json!({}))
+      .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+        "serverrequestid": "SUPERSECRETSERVERREQUESTID",
+        "clientrequestid": "",
+        "action": "listallDomains",
+        "status": "error",
+        "statuscode": 4013,
+        "shortmessage": "Validation Error.",
+        "longmessage": "",
+        "responsedata": ""
+      })))
+      .mount(&netcup)
+      .await;
+    mock::action("infoDnsZone", json!({ "domainname": "example.com" }))
+      .respond_with(mock::response(
+        "infoDnsZone",
+        json!({ "name": "example.com" }),
+      ))
+      .mount(&netcup)
+      .await;
+
+    let client = Client::new(&cli).unwrap().login().await.unwrap();
+    let failed = run(&client, "listallDomains", &[]).await;
+    let succeeded = run(
+      &client,
+      "infoDnsZone",
+      &["domainname=example.com".parse().unwrap()],
+    )
+    .await;
+    client.logout().await.unwrap();
+
+    assert!(matches!(
+      failed.unwrap_err().current_context(),
+      Errors::CallRaw(action) if action == "listallDomains"
+    ));
+    assert!(succeeded.is_ok());
+  }
+}
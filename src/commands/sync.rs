@@ -0,0 +1,486 @@
+//! Mirrors Netcup zones to other providers or zone files, e.g. for a secondary
+//! provider or a hidden primary.
+//!
+//! The records of the Netcup zone are the master. Records are matched by host,
+//! type and value, every record a target has in addition is removed. Zone
+//! files only contain the records without the SOA so they can be included in
+//! the zone of the hidden primary with `$INCLUDE`.
+
+use std::{collections::BTreeSet, fmt};
+
+use error_stack::{IntoReport, Report, ResultExt};
+use tracing::{error, info, warn};
+
+use crate::{
+  api::netcup::models::{DnsRecord, RecordType},
+  cli::{Cli, SyncTarget},
+  errors::Errors,
+  provider::{DnsProvider, ProviderKind, Providers},
+  report::{Outcome, RunReport},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+  Create(DnsRecord),
+  Update { from: DnsRecord, to: DnsRecord },
+  Delete(DnsRecord),
+}
+
+impl Change {
+  /// The record which applies the change on the target.
+  fn into_record(self) -> DnsRecord {
+    match self {
+      Self::Create(record) => copy(&record),
+      Self::Update { from, to } => {
+        let mut record = copy(&to);
+        if let Some(id) = from.id() {
+          record.id_mut(id);
+        }
+        record
+      }
+      Self::Delete(mut record) => {
+        record.delete_record_mut(true);
+        record
+      }
+    }
+  }
+}
+
+impl fmt::Display for Change {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::Create(record) => write!(f, "+ {}", describe(record)),
+      Self::Update { from, to } => write!(f, "~ {} -> {}", describe(from), to.destination()),
+      Self::Delete(record) => write!(f, "- {}", describe(record)),
+    }
+  }
+}
+
+/// Mirrors the Netcup zones of the domains to the `--mirror` targets.
+pub async fn mirror(providers: &Providers, cli: &Cli) -> error_stack::Result<RunReport, Errors> {
+  run(providers, cli, &[], cli.mirrors(), false).await
+}
+
+/// Mirrors `zones`, or the Netcup zones of the domains if empty, to `targets`.
+/// The report has an entry for every mirror of a zone, the failures of single
+/// zones and mirrors are reported instead of aborting the run. Several zones
+/// can't be mirrored to the same zone file, its path needs `{zone}` then.
+pub async fn run(
+  providers: &Providers,
+  cli: &Cli,
+  zones: &[String],
+  targets: &[SyncTarget],
+  dry_run: bool,
+) -> error_stack::Result<RunReport, Errors> {
+  let master = providers.kind(ProviderKind::Netcup)?;
+
+  let zones = if zones.is_empty() {
+    providers
+      .zones(cli.domains())
+      .await
+      .iter()
+      .filter(|domain| providers.kind_of(domain) == ProviderKind::Netcup)
+      .map(|domain| domain.domain().to_string())
+      .collect::<Vec<_>>()
+  } else {
+    zones.to_vec()
+  };
+
+  if zones.len() > 1 {
+    if let Some(path) = targets.iter().find_map(|target| match target {
+      SyncTarget::ZoneFile(path) if !path.to_string_lossy().contains("{zone}") => Some(path),
+      _ => None,
+    }) {
+      return Err(Report::new(Errors::SharedZoneFile(
+        path.display().to_string(),
+      )));
+    }
+  }
+
+  let mut report = RunReport::default();
+  for zone in &zones {
+    let records = match master.records(zone).await {
+      Ok(records) => records,
+      Err(e) => {
+        error!("{e:?}");
+        report.failed(Some(zone), None, &e);
+        continue;
+      }
+    };
+
+    for target in targets {
+      let name = match target {
+        SyncTarget::Provider(kind) => format!("{kind:?}"),
+        SyncTarget::ZoneFile(path) => path.display().to_string(),
+      };
+
+      let result = match target {
+        SyncTarget::Provider(kind) => match providers.kind(*kind) {
+          Ok(provider) => sync_provider(provider, zone, &records, dry_run).await,
+          Err(e) => Err(e),
+        },
+        SyncTarget::ZoneFile(path) => {
+          let path = path.to_string_lossy().replace("{zone}", zone);
+          match master.zone_settings(zone).await {
+            Ok(settings) => sync_zone_file(&path, zone, settings.ttl(), &records, dry_run).await,
+            Err(e) => Err(e),
+          }
+        }
+      };
+
+      match result.change_context_lazy(|| Errors::Sync(zone.clone(), name.clone())) {
+        Ok(0) => {
+          info!("Mirror {name} of {zone} is up to date");
+          report.record(zone, &name, Outcome::Unchanged);
+        }
+        Ok(changes) => {
+          info!("Found {changes} differences between {zone} and its mirror {name}");
+          let outcome = if dry_run {
+            Outcome::Unchanged
+          } else {
+            Outcome::Updated
+          };
+          report.record(zone, &name, outcome);
+        }
+        Err(e) => {
+          error!("{e:?}");
+          report.failed(Some(zone), Some(&name), &e);
+        }
+      }
+    }
+  }
+
+  Ok(report)
+}
+
+async fn sync_provider(
+  provider: &dyn DnsProvider,
+  zone: &str,
+  records: &[DnsRecord],
+  dry_run: bool,
+) -> error_stack::Result<usize, Errors> {
+  let changes = diff(records, &provider.records(zone).await?);
+
+  for change in &changes {
+    warn!("{zone} on {}: {change}", provider.name());
+  }
+
+  if !changes.is_empty() && !dry_run {
+    let count = changes.len();
+    provider
+      .apply(zone, changes.into_iter().map(Change::into_record).collect())
+      .await?;
+    return Ok(count);
+  }
+
+  Ok(changes.len())
+}
+
+async fn sync_zone_file(
+  path: &str,
+  zone: &str,
+  ttl: u32,
+  records: &[DnsRecord],
+  dry_run: bool,
+) -> error_stack::Result<usize, Errors> {
+  let content = render_zone_file(zone, ttl, records);
+  let current = tokio::fs::read_to_string(path).await.unwrap_or_default();
+
+  let lines = |content: &str| {
+    content
+      .lines()
+      .filter(|line| !line.is_empty() && !line.starts_with(';'))
+      .map(str::to_string)
+      .collect::<BTreeSet<_>>()
+  };
+  let (new, old) = (lines(&content), lines(&current));
+
+  let changes = new
+    .difference(&old)
+    .map(|line| format!("+ {line}"))
+    .chain(old.difference(&new).map(|line| format!("- {line}")))
+    .collect::<Vec<_>>();
+
+  for change in &changes {
+    warn!("{zone} in {path}: {change}");
+  }
+
+  if !changes.is_empty() && !dry_run {
+    tokio::fs::write(path, content)
+      .await
+      .into_report()
+      .change_context(Errors::WriteZoneFile(path.to_string()))?;
+  }
+
+  Ok(changes.len())
+}
+
+/// The changes which turn the records of `target` into the ones of `master`.
+pub fn diff(master: &[DnsRecord], target: &[DnsRecord]) -> Vec<Change> {
+  let mut unmatched = target.iter().collect::<Vec<_>>();
+
+  let mut missing = vec![];
+  for record in master {
+    match unmatched
+      .iter()
+      .position(|other| key(other) == key(record) && value(other) == value(record))
+    {
+      Some(index) => {
+        unmatched.remove(index);
+      }
+      None => missing.push(record),
+    }
+  }
+
+  let mut changes = vec![];
+  for record in missing {
+    match unmatched.iter().position(|other| key(other) == key(record)) {
+      Some(index) => changes.push(Change::Update {
+        from: unmatched.remove(index).clone(),
+        to: record.clone(),
+      }),
+      None => changes.push(Change::Create(record.clone())),
+    }
+  }
+  changes.extend(unmatched.into_iter().cloned().map(Change::Delete));
+
+  changes
+}
+
+/// Renders the records as zone file relative to `zone`.
+pub fn render_zone_file(zone: &str, ttl: u32, records: &[DnsRecord]) -> String {
+  let mut content = format!(
+    "; Mirrored from the Netcup zone {zone}, changes are overwritten.\n$ORIGIN {}.\n$TTL {ttl}\n",
+    zone.trim_end_matches('.')
+  );
+
+  for record in records {
+    let host_name = match record.host_name() {
+      "" => "@",
+      host_name => host_name,
+    };
+    let record_type = record.record_type().to_string().to_uppercase();
+    let destination = record.destination().to_string();
+
+    let data = match record_type.as_str() {
+      "TXT" => format!("\"{}\"", destination.replace('"', "\\\"")),
+      "MX" => format!(
+        "{} {}",
+        record.priority().unwrap_or("10"),
+        absolute(&destination)
+      ),
+      "CNAME" | "NS" => absolute(&destination),
+      _ => destination,
+    };
+
+    content.push_str(&format!("{host_name}\tIN\t{record_type}\t{data}\n"));
+  }
+
+  content
+}
+
+/// Netcup stores names without the trailing dot.
+fn absolute(name: &str) -> String {
+  if name == "@" || name.ends_with('.') || !name.contains('.') {
+    name.to_string()
+  } else {
+    format!("{name}.")
+  }
+}
+
+fn key(record: &DnsRecord) -> (String, String) {
+  (
+    record.host_name().to_lowercase(),
+    record.record_type().to_string().to_uppercase(),
+  )
+}
+
+fn value(record: &DnsRecord) -> (String, Option<&str>) {
+  let destination = record.destination().to_string();
+  let destination = match record.record_type() {
+    RecordType::Other(record_type) if record_type.eq_ignore_ascii_case("TXT") => destination,
+    _ => destination.trim_end_matches('.').to_lowercase(),
+  };
+
+  let priority = match record.record_type() {
+    RecordType::Other(record_type) if record_type.eq_ignore_ascii_case("MX") => record.priority(),
+    _ => None,
+  };
+
+  (destination, priority)
+}
+
+/// A copy of `record` without the id of the provider it was read from.
+fn copy(record: &DnsRecord) -> DnsRecord {
+  let mut copy = DnsRecord::other(
+    record.host_name(),
+    record.record_type().clone(),
+    record.destination().to_string(),
+  );
+  if let Some(priority) = record.priority() {
+    copy.priority_mut(priority);
+  }
+  copy
+}
+
+fn describe(record: &DnsRecord) -> String {
+  format!(
+    "{} {} {}",
+    record.host_name(),
+    record.record_type(),
+    record.destination()
+  )
+}
+
+#[cfg(test)]
+mod test {
+  use serde_json::json;
+
+  use super::*;
+  use crate::{
+    api::netcup::mock,
+    report::{ReportFormat, EXIT_PARTIAL_FAILURE},
+  };
+
+  fn with_id(mut record: DnsRecord, id: &str) -> DnsRecord {
+    record.id_mut(id);
+    record
+  }
+
+  #[test]
+  fn diff_records() {
+    let master = vec![
+      with_id(DnsRecord::new("@", "192.0.2.2".parse().unwrap()), "1"),
+      with_id(DnsRecord::new("www", "2001:db8::1".parse().unwrap()), "2"),
+      with_id(
+        DnsRecord::other("@", RecordType::Other("CNAME".into()), "Example.com"),
+        "3",
+      ),
+    ];
+    let target = vec![
+      with_id(DnsRecord::new("@", "192.0.2.1".parse().unwrap()), "a"),
+      with_id(
+        DnsRecord::other("@", RecordType::Other("CNAME".into()), "example.com."),
+        "b",
+      ),
+      with_id(DnsRecord::new("old", "192.0.2.1".parse().unwrap()), "c"),
+    ];
+
+    let changes = diff(&master, &target);
+    assert_eq!(
+      vec![
+        "~ @ A 192.0.2.1 -> 192.0.2.2",
+        "+ www AAAA 2001:db8::1",
+        "- old A 192.0.2.1"
+      ],
+      changes.iter().map(ToString::to_string).collect::<Vec<_>>()
+    );
+
+    let records = changes
+      .into_iter()
+      .map(Change::into_record)
+      .collect::<Vec<_>>();
+    assert_eq!(Some("a"), records[0].id());
+    assert_eq!(None, records[1].id());
+    assert!(records[2].delete_record());
+
+    assert!(diff(&master, &master).is_empty());
+  }
+
+  #[test]
+  fn render_zone_files() {
+    let mut mx = DnsRecord::other("@", RecordType::Other("MX".into()), "mail.example.com");
+    mx.priority_mut("20");
+
+    assert_eq!(
+      "; Mirrored from the Netcup zone example.com, changes are overwritten.\n\
+       $ORIGIN example.com.\n\
+       $TTL 300\n\
+       www\tIN\tA\t192.0.2.1\n\
+       @\tIN\tMX\t20 mail.example.com.\n\
+       _acme-challenge\tIN\tTXT\t\"token\"\n",
+      render_zone_file(
+        "example.com",
+        300,
+        &[
+          DnsRecord::new("www", "192.0.2.1".parse().unwrap()),
+          mx,
+          DnsRecord::other("_acme-challenge", RecordType::Other("TXT".into()), "token"),
+        ]
+      )
+    );
+  }
+
+  #[tokio::test]
+  async fn report_failed_zones() {
+    let netcup = mock::server().await;
+    let cli = mock::cli(&netcup, &["example.com: www", "example.org: www"]);
+
+    mock::action("infoDnsZone", json!({ "domainname": "example.com" }))
+      .respond_with(mock::response(
+        "infoDnsZone",
+        json!({
+          "name": "example.com", "ttl": "300", "serial": "1", "refresh": "28800",
+          "retry": "7200", "expire": "1209600", "dnssecstatus": false
+        }),
+      ))
+      .mount(&netcup)
+      .await;
+    mock::action("infoDnsRecords", json!({ "domainname": "example.com" }))
+      .respond_with(mock::response(
+        "infoDnsRecords",
+        json!({ "dnsrecords": [
+          { "id": "1", "hostname": "www", "type": "A", "destination": "192.0.2.1" }
+        ] }),
+      ))
+      .mount(&netcup)
+      .await;
+
+    let directory = std::env::temp_dir().join(format!("sync-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    let path = directory.join("{zone}.zone");
+    let providers = Providers::connect(&cli).await.unwrap();
+    let report = run(
+      &providers,
+      &cli,
+      &[],
+      &[SyncTarget::ZoneFile(path.clone())],
+      false,
+    )
+    .await
+    .unwrap();
+
+    let name = path.display().to_string();
+    let table = report.render(ReportFormat::Table);
+    assert!(
+      table.contains(&format!("example.com  {name}  updated")),
+      "{table}"
+    );
+    assert!(table.contains("example.org  *"), "{table}");
+    assert_eq!(EXIT_PARTIAL_FAILURE, report.exit_code());
+
+    let _ = std::fs::remove_dir_all(directory);
+  }
+
+  #[tokio::test]
+  async fn refuse_shared_zone_file() {
+    let netcup = mock::server().await;
+    let cli = mock::cli(&netcup, &["example.com: www"]);
+    let providers = Providers::connect(&cli).await.unwrap();
+
+    let error = run(
+      &providers,
+      &cli,
+      &["example.com".into(), "example.org".into()],
+      &[SyncTarget::ZoneFile("/var/lib/bind/db.zone".into())],
+      false,
+    )
+    .await
+    .unwrap_err();
+
+    assert!(matches!(
+      error.current_context(),
+      Errors::SharedZoneFile(path) if path == "/var/lib/bind/db.zone"
+    ));
+  }
+}
@@ -0,0 +1,495 @@
+use std::{net::IpAddr, sync::Mutex};
+
+use error_stack::{IntoReport, Report};
+use futures::{stream, StreamExt};
+use tokio::task::JoinHandle;
+use tracing::{debug, error, info, info_span, instrument, warn, Instrument};
+
+use crate::{
+  api::{
+    self,
+    netcup::models::{info_dns_zone::TTL_LIMITS, DnsRecord, IpType, RecordType},
+  },
+  cli::{Cli, DNSEntry},
+  errors::Errors,
+  history::{Change, History},
+  hostname::HostName,
+  notify::{Event, Notifiers},
+  propagation::Verifier,
+  provider::{DnsProvider, PartiallyApplied, Providers},
+  report::{Outcome, RunReport},
+};
+
+/// The propagation check of a zone running in the background.
+type Verification = JoinHandle<error_stack::Result<(), Errors>>;
+
+/// What every zone of a run shares.
+struct Run<'a> {
+  cli: &'a Cli,
+  ips: Vec<IpAddr>,
+  verifier: Option<Verifier>,
+  notifiers: &'a Notifiers,
+  history: &'a History,
+  /// The propagation checks of the zones, awaited once every zone is updated.
+  verifications: Mutex<Vec<(String, Verification)>>,
+}
+
+impl Run<'_> {
+  /// Logs and notifies the failure once and reports it for every host of
+  /// `hosts`, or for the whole `zone` if there are none.
+  fn failed(&self, report: &mut RunReport, zone: &str, hosts: &[&str], e: &Report<Errors>) {
+    error!(?hosts, "{e}");
+    match hosts {
+      [] => report.failed(Some(zone), None, e),
+      hosts => hosts
+        .iter()
+        .for_each(|host| report.failed(Some(zone), Some(host), e)),
+    }
+    self.notifiers.notify(Event::error(Some(zone), e));
+  }
+}
+
+/// Reconciles the records of every domain with the external addresses. The
+/// failures of single zones and hosts are reported instead of aborting the
+/// run.
+pub async fn run(
+  providers: &Providers,
+  cli: &Cli,
+  notifiers: &Notifiers,
+  history: &History,
+) -> RunReport {
+  let ips = api::ip::external().await;
+  ips.iter().for_each(|ip| info!(%ip, "Got IP"));
+  notifiers.detected(&ips);
+
+  reconcile(providers, cli, notifiers, history, ips).await
+}
+
+async fn reconcile(
+  providers: &Providers,
+  cli: &Cli,
+  notifiers: &Notifiers,
+  history: &History,
+  ips: Vec<IpAddr>,
+) -> RunReport {
+  let run = Run {
+    cli,
+    ips,
+    verifier: Verifier::from_cli(cli),
+    notifiers,
+    history,
+    verifications: Mutex::default(),
+  };
+
+  // The zones are updated concurrently, the reports keep their order.
+  let zones = providers.zones(cli.domains()).await;
+  let mut report: RunReport = stream::iter(&zones)
+    .map(|domain_zone| {
+      let run = &run;
+      let span = info_span!("zone", zone = domain_zone.domain());
+      async move {
+        let mut report = RunReport::default();
+        notifiers.processed(domain_zone.domain());
+        let result = match providers.get(domain_zone) {
+          Ok(provider) => zone(provider, domain_zone, run, &mut report).await,
+          Err(e) => Err(e),
+        };
+
+        if let Err(e) = result {
+          run.failed(&mut report, domain_zone.domain(), &[], &e);
+        }
+        report
+      }
+      .instrument(span)
+    })
+    .buffered(cli.concurrency())
+    .collect()
+    .await;
+
+  let verifications = run
+    .verifications
+    .lock()
+    .map(|mut verifications| std::mem::take(&mut *verifications))
+    .unwrap_or_default();
+  for (zone, verification) in verifications {
+    let result = verification.await.unwrap_or_else(|e| {
+      Err(Report::new(Errors::Propagation(zone.clone())).attach_printable(e.to_string()))
+    });
+    report.propagated(&zone, result.err().as_ref());
+  }
+
+  report
+}
+
+#[instrument(skip_all, fields(provider = provider.name()))]
+async fn zone(
+  provider: &dyn DnsProvider,
+  domain_zone: &DNSEntry,
+  run: &Run<'_>,
+  report: &mut RunReport,
+) -> error_stack::Result<(), Errors> {
+  let zone = domain_zone.domain();
+  info!(sub_domains = ?domain_zone.sub_domains(), "Looking at domain-zone");
+
+  let mut zone_settings = provider.zone_settings(zone).await?;
+
+  let current_ttl = zone_settings.ttl();
+  match run.cli.ttl() {
+    Some(ttl) if ttl != current_ttl => {
+      zone_settings.ttl_mut(ttl);
+      zone_settings.validate_ttl().into_report()?;
+      info!(from = current_ttl, to = ttl, "Changing TTL");
+      provider.update_zone_settings(zone, zone_settings).await?;
+      info!("Updated dns zone!");
+    }
+    Some(_) => {}
+    None if current_ttl > *TTL_LIMITS.start() => {
+      let message = format!("TTL is {current_ttl} and should be {}", TTL_LIMITS.start());
+      warn!(ttl = current_ttl, "{message}");
+      run.notifiers.notify(Event::warning(zone, message));
+    }
+    None => {}
+  }
+
+  info!("Getting all dns records");
+  let dns_records = provider.records(zone).await?;
+
+  let mut hosts = vec![];
+  for host in domain_zone.sub_domains() {
+    match changes(zone, host, &dns_records, run) {
+      Ok(changes) => hosts.push((host.as_str(), changes)),
+      Err(e) => run.failed(report, zone, &[host.as_str()], &e),
+    }
+  }
+  let changes = hosts
+    .iter()
+    .flat_map(|(_, changes)| changes)
+    .collect::<Vec<_>>();
+  if changes.is_empty() {
+    info!("Every record is up to date");
+    hosts
+      .iter()
+      .for_each(|(host, _)| report.record(zone, host, Outcome::Unchanged));
+    return Ok(());
+  }
+
+  // All changes of the zone are sent in one request. Providers which send
+  // them one by one tell how many were applied before a failure.
+  info!(count = changes.len(), "Applying the changes");
+  let records = changes
+    .iter()
+    .map(|(record, _)| record.clone())
+    .collect::<Vec<_>>();
+  let (applied, count, failure) = match provider.apply(zone, records).await {
+    Ok(applied) => (applied, changes.len(), None),
+    Err(e) => {
+      let partial = e
+        .downcast_ref::<PartiallyApplied>()
+        .cloned()
+        .unwrap_or_default();
+      (partial.applied().clone(), partial.count(), Some(e))
+    }
+  };
+
+  let mut failed = vec![];
+  let mut end = 0;
+  for (host, changes) in &hosts {
+    end += changes.len();
+    if !changes.is_empty() && end > count {
+      failed.push(*host);
+      continue;
+    }
+
+    let outcome = changes
+      .iter()
+      .map(|(record, old)| Outcome::of(record, *old))
+      .max()
+      .unwrap_or(Outcome::Unchanged);
+    report.record(zone, host, outcome);
+  }
+  if let Some(e) = &failure {
+    run.failed(report, zone, &failed, e);
+  }
+
+  let changes = &changes[..count];
+  if changes.is_empty() {
+    return Ok(());
+  }
+
+  let history = changes
+    .iter()
+    .map(|(record, old)| Change::new(zone, record, *old, &applied))
+    .collect::<Vec<_>>();
+  // The changes are applied already, a history which can't be written
+  // doesn't turn them into failures.
+  if let Err(e) = run.history.record(&history).await {
+    warn!("{e:?}");
+    let message = format!("The changes were applied but not recorded: {e}");
+    run.notifiers.notify(Event::warning(zone, message));
+  }
+
+  for (record, old) in changes {
+    if !record.delete_record() {
+      run.notifiers.published(zone, record);
+    }
+    run.notifiers.notify(Event::change(zone, record, *old));
+  }
+
+  // The propagation is checked while the other zones are updated.
+  if let Some(verifier) = &run.verifier {
+    let verifier = verifier.clone();
+    let published = changes
+      .iter()
+      .map(|(record, _)| record.clone())
+      .filter(|record| !record.delete_record())
+      .collect::<Vec<_>>();
+    let verification = tokio::spawn({
+      let zone = zone.to_string();
+      async move {
+        let result = verifier.wait(&zone, &published).await;
+        if let Err(e) = &result {
+          error!("{e:?}");
+        }
+        result.map(|_| ())
+      }
+      .in_current_span()
+    });
+    if let Ok(mut verifications) = run.verifications.lock() {
+      verifications.push((zone.to_string(), verification));
+    }
+  }
+
+  Ok(())
+}
+
+/// The changes which point `host` to the external addresses, each with the
+/// record it replaces. The records which are up to date already are published
+/// as they are. A host with several records of an address family is left
+/// alone, they may be round-robin or multi-homed on purpose.
+#[instrument(skip(host, dns_records, run), fields(%host))]
+fn changes<'r>(
+  zone: &str,
+  host: &HostName,
+  dns_records: &'r [DnsRecord],
+  run: &Run<'_>,
+) -> error_stack::Result<Vec<(DnsRecord, Option<&'r DnsRecord>)>, Errors> {
+  info!("Looking at subdomain");
+
+  let found_records = dns_records
+    .iter()
+    .filter(|record| host.matches(record.host_name()))
+    .filter(|record| !matches!(record.record_type(), RecordType::Other(_)))
+    .collect::<Vec<_>>();
+
+  debug!(records = ?found_records, "Found records");
+
+  let mut changes = vec![];
+  for ip in &run.ips {
+    let record_type = RecordType::from(*ip);
+    let existing = found_records
+      .iter()
+      .copied()
+      .filter(|record| *record.record_type() == record_type)
+      .collect::<Vec<_>>();
+
+    match existing.as_slice() {
+      [] => {
+        info!(%record_type, "No DNS record found for the subdomain.. creating one..");
+        changes.push((DnsRecord::new(host.as_str(), *ip), None));
+      }
+      [record] if *record.destination() == IpType::Ip(*ip) => {
+        debug!(%record_type, "The record is up to date");
+        run.notifiers.published(zone, record);
+      }
+      [record] => {
+        info!(%record_type, from = %record.destination(), to = %ip, "Updating the record");
+        let mut updated = DnsRecord::new(host.as_str(), *ip);
+        if let Some(id) = record.id() {
+          updated.id_mut(id);
+        }
+        changes.push((updated, Some(*record)));
+      }
+      records => {
+        return Err(Report::new(Errors::TooManyRecords(
+          host.to_string(),
+          record_type.to_string(),
+          records.len(),
+        )))
+      }
+    }
+  }
+
+  Ok(changes)
+}
+
+#[cfg(test)]
+mod test {
+  use serde_json::{json, Value};
+
+  use super::*;
+  use crate::{
+    api::netcup::mock::{self, API_SESSION_ID},
+    report::{ReportFormat, EXIT_PARTIAL_FAILURE, EXIT_SUCCESS},
+  };
+
+  #[tokio::test]
+  async fn reconcile_hosts() {
+    let netcup = mock::server().await;
+    let cli = mock::cli(&netcup, &["example.com: www, mail, dup"]);
+
+    mock::action("infoDnsZone", json!({ "domainname": "example.com" }))
+      .respond_with(mock::response(
+        "infoDnsZone",
+        json!({
+          "name": "example.com", "ttl": "300", "serial": "1", "refresh": "28800",
+          "retry": "7200", "expire": "1209600", "dnssecstatus": false
+        }),
+      ))
+      .mount(&netcup)
+      .await;
+    mock::action("infoDnsRecords", json!({ "domainname": "example.com" }))
+      .respond_with(mock::response(
+        "infoDnsRecords",
+        json!({ "dnsrecords": [
+          { "id": "1", "hostname": "www", "type": "A", "destination": "192.0.2.1" },
+          { "id": "2", "hostname": "www", "type": "AAAA", "destination": "2001:db8::1" },
+          { "id": "3", "hostname": "dup", "type": "A", "destination": "192.0.2.1" },
+          { "id": "4", "hostname": "dup", "type": "A", "destination": "192.0.2.3" }
+        ] }),
+      ))
+      .mount(&netcup)
+      .await;
+    mock::action(
+      "updateDnsRecords",
+      json!({ "domainname": "example.com", "apisessionid": API_SESSION_ID }),
+    )
+    .respond_with(mock::response(
+      "updateDnsRecords",
+      json!({ "dnsrecords": [
+        { "id": "5", "hostname": "mail", "type": "A", "destination": "192.0.2.2" }
+      ] }),
+    ))
+    .expect(1)
+    .mount(&netcup)
+    .await;
+
+    let history_file = std::env::temp_dir().join(format!("update-{}.jsonl", std::process::id()));
+    let history = History::new(&history_file);
+    let providers = Providers::connect(&cli).await.unwrap();
+    let report = reconcile(
+      &providers,
+      &cli,
+      &Notifiers::from_cli(&cli).unwrap(),
+      &history,
+      vec!["192.0.2.2".parse().unwrap(), "2001:db8::1".parse().unwrap()],
+    )
+    .await;
+
+    assert_eq!(
+      "ZONE         HOST  OUTCOME  ERROR\n\
+       example.com  dup   failed   Found 2 A records for dup, please remove all but one\n\
+       example.com  www   updated\n\
+       example.com  mail  created\n",
+      report.render(ReportFormat::Table)
+    );
+    assert_eq!(EXIT_PARTIAL_FAILURE, report.exit_code());
+
+    let requests = netcup.received_requests().await.unwrap();
+    let update = requests
+      .iter()
+      .map(|request| request.body_json::<Value>().unwrap())
+      .find(|body| body["action"] == "updateDnsRecords")
+      .unwrap();
+    let sent = update["param"]["dnsrecordset"]["dnsrecords"]
+      .as_array()
+      .unwrap()
+      .iter()
+      .map(|record| {
+        (
+          record["id"].as_str().unwrap_or_default(),
+          record["hostname"].as_str().unwrap(),
+          record["type"].as_str().unwrap(),
+          record["destination"].as_str().unwrap(),
+          record["deleterecord"] == true,
+        )
+      })
+      .collect::<Vec<_>>();
+    assert_eq!(
+      vec![
+        ("1", "www", "A", "192.0.2.2", false),
+        ("", "mail", "A", "192.0.2.2", false),
+        ("", "mail", "AAAA", "2001:db8::1", false),
+      ],
+      sent
+    );
+
+    let changes = history
+      .query(&Default::default())
+      .await
+      .unwrap()
+      .into_iter()
+      .map(|change| serde_json::to_value(change).unwrap())
+      .collect::<Vec<_>>();
+    assert_eq!(3, changes.len());
+    assert_eq!("192.0.2.1", changes[0]["old_destination"]);
+    assert_eq!("1", changes[0]["record_id"]);
+    assert_eq!("5", changes[1]["record_id"]);
+    assert_eq!(
+      "SUPERSECRETSERVERREQUESTID",
+      changes[1]["server_request_id"]
+    );
+
+    let _ = std::fs::remove_file(history_file);
+  }
+
+  #[tokio::test]
+  async fn unwritable_history() {
+    let netcup = mock::server().await;
+    let cli = mock::cli(&netcup, &["example.com: www"]);
+
+    mock::action("infoDnsZone", json!({ "domainname": "example.com" }))
+      .respond_with(mock::response(
+        "infoDnsZone",
+        json!({
+          "name": "example.com", "ttl": "300", "serial": "1", "refresh": "28800",
+          "retry": "7200", "expire": "1209600", "dnssecstatus": false
+        }),
+      ))
+      .mount(&netcup)
+      .await;
+    mock::action("infoDnsRecords", json!({ "domainname": "example.com" }))
+      .respond_with(mock::response(
+        "infoDnsRecords",
+        json!({ "dnsrecords": [] }),
+      ))
+      .mount(&netcup)
+      .await;
+    mock::action("updateDnsRecords", json!({ "domainname": "example.com" }))
+      .respond_with(mock::response(
+        "updateDnsRecords",
+        json!({ "dnsrecords": [] }),
+      ))
+      .expect(1)
+      .mount(&netcup)
+      .await;
+
+    // A directory can't be appended to.
+    let history = History::new(std::env::temp_dir());
+    let providers = Providers::connect(&cli).await.unwrap();
+    let report = reconcile(
+      &providers,
+      &cli,
+      &Notifiers::from_cli(&cli).unwrap(),
+      &history,
+      vec!["192.0.2.2".parse().unwrap()],
+    )
+    .await;
+
+    assert_eq!(
+      "ZONE         HOST  OUTCOME  ERROR\n\
+       example.com  www   created\n",
+      report.render(ReportFormat::Table)
+    );
+    assert_eq!(EXIT_SUCCESS, report.exit_code());
+  }
+}
@@ -0,0 +1,91 @@
+use error_stack::Report;
+use tracing::info;
+
+use crate::{
+  api::netcup::{
+    models::{info_dns_zone, ApiSessionId},
+    Client,
+  },
+  cli::{ZoneCommand, ZoneSettingsArgs},
+  errors::Errors,
+};
+
+pub async fn run(
+  client: &Client<ApiSessionId>,
+  command: &ZoneCommand,
+) -> error_stack::Result<(), Errors> {
+  match command {
+    ZoneCommand::Show { domain } => {
+      let zone = info_dns_zone(client, domain).await?;
+      print!("{zone}");
+    }
+    ZoneCommand::Set {
+      domain,
+      settings,
+      dry_run,
+    } => {
+      let before = info_dns_zone(client, domain).await?;
+      let after = apply(before.clone(), settings);
+
+      after.validate()?;
+
+      for (name, before, after) in before.diff(&after) {
+        if before == after {
+          println!("  {name}: {before}");
+        } else {
+          println!("~ {name}: {before} -> {after}");
+        }
+      }
+
+      if before == after {
+        info!("Zone {domain} is already up to date");
+        return Ok(());
+      }
+
+      if *dry_run {
+        info!("Not updating zone {domain} because of --dry-run");
+        return Ok(());
+      }
+
+      client.update_dns_zone(domain.as_str(), after).await?;
+      info!("Updated dns zone {domain}!");
+    }
+  }
+
+  Ok(())
+}
+
+async fn info_dns_zone(
+  client: &Client<ApiSessionId>,
+  domain: &str,
+) -> error_stack::Result<info_dns_zone::ResponseData, Errors> {
+  client
+    .info_dns_zone(domain)
+    .await?
+    .response_data()
+    .cloned()
+    .ok_or_else(|| Report::new(Errors::DNSZoneNotFound(domain.to_string())))
+}
+
+fn apply(
+  mut zone: info_dns_zone::ResponseData,
+  settings: &ZoneSettingsArgs,
+) -> info_dns_zone::ResponseData {
+  if let Some(ttl) = settings.ttl() {
+    zone.ttl_mut(ttl);
+  }
+  if let Some(refresh) = settings.refresh() {
+    zone.refresh_mut(refresh);
+  }
+  if let Some(retry) = settings.retry() {
+    zone.retry_mut(retry);
+  }
+  if let Some(expire) = settings.expire() {
+    zone.expire_mut(expire);
+  }
+  if let Some(dnssec) = settings.dnssec() {
+    zone.dns_sec_status_mut(dnssec);
+  }
+
+  zone
+}
@@ -22,4 +22,108 @@ pub enum Errors {
   UpdateDNSZone(String),
   #[error("Could not update dns records {0}")]
   UpdateDNSRecords(String),
+  #[error("Could not get info about domain {0}")]
+  InfoDomain(String),
+  #[error("Could not update domain {0}")]
+  UpdateDomain(String),
+  #[error("Failed to parse the nameserver {0}")]
+  ParseNameserver(String),
+  #[error(
+    "Refusing to move {0} away from the Netcup nameservers while DDNS records are managed for it"
+  )]
+  NameserverChangeRefused(String),
+  #[error("Failed to parse the handle type {0}")]
+  ParseHandleType(String),
+  #[error("Could not create the handle")]
+  CreateHandle,
+  #[error("Could not update handle {0}")]
+  UpdateHandle(u32),
+  #[error("Could not get info about handle {0}")]
+  InfoHandle(u32),
+  #[error("Could not list the handles")]
+  ListHandles,
+  #[error("Could not delete handle {0}")]
+  DeleteHandle(u32),
+  #[error("Missing the fields {0} to create a handle")]
+  MissingHandleFields(String),
+  #[error("Could not poll the messages")]
+  Poll,
+  #[error("Could not acknowledge poll message {0}")]
+  AckPoll(u32),
+  #[error("Could not forward poll message {0}")]
+  ForwardPollMessage(u32),
+  #[error("The raw params have to be a JSON object")]
+  RawParams,
+  #[error("Failed to parse the raw param {0}, expected key=value")]
+  ParseRawParam(String),
+  #[error("Could not perform the raw action {0}")]
+  CallRaw(String),
+  #[error("Invalid zone setting: {0}")]
+  InvalidZoneSetting(String),
+  #[error("Failed to parse {0}, expected on or off")]
+  ParseToggle(String),
+  #[error("The customer number, API key and API password are required for Netcup")]
+  MissingCredentials,
+  #[error("Failed to parse the provider {0}, expected netcup, rfc2136, hetzner or cloudflare")]
+  ParseProvider(String),
+  #[error("The RFC 2136 provider is not configured: {0}")]
+  Rfc2136Config(String),
+  #[error("Could not connect to the nameserver {0}")]
+  Rfc2136Connect(String),
+  #[error("The nameserver rejected the update of zone {0}")]
+  Rfc2136Update(String),
+  #[error("Could not transfer zone {0}")]
+  Rfc2136Transfer(String),
+  #[error("Could not convert the record {0}")]
+  ConvertRecord(String),
+  #[error("The {0} provider is not configured: {1}")]
+  ProviderConfig(&'static str, String),
+  #[error("Request to {0} failed.")]
+  ProviderRequest(&'static str),
+  #[error("Could not mirror zone {0} to {1}")]
+  Sync(String, String),
+  #[error("Could not write the zone file {0}")]
+  WriteZoneFile(String),
+  #[error("The zone file {0} would be written for every zone, add {{zone}} to its path")]
+  SharedZoneFile(String),
+  #[error("Failed to parse the mirror {0}, expected hetzner, cloudflare, rfc2136 or file:path")]
+  ParseSyncTarget(String),
+  #[error("Could not query the nameserver {0}")]
+  QueryNameserver(String),
+  #[error("The changes of zone {0} did not propagate in time")]
+  Propagation(String),
+  #[error("The HTTP server on {0} failed")]
+  Server(String),
+  #[error("Could not read or write the registrations in {0}")]
+  Storage(String),
+  #[error("The {0} notifier is not configured: {1}")]
+  NotifierConfig(&'static str, String),
+  #[error("Could not send the {0} notification")]
+  Notify(&'static str),
+  #[error("Failed to parse the notifier {0}, expected a ntfy://, gotify:// or matrix:// URL")]
+  ParseNotifier(String),
+  #[error("Failed to parse the severity {0}, expected change, warning or error")]
+  ParseSeverity(String),
+  #[error("Failed to parse the SMTP security {0}, expected none, starttls or tls")]
+  ParseSmtpSecurity(String),
+  #[error("Failed to parse the log format {0}, expected text or json")]
+  ParseLogFormat(String),
+  #[error("Could not read or write the change history in {0}")]
+  History(String),
+  #[error("Failed to parse the time {0}, expected a RFC 3339 timestamp, a date or a duration")]
+  ParseTime(String),
+  #[error("Failed to parse the history format {0}, expected json or csv")]
+  ParseHistoryFormat(String),
+  #[error("Found {2} {1} records for {0}, please remove all but one")]
+  TooManyRecords(String, String, usize),
+  #[error("Failed to parse the report format {0}, expected table, json or none")]
+  ParseReportFormat(String),
+  #[error("The command panicked")]
+  Panicked,
+  #[error("The command was interrupted")]
+  Interrupted,
+  #[error("Could not write the session cache {0}")]
+  SessionCache(String),
+  #[error("Invalid domain name {0}, the label {1} is no valid IDNA label")]
+  InvalidDomainName(String, String),
 }
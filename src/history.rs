@@ -0,0 +1,347 @@
+//! An append-only audit trail of the record changes as JSON lines.
+
+use std::{fmt::Write, path::PathBuf, str::FromStr, time::SystemTime};
+
+use error_stack::{IntoReport, ResultExt};
+use serde::{Deserialize, Serialize};
+use tokio::{
+  fs::{self, OpenOptions},
+  io::AsyncWriteExt,
+};
+
+use crate::{api::netcup::models::DnsRecord, cli::Cli, errors::Errors, provider::Applied};
+
+/// A record written by the updater.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Change {
+  #[serde(with = "rfc3339")]
+  timestamp: SystemTime,
+  zone: String,
+  host: String,
+  #[serde(rename = "type")]
+  record_type: String,
+  old_destination: Option<String>,
+  /// `None` if the record was deleted.
+  new_destination: Option<String>,
+  record_id: Option<String>,
+  server_request_id: Option<String>,
+}
+
+impl Change {
+  /// The change of `record` from `old` in `zone`. The id of a created record
+  /// is looked up in the records the provider answered with.
+  pub fn new(zone: &str, record: &DnsRecord, old: Option<&DnsRecord>, applied: &Applied) -> Self {
+    let record_id = record
+      .id()
+      .or_else(|| old.and_then(DnsRecord::id))
+      .or_else(|| {
+        applied
+          .records()
+          .iter()
+          .find(|stored| {
+            stored.host_name() == record.host_name()
+              && stored.record_type() == record.record_type()
+              && stored.destination() == record.destination()
+          })
+          .and_then(DnsRecord::id)
+      });
+
+    Self {
+      timestamp: SystemTime::now(),
+      zone: zone.to_string(),
+      host: record.host_name().to_string(),
+      record_type: record.record_type().to_string(),
+      old_destination: old.map(|old| old.destination().to_string()),
+      new_destination: (!record.delete_record()).then(|| record.destination().to_string()),
+      record_id: record_id.map(str::to_string),
+      server_request_id: applied.request_id().map(str::to_string),
+    }
+  }
+
+  /// Whether the change is of `host`, either as the host name in the zone or
+  /// as the fully qualified name.
+  fn is_host(&self, host: &str) -> bool {
+    let host = host.trim_end_matches('.');
+    let fqdn = match self.host.as_str() {
+      "@" => self.zone.clone(),
+      name => format!("{name}.{}", self.zone),
+    };
+
+    self.host.eq_ignore_ascii_case(host) || fqdn.eq_ignore_ascii_case(host)
+  }
+}
+
+/// Which changes the `history` command shows.
+#[derive(Debug, Default)]
+pub struct Query {
+  pub host: Option<String>,
+  pub zone: Option<String>,
+  pub since: Option<Time>,
+  pub until: Option<Time>,
+}
+
+impl Query {
+  fn matches(&self, change: &Change) -> bool {
+    self.host.as_deref().is_none_or(|host| change.is_host(host))
+      && self
+        .zone
+        .as_deref()
+        .is_none_or(|zone| change.zone.eq_ignore_ascii_case(zone.trim_end_matches('.')))
+      && self.since.is_none_or(|since| change.timestamp >= since.0)
+      && self.until.is_none_or(|until| change.timestamp <= until.0)
+  }
+}
+
+/// A point in time given as RFC 3339 timestamp, date or duration ago.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Time(SystemTime);
+
+impl FromStr for Time {
+  type Err = Errors;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let s = s.trim();
+    if let Ok(ago) = humantime::parse_duration(s) {
+      return SystemTime::now()
+        .checked_sub(ago)
+        .map(Self)
+        .ok_or_else(|| Errors::ParseTime(s.to_string()));
+    }
+
+    let timestamp = match s.len() {
+      10 => format!("{s}T00:00:00Z"),
+      _ => s.to_string(),
+    };
+    humantime::parse_rfc3339_weak(&timestamp)
+      .map(Self)
+      .map_err(|_| Errors::ParseTime(s.to_string()))
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+  Json,
+  Csv,
+}
+
+impl FromStr for Format {
+  type Err = Errors;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.trim().to_lowercase().as_str() {
+      "json" => Ok(Self::Json),
+      "csv" => Ok(Self::Csv),
+      _ => Err(Errors::ParseHistoryFormat(s.to_string())),
+    }
+  }
+}
+
+pub struct History {
+  path: PathBuf,
+}
+
+impl History {
+  pub fn new(path: impl Into<PathBuf>) -> Self {
+    Self { path: path.into() }
+  }
+
+  pub fn from_cli(cli: &Cli) -> Self {
+    Self::new(cli.history_file())
+  }
+
+  /// Appends the changes, every change is one line.
+  pub async fn record(&self, changes: &[Change]) -> error_stack::Result<(), Errors> {
+    if changes.is_empty() {
+      return Ok(());
+    }
+
+    let mut lines = String::new();
+    for change in changes {
+      let line = serde_json::to_string(change)
+        .into_report()
+        .change_context_lazy(|| self.error())?;
+      let _ = writeln!(lines, "{line}");
+    }
+
+    if let Some(directory) = self.path.parent() {
+      fs::create_dir_all(directory)
+        .await
+        .into_report()
+        .change_context_lazy(|| self.error())?;
+    }
+    let mut file = OpenOptions::new()
+      .create(true)
+      .append(true)
+      .open(&self.path)
+      .await
+      .into_report()
+      .change_context_lazy(|| self.error())?;
+    file
+      .write_all(lines.as_bytes())
+      .await
+      .into_report()
+      .change_context_lazy(|| self.error())
+  }
+
+  /// The changes matching `query` in the order they were made. A missing
+  /// history has no changes.
+  pub async fn query(&self, query: &Query) -> error_stack::Result<Vec<Change>, Errors> {
+    let content = match fs::read_to_string(&self.path).await {
+      Ok(content) => content,
+      Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+      Err(e) => return Err(e).into_report().change_context_lazy(|| self.error()),
+    };
+
+    let mut changes = vec![];
+    for (number, line) in content.lines().enumerate() {
+      if line.trim().is_empty() {
+        continue;
+      }
+      let change = serde_json::from_str::<Change>(line)
+        .into_report()
+        .change_context_lazy(|| self.error())
+        .attach_printable_lazy(|| format!("Line {} is not a change", number + 1))?;
+      if query.matches(&change) {
+        changes.push(change);
+      }
+    }
+
+    Ok(changes)
+  }
+
+  fn error(&self) -> Errors {
+    Errors::History(self.path.display().to_string())
+  }
+}
+
+pub fn export(changes: &[Change], format: Format) -> error_stack::Result<String, Errors> {
+  match format {
+    Format::Json => serde_json::to_string_pretty(changes)
+      .into_report()
+      .change_context(Errors::SerializeResponse),
+    Format::Csv => {
+      let mut csv = String::from(
+        "timestamp,zone,host,type,old_destination,new_destination,record_id,server_request_id\n",
+      );
+      for change in changes {
+        let fields = [
+          humantime::format_rfc3339_seconds(change.timestamp).to_string(),
+          change.zone.clone(),
+          change.host.clone(),
+          change.record_type.clone(),
+          change.old_destination.clone().unwrap_or_default(),
+          change.new_destination.clone().unwrap_or_default(),
+          change.record_id.clone().unwrap_or_default(),
+          change.server_request_id.clone().unwrap_or_default(),
+        ];
+        let row = fields.iter().map(|field| escape(field)).collect::<Vec<_>>();
+        let _ = writeln!(csv, "{}", row.join(","));
+      }
+      Ok(csv)
+    }
+  }
+}
+
+fn escape(field: &str) -> String {
+  if field.contains([',', '"', '\n', '\r']) {
+    format!("\"{}\"", field.replace('"', "\"\""))
+  } else {
+    field.to_string()
+  }
+}
+
+mod rfc3339 {
+  use std::time::SystemTime;
+
+  use serde::{de, Deserialize, Deserializer, Serializer};
+
+  pub fn serialize<S: Serializer>(time: &SystemTime, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(&humantime::format_rfc3339_seconds(*time))
+  }
+
+  pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<SystemTime, D::Error> {
+    let timestamp = String::deserialize(deserializer)?;
+    humantime::parse_rfc3339_weak(&timestamp).map_err(de::Error::custom)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use std::time::Duration;
+
+  use super::*;
+
+  #[tokio::test]
+  async fn record_and_query_changes() {
+    let history =
+      History::new(std::env::temp_dir().join(format!("history-{}.jsonl", std::process::id())));
+    let _ = fs::remove_file(&history.path).await;
+
+    let mut stored = DnsRecord::new("www", "192.0.2.2".parse().unwrap());
+    stored.id_mut("42");
+    let applied = Applied::new(Some("request".into()), vec![stored]);
+    let old = DnsRecord::new("@", "192.0.2.1".parse().unwrap());
+    let mut changes = vec![
+      Change::new(
+        "example.com",
+        &DnsRecord::new("www", "192.0.2.2".parse().unwrap()),
+        None,
+        &applied,
+      ),
+      Change::new(
+        "example.com",
+        &DnsRecord::new("@", "192.0.2.3".parse().unwrap()),
+        Some(&old),
+        &Applied::default(),
+      ),
+    ];
+    changes[1].timestamp -= Duration::from_secs(3 * 24 * 60 * 60);
+    for change in &mut changes {
+      // The history only keeps seconds.
+      let seconds = change
+        .timestamp
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+      change.timestamp = SystemTime::UNIX_EPOCH + Duration::from_secs(seconds);
+    }
+    history.record(&changes[..1]).await.unwrap();
+    history.record(&changes[1..]).await.unwrap();
+
+    assert_eq!(changes, history.query(&Query::default()).await.unwrap());
+    assert_eq!(Some("42"), changes[0].record_id.as_deref());
+    assert_eq!(Some("request"), changes[0].server_request_id.as_deref());
+
+    let query = |host: &str, since: Option<&str>| Query {
+      host: Some(host.into()),
+      since: since.map(|since| since.parse().unwrap()),
+      ..Query::default()
+    };
+    assert_eq!(
+      changes[..1],
+      history
+        .query(&query("www.example.com.", None))
+        .await
+        .unwrap()
+    );
+    assert_eq!(
+      changes[1..],
+      history.query(&query("example.com", None)).await.unwrap()
+    );
+    assert!(history
+      .query(&query("example.com", Some("1d")))
+      .await
+      .unwrap()
+      .is_empty());
+
+    let csv = export(&changes[1..], Format::Csv).unwrap();
+    let row = csv.lines().nth(1).unwrap();
+    assert!(
+      row.ends_with(",example.com,@,A,192.0.2.1,192.0.2.3,,"),
+      "{row}"
+    );
+    assert_eq!("\"a,\"\"b\"\"\"", escape("a,\"b\""));
+
+    fs::remove_file(&history.path).await.unwrap();
+  }
+}
@@ -0,0 +1,171 @@
+//! Host names as the records of a zone hold them: relative to the zone,
+//! lowercase and without a trailing dot, `@` for the zone itself.
+//! Internationalized names are sent to the APIs as punycode and shown in
+//! Unicode.
+
+use std::{borrow::Cow, fmt};
+
+use idna::{
+  uts46::{DnsLength, Hyphens, Uts46},
+  AsciiDenyList,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::errors::Errors;
+
+/// The host name of the zone itself.
+pub const APEX: &str = "@";
+
+/// Lowercases `name` and strips the trailing dot of a fully qualified name.
+pub fn normalize(name: &str) -> String {
+  name.trim().trim_end_matches('.').to_lowercase()
+}
+
+/// Converts `name` to punycode according to UTS #46, which rejects invalid
+/// labels before they reach an API. `_` and `*` are allowed for names like
+/// `_acme-challenge` and wildcards.
+pub fn to_ascii(name: &str) -> Result<String, Errors> {
+  let name = normalize(name);
+  if name.is_empty() || name == APEX {
+    return Ok(name);
+  }
+
+  uts46(&name).map_err(|_| {
+    let label = name
+      .split('.')
+      .find(|label| uts46(label).is_err())
+      .unwrap_or(&name);
+    Errors::InvalidDomainName(name.clone(), label.to_string())
+  })
+}
+
+fn uts46(name: &str) -> Result<String, idna::Errors> {
+  Uts46::new()
+    .to_ascii(
+      name.as_bytes(),
+      AsciiDenyList::URL,
+      Hyphens::CheckFirstLast,
+      DnsLength::Verify,
+    )
+    .map(Cow::into_owned)
+}
+
+/// The Unicode form of a punycode `name` for display, `name` itself if it is
+/// not valid punycode.
+pub fn to_unicode(name: &str) -> String {
+  match idna::domain_to_unicode(name) {
+    (unicode, Ok(())) => unicode,
+    _ => name.to_string(),
+  }
+}
+
+/// Every parent domain of `name` which could be its zone, the most specific
+/// first.
+pub fn candidate_zones(name: &str) -> Vec<&str> {
+  name
+    .match_indices('.')
+    .map(|(index, _)| &name[index + 1..])
+    .filter(|zone| zone.contains('.'))
+    .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct HostName(String);
+
+impl HostName {
+  /// `name` relative to `zone`. An empty name, `@` and the zone itself are the
+  /// apex, names within the zone lose the zone and every other name, e.g.
+  /// `www` or `*`, is taken as relative already.
+  pub fn relative(name: &str, zone: &str) -> Self {
+    let name = normalize(name);
+    let zone = normalize(zone);
+
+    let host_name = if name.is_empty() || name == APEX || name == zone {
+      APEX.to_string()
+    } else {
+      match name.strip_suffix(&format!(".{zone}")) {
+        Some(host_name) if !zone.is_empty() => host_name.to_string(),
+        _ => name,
+      }
+    };
+    Self(host_name)
+  }
+
+  pub fn as_str(&self) -> &str {
+    &self.0
+  }
+
+  /// Whether `host_name` of a record names this host.
+  pub fn matches(&self, host_name: &str) -> bool {
+    *self == Self::relative(host_name, "")
+  }
+}
+
+impl fmt::Display for HostName {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(&self.0)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn relative_host_names() {
+    let relative = |name| HostName::relative(name, "example.com");
+
+    assert_eq!("www", relative("WWW").as_str());
+    assert_eq!("www", relative("www.example.com.").as_str());
+    assert_eq!("nas.home", relative("nas.home.Example.com").as_str());
+    assert_eq!("@", relative("").as_str());
+    assert_eq!("@", relative("@").as_str());
+    assert_eq!("@", relative("example.com.").as_str());
+    assert_eq!("*", relative("*").as_str());
+    assert_eq!("*", relative("*.example.com").as_str());
+    assert_eq!("www.example.org", relative("www.example.org").as_str());
+
+    assert!(relative("www.example.com").matches("WWW"));
+    assert!(relative("").matches("@"));
+    assert!(!relative("www").matches("mail"));
+  }
+
+  #[test]
+  fn punycode() {
+    assert_eq!("xn--mller-bau-q9a.de", to_ascii("Müller-Bau.de.").unwrap());
+    assert_eq!("büro", to_unicode(&to_ascii("büro").unwrap()));
+    assert_eq!("müller-bau.de", to_unicode("xn--mller-bau-q9a.de"));
+    assert_eq!(
+      "_acme-challenge.www",
+      to_ascii("_acme-challenge.www").unwrap()
+    );
+    assert_eq!("*", to_ascii("*").unwrap());
+    assert_eq!("@", to_ascii("@").unwrap());
+    assert_eq!("@", to_unicode("@"));
+
+    for (name, label) in [
+      ("-www.example.com", "-www"),
+      ("www..example.com", ""),
+      ("a b.example.com", "a b"),
+      ("xn--zz.example.com", "xn--zz"),
+    ] {
+      match to_ascii(name) {
+        Err(Errors::InvalidDomainName(_, invalid)) => assert_eq!(label, invalid),
+        result => panic!("{name} should be invalid: {result:?}"),
+      }
+    }
+  }
+
+  #[test]
+  fn zone_candidates() {
+    assert_eq!(
+      vec!["www.example.co.uk", "example.co.uk", "co.uk"],
+      candidate_zones("_acme-challenge.www.example.co.uk")
+    );
+    assert_eq!(
+      vec!["example.com"],
+      candidate_zones("_acme-challenge.example.com")
+    );
+  }
+}
@@ -0,0 +1,46 @@
+//! Logs as text or JSON lines, the `log` records of the dependencies included.
+
+use std::str::FromStr;
+
+use tracing_subscriber::EnvFilter;
+
+use crate::errors::Errors;
+
+/// The level when `RUST_LOG` is not set.
+const DEFAULT_FILTER: &str = "info";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+  Text,
+  Json,
+}
+
+impl FromStr for LogFormat {
+  type Err = Errors;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.trim().to_lowercase().as_str() {
+      "text" => Ok(Self::Text),
+      "json" => Ok(Self::Json),
+      _ => Err(Errors::ParseLogFormat(s.to_string())),
+    }
+  }
+}
+
+pub fn init(format: LogFormat) {
+  let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER));
+  // Standard output is left to the reports and exports.
+  let subscriber = tracing_subscriber::fmt()
+    .with_env_filter(filter)
+    .with_writer(std::io::stderr);
+
+  match format {
+    LogFormat::Text => subscriber.init(),
+    LogFormat::Json => subscriber
+      .json()
+      .flatten_event(true)
+      .with_current_span(true)
+      .with_span_list(true)
+      .init(),
+  }
+}
@@ -1,136 +1,207 @@
-use std::{env, time::Duration};
+use std::{process::ExitCode, time::Duration};
 
-use cli::Cli;
+use cli::{Cli, Command, SyncTarget};
 use dotenv::dotenv;
+use error_stack::Report;
 use errors::Errors;
-use log::{debug, error, info, warn};
 use structopt::StructOpt;
 use tokio::time::sleep;
+use tracing::{error, info, info_span, Instrument};
 
-use crate::api::netcup::{self, models::DnsRecord};
+use crate::{
+  api::netcup::{self, models::ApiSessionId},
+  history::{History, Query},
+  notify::{Event, Notifiers},
+  provider::{ProviderKind, Providers},
+  report::RunReport,
+};
 
 mod api;
 mod cli;
+mod commands;
 mod errors;
+mod history;
+mod hostname;
+mod logging;
+mod metrics;
+mod notify;
+mod propagation;
+mod provider;
+mod report;
 mod serialization;
+mod server;
+mod shutdown;
 
 #[tokio::main]
-async fn main() -> error_stack::Result<(), Errors> {
-  env::set_var("RUST_LOG", "debug");
+async fn main() -> error_stack::Result<ExitCode, Errors> {
   dotenv().ok();
-  env_logger::init();
 
   let cli = Cli::from_args();
-  let client = netcup::Client::new(&cli);
+  logging::init(cli.log_format());
 
-  let client = client.login().await?;
+  let Some(command) = cli.command() else {
+    let notifiers = Notifiers::from_cli(&cli)?;
 
-  let ips = api::ip::external().await;
-  ips.iter().for_each(|ip| info!("Got IP {ip:?}"));
-
-  for domain_zone in cli.domains() {
-    info!("Looking at domain-zone {:#?}", domain_zone);
-
-    let info_dns_zone_response = match client.info_dns_zone(domain_zone.domain()).await {
-      Ok(info_dns_zone_response) => info_dns_zone_response,
-      Err(e) => {
-        error!("{e}");
-        continue;
-      }
-    };
+    if let Some(listen) = cli.metrics_listen() {
+      let (address, server) = server::bind(listen, server::metrics::Metrics)?;
+      info!("Serving the metrics on http://{address}/metrics");
+      tokio::spawn(async move {
+        if let Err(e) = server.await {
+          error!("{e:?}");
+        }
+      });
+    }
 
-    if let Some(mut response_data) = info_dns_zone_response.response_data().cloned() {
-      let current_ttl = response_data.ttl();
-      if current_ttl > 300 {
-        warn!("TTL is {current_ttl} and should be 300");
-        if let Some(ttl) = cli.ttl() {
-          response_data.ttl_mut(ttl);
-          info!("Changing TTL to {}", ttl);
-          let update_dns_zone_response = match client
-            .update_dns_zone(domain_zone.domain(), response_data)
-            .await
-          {
-            Ok(update_dns_zone_response) => update_dns_zone_response,
-            Err(e) => {
-              error!("{e}");
-              continue;
-            }
-          };
-          if update_dns_zone_response.status_code() != netcup::StatusCode::Success {
-            error!(
-              "{}",
-              Errors::UpdateDNSZone(domain_zone.domain().to_string())
-            );
-            continue;
-          }
-          info!("Updated dns zone!");
+    let mut run = 0u64;
+    loop {
+      run += 1;
+      let report = update(&cli, &notifiers)
+        .instrument(info_span!("run", run))
+        .await;
+      notifiers.finish().await;
+      print!("{}", report.render(cli.report_format()));
+
+      let interval = match cli.interval() {
+        Some(interval) if !shutdown::requested() => interval,
+        _ => {
+          notifiers.close().await;
+          return Ok(ExitCode::from(report.exit_code()));
+        }
+      };
+
+      tokio::select! {
+        _ = sleep(Duration::from_secs(interval)) => {}
+        _ = notifiers.triggered() => info!("Running the update on request"),
+        _ = shutdown::signal() => {
+          notifiers.close().await;
+          return Ok(ExitCode::from(report.exit_code()));
         }
       }
     }
+  };
+
+  if let Command::Sync {
+    targets,
+    zones,
+    dry_run,
+  } = command
+  {
+    let kinds = targets.iter().filter_map(SyncTarget::provider);
+    let providers = Providers::connect_kinds(&cli, kinds.chain(Some(ProviderKind::Netcup))).await?;
+    let result = shutdown::guard(commands::sync::run(
+      &providers, &cli, zones, targets, *dry_run,
+    ))
+    .await;
+    providers.close().await?;
+
+    let report = result?;
+    print!("{}", report.render(cli.report_format()));
+    return Ok(ExitCode::from(report.exit_code()));
+  }
 
-    info!("Getting all dns records");
-    let info_dns_records_response = match client.info_dns_records(domain_zone.domain()).await {
-      Ok(info_dns_records_response) => info_dns_records_response,
-      Err(e) => {
-        error!("{e}");
-        continue;
-      }
-    };
+  if let Command::AcmeDns {
+    listen,
+    zone,
+    storage,
+    enable_registration,
+  } = command
+  {
+    let storage = cli::state_file(storage.as_deref(), "acme-dns.json");
+    commands::acme_dns::run(&cli, *listen, zone, &storage, *enable_registration).await?;
+    return Ok(ExitCode::SUCCESS);
+  }
 
-    let dns_records = match info_dns_records_response
-      .response_data()
-      .map(|data| data.dns_records())
-    {
-      Some(dns_records) => dns_records,
-      None => {
-        error!("No info about dns records");
-        continue;
-      }
+  if let Command::History {
+    host,
+    zone,
+    since,
+    until,
+    format,
+  } = command
+  {
+    let query = Query {
+      host: host.clone(),
+      zone: zone.clone(),
+      since: *since,
+      until: *until,
     };
+    commands::history::run(&History::from_cli(&cli), &query, *format).await?;
+    return Ok(ExitCode::SUCCESS);
+  }
 
-    for sub_domain in domain_zone.sub_domains() {
-      info!("Looking at {sub_domain:#?} subdomain");
-
-      let found_records = dns_records
-        .iter()
-        .filter(|record| record.host_name() == sub_domain)
-        .filter(|record| !matches!(record.record_type(), netcup::models::RecordType::Other(_)))
-        .collect::<Vec<_>>();
-
-      debug!("Found records: {:#?}", found_records);
-
-      match found_records.len() {
-        0 => {
-          info!("No DNS record found for {sub_domain:#?} subdomain.. creating one..");
-
-          let dns_records = ips.iter().fold(vec![], |mut dns_records, destination| {
-            dns_records.push(DnsRecord::new(sub_domain, *destination));
-            dns_records
-          });
-
-          let update_dns_records_response = match client
-            .update_dns_records(domain_zone.domain(), dns_records)
-            .await
-          {
-            Ok(update_dns_records_response) => update_dns_records_response,
-            Err(e) => {
-              error!("{e}");
-              continue;
-            }
-          };
-        }
-        1 => {
-          info!("One DNS record found for {sub_domain:#?} subdomain.. updating..");
-        }
-        _ => {
-          error!("Too many DNS records found for {sub_domain:#?} subdomain.. please specify");
-        }
-      }
+  let client = netcup::Client::new(&cli)?.login().await?;
+  let result = shutdown::guard(run_command(&client, &cli, command)).await;
+
+  sleep(Duration::from_secs(2)).await;
+
+  match (result, client.logout().await) {
+    (Err(e), Err(logout)) => {
+      error!("{logout:?}");
+      Err(e)
+    }
+    (result, logout) => {
+      logout?;
+      result.map(|()| ExitCode::SUCCESS)
     }
   }
+}
 
-  sleep(Duration::from_secs(2)).await;
+/// Runs a command which needs a Netcup session.
+async fn run_command(
+  client: &netcup::Client<ApiSessionId>,
+  cli: &Cli,
+  command: &Command,
+) -> error_stack::Result<(), Errors> {
+  match command {
+    Command::Acme(command) => commands::acme::run(client, cli, command).await,
+    Command::Nameservers(command) => commands::nameservers::run(client, cli, command).await,
+    Command::Handles(command) => commands::handles::run(client, command).await,
+    Command::Zone(command) => commands::zone::run(client, command).await,
+    Command::Poll {
+      count,
+      forward_url,
+      no_ack,
+    } => commands::poll::run(client, *count, forward_url.as_deref(), *no_ack).await,
+    Command::Raw { action, params } => commands::raw::run(client, action, params).await,
+    Command::Sync { .. } | Command::AcmeDns { .. } | Command::History { .. } => {
+      unreachable!("the command is handled before logging in")
+    }
+  }
+}
 
-  client.logout().await?;
-  Ok(())
+/// Runs the update once, a failure of the whole run ends up in the report as
+/// well. The providers are closed on every path.
+async fn update(cli: &Cli, notifiers: &Notifiers) -> RunReport {
+  let failed = |mut report: RunReport, e: Report<Errors>| {
+    error!("{e:?}");
+    report.failed(None, None, &e);
+    notifiers.notify(Event::error(None, &e));
+    report
+  };
+
+  let providers = match Providers::connect(cli).await {
+    Ok(providers) => providers,
+    Err(e) => return failed(RunReport::default(), e),
+  };
+  let history = History::from_cli(cli);
+  let result = shutdown::guard(async {
+    let mut report = commands::update::run(&providers, cli, notifiers, &history).await;
+    if !cli.mirrors().is_empty() {
+      match commands::sync::mirror(&providers, cli).await {
+        Ok(mirrors) => report.extend([mirrors]),
+        Err(e) => report = failed(report, e),
+      }
+    }
+    Ok(report)
+  })
+  .await;
+  let report = match result {
+    Ok(report) => report,
+    Err(e) => failed(RunReport::default(), e),
+  };
+
+  match providers.close().await {
+    Ok(()) => report,
+    Err(e) => failed(report, e),
+  }
 }
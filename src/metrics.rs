@@ -0,0 +1,285 @@
+//! Metrics of the updater in the Prometheus text format.
+
+use std::{
+  collections::BTreeMap,
+  fmt::Write,
+  sync::{Mutex, OnceLock},
+  time::{Duration, SystemTime},
+};
+
+use crate::api::netcup::{Action, StatusCode};
+
+/// The upper bounds of the request latency buckets in seconds.
+const BUCKETS: [f64; 9] = [0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+
+/// The metrics of the process.
+pub fn metrics() -> &'static Metrics {
+  static METRICS: OnceLock<Metrics> = OnceLock::new();
+  METRICS.get_or_init(Metrics::default)
+}
+
+#[derive(Debug, Default, Clone)]
+struct Histogram {
+  /// The count of every bucket, `+Inf` is the total count.
+  buckets: [u64; BUCKETS.len()],
+  count: u64,
+  sum: f64,
+}
+
+impl Histogram {
+  fn observe(&mut self, value: f64) {
+    for (bucket, bound) in self.buckets.iter_mut().zip(BUCKETS) {
+      if value <= bound {
+        *bucket += 1;
+      }
+    }
+    self.count += 1;
+    self.sum += value;
+  }
+}
+
+#[derive(Debug, Clone)]
+struct Published {
+  address: String,
+  updated: SystemTime,
+}
+
+#[derive(Debug, Default)]
+struct Registry {
+  /// By action and Netcup status code, `none` if there was no response.
+  requests: BTreeMap<(String, String), u64>,
+  durations: BTreeMap<String, Histogram>,
+  /// By source, address family and whether an address was found.
+  ip_detections: BTreeMap<(String, String, bool), u64>,
+  /// By hostname and record type, so dual-stack hosts keep both addresses.
+  published: BTreeMap<(String, String), Published>,
+}
+
+#[derive(Debug, Default)]
+pub struct Metrics {
+  registry: Mutex<Registry>,
+}
+
+impl Metrics {
+  /// Records a request to the Netcup API.
+  pub fn request(&self, action: &Action, status_code: Option<StatusCode>, duration: Duration) {
+    let Ok(mut registry) = self.registry.lock() else {
+      return;
+    };
+
+    let status_code = status_code
+      .map(|status_code| (status_code as u32).to_string())
+      .unwrap_or_else(|| "none".into());
+    *registry
+      .requests
+      .entry((action.to_string(), status_code))
+      .or_default() += 1;
+    registry
+      .durations
+      .entry(action.to_string())
+      .or_default()
+      .observe(duration.as_secs_f64());
+  }
+
+  pub fn ip_detection(&self, source: &str, family: &str, success: bool) {
+    if let Ok(mut registry) = self.registry.lock() {
+      *registry
+        .ip_detections
+        .entry((source.into(), family.into(), success))
+        .or_default() += 1;
+    }
+  }
+
+  /// Records that the `record_type` record of `hostname` points to `address`
+  /// after a successful update.
+  pub fn published(&self, hostname: &str, record_type: &str, address: &str) {
+    self.published_at(hostname, record_type, address, SystemTime::now())
+  }
+
+  fn published_at(&self, hostname: &str, record_type: &str, address: &str, updated: SystemTime) {
+    if let Ok(mut registry) = self.registry.lock() {
+      registry.published.insert(
+        (hostname.into(), record_type.into()),
+        Published {
+          address: address.into(),
+          updated,
+        },
+      );
+    }
+  }
+
+  pub fn render(&self) -> String {
+    self.render_at(SystemTime::now())
+  }
+
+  fn render_at(&self, now: SystemTime) -> String {
+    let Ok(registry) = self.registry.lock() else {
+      return String::new();
+    };
+    let mut out = String::new();
+
+    header(
+      &mut out,
+      "netcup_api_requests_total",
+      "counter",
+      "Requests to the Netcup API by action and status code.",
+    );
+    for ((action, status_code), count) in &registry.requests {
+      let _ = writeln!(
+        out,
+        "netcup_api_requests_total{{action=\"{}\",status_code=\"{status_code}\"}} {count}",
+        escape(action)
+      );
+    }
+
+    header(
+      &mut out,
+      "netcup_api_request_duration_seconds",
+      "histogram",
+      "The latency of the requests to the Netcup API.",
+    );
+    for (action, histogram) in &registry.durations {
+      let action = escape(action);
+      for (bound, count) in BUCKETS.iter().zip(histogram.buckets) {
+        let _ = writeln!(
+          out,
+          "netcup_api_request_duration_seconds_bucket{{action=\"{action}\",le=\"{bound}\"}} {count}"
+        );
+      }
+      let _ = writeln!(
+        out,
+        "netcup_api_request_duration_seconds_bucket{{action=\"{action}\",le=\"+Inf\"}} {}",
+        histogram.count
+      );
+      let _ = writeln!(
+        out,
+        "netcup_api_request_duration_seconds_sum{{action=\"{action}\"}} {}",
+        histogram.sum
+      );
+      let _ = writeln!(
+        out,
+        "netcup_api_request_duration_seconds_count{{action=\"{action}\"}} {}",
+        histogram.count
+      );
+    }
+
+    header(
+      &mut out,
+      "ddns_ip_detections_total",
+      "counter",
+      "Lookups of the external address by source, family and result.",
+    );
+    for ((source, family, success), count) in &registry.ip_detections {
+      let result = if *success { "success" } else { "failure" };
+      let _ = writeln!(
+        out,
+        "ddns_ip_detections_total{{source=\"{}\",family=\"{}\",result=\"{result}\"}} {count}",
+        escape(source),
+        escape(family)
+      );
+    }
+
+    header(
+      &mut out,
+      "ddns_seconds_since_last_update",
+      "gauge",
+      "Seconds since the record of a hostname and type was last updated successfully.",
+    );
+    for ((hostname, record_type), published) in &registry.published {
+      let age = now
+        .duration_since(published.updated)
+        .unwrap_or_default()
+        .as_secs();
+      let _ = writeln!(
+        out,
+        "ddns_seconds_since_last_update{{hostname=\"{}\",type=\"{}\"}} {age}",
+        escape(hostname),
+        escape(record_type)
+      );
+    }
+
+    header(
+      &mut out,
+      "ddns_published_address_info",
+      "gauge",
+      "The address the record of a hostname and type currently points to.",
+    );
+    for ((hostname, record_type), published) in &registry.published {
+      let _ = writeln!(
+        out,
+        "ddns_published_address_info{{hostname=\"{}\",type=\"{}\",address=\"{}\"}} 1",
+        escape(hostname),
+        escape(record_type),
+        escape(&published.address)
+      );
+    }
+
+    out
+  }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+  let _ = writeln!(out, "# HELP {name} {help}");
+  let _ = writeln!(out, "# TYPE {name} {kind}");
+}
+
+fn escape(value: &str) -> String {
+  value
+    .replace('\\', "\\\\")
+    .replace('"', "\\\"")
+    .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn render_metrics() {
+    let metrics = Metrics::default();
+    let now = SystemTime::now();
+
+    metrics.request(
+      &Action::InfoDnsRecords,
+      Some(StatusCode::Success),
+      Duration::from_millis(200),
+    );
+    metrics.request(&Action::InfoDnsRecords, None, Duration::from_secs(3));
+    metrics.ip_detection("public-ip", "ipv4", true);
+    metrics.ip_detection("public-ip", "ipv6", false);
+    metrics.published_at(
+      "www.example.com",
+      "A",
+      "192.0.2.1",
+      now - Duration::from_secs(90),
+    );
+    metrics.published_at(
+      "www.example.com",
+      "AAAA",
+      "2001:db8::1",
+      now - Duration::from_secs(30),
+    );
+
+    let rendered = metrics.render_at(now);
+    for line in [
+      "# TYPE netcup_api_requests_total counter",
+      r#"netcup_api_requests_total{action="infoDnsRecords",status_code="2000"} 1"#,
+      r#"netcup_api_requests_total{action="infoDnsRecords",status_code="none"} 1"#,
+      r#"netcup_api_request_duration_seconds_bucket{action="infoDnsRecords",le="0.25"} 1"#,
+      r#"netcup_api_request_duration_seconds_bucket{action="infoDnsRecords",le="5"} 2"#,
+      r#"netcup_api_request_duration_seconds_bucket{action="infoDnsRecords",le="+Inf"} 2"#,
+      r#"netcup_api_request_duration_seconds_count{action="infoDnsRecords"} 2"#,
+      r#"ddns_ip_detections_total{source="public-ip",family="ipv4",result="success"} 1"#,
+      r#"ddns_ip_detections_total{source="public-ip",family="ipv6",result="failure"} 1"#,
+      r#"ddns_seconds_since_last_update{hostname="www.example.com",type="A"} 90"#,
+      r#"ddns_seconds_since_last_update{hostname="www.example.com",type="AAAA"} 30"#,
+      r#"ddns_published_address_info{hostname="www.example.com",type="A",address="192.0.2.1"} 1"#,
+      r#"ddns_published_address_info{hostname="www.example.com",type="AAAA",address="2001:db8::1"} 1"#,
+    ] {
+      assert!(
+        rendered.lines().any(|rendered| rendered == line),
+        "{line} is missing in\n{rendered}"
+      );
+    }
+  }
+}
@@ -0,0 +1,491 @@
+//! Mails a digest of every run with changes, warnings or errors over SMTP.
+//!
+//! At most one digest is sent per interval, so a daemon failing every few
+//! minutes doesn't flood the inboxes. The digests in between are counted in a
+//! state file and mentioned in the next mail.
+
+use std::{
+  path::PathBuf,
+  str::FromStr,
+  time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use async_trait::async_trait;
+use error_stack::{IntoReport, Report, ResultExt};
+use lettre::{
+  message::{header::ContentType, Mailbox},
+  transport::smtp::{authentication::Credentials, extension::ClientId},
+  AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::{cli::Cli, errors::Errors};
+
+use super::{Digest, Event, Notifier, Severity};
+
+const NAME: &str = "smtp";
+/// How long connecting and every command may take, so a stalled server does
+/// not hang the run.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Security {
+  None,
+  StartTls,
+  Tls,
+}
+
+impl Security {
+  fn default_port(&self) -> u16 {
+    match self {
+      Self::None => 25,
+      Self::StartTls => 587,
+      Self::Tls => 465,
+    }
+  }
+}
+
+impl FromStr for Security {
+  type Err = Errors;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.trim().to_lowercase().as_str() {
+      "none" => Ok(Self::None),
+      "starttls" => Ok(Self::StartTls),
+      "tls" => Ok(Self::Tls),
+      _ => Err(Errors::ParseSmtpSecurity(s.to_string())),
+    }
+  }
+}
+
+/// When the last digest was sent and the digests held back since, which are
+/// mailed with the next one.
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct RateLimit {
+  last_sent: u64,
+  suppressed: u32,
+  #[serde(default)]
+  zones: Vec<String>,
+  #[serde(default)]
+  events: Vec<Event>,
+}
+
+impl RateLimit {
+  fn hold(&mut self, digest: &Digest) {
+    self.suppressed += 1;
+    for zone in digest.zones() {
+      if !self.zones.contains(zone) {
+        self.zones.push(zone.clone());
+      }
+    }
+    self.events.extend(digest.events().iter().cloned());
+  }
+
+  /// The digests held back followed by `digest`.
+  fn with(&self, digest: &Digest) -> Digest {
+    let mut held = Digest {
+      zones: self.zones.clone(),
+      events: self.events.clone(),
+      ..digest.clone()
+    };
+    for zone in digest.zones() {
+      if !held.zones.contains(zone) {
+        held.zones.push(zone.clone());
+      }
+    }
+    held.events.extend(digest.events().iter().cloned());
+    held
+  }
+}
+
+pub struct Email {
+  transport: AsyncSmtpTransport<Tokio1Executor>,
+  from: Mailbox,
+  to: Vec<Mailbox>,
+  interval: Duration,
+  state: PathBuf,
+}
+
+impl Email {
+  pub fn from_cli(cli: &Cli) -> error_stack::Result<Option<Self>, Errors> {
+    let Some(server) = cli.smtp_server() else {
+      return Ok(None);
+    };
+    let config_error = |message: &str| Report::new(Errors::NotifierConfig(NAME, message.into()));
+
+    let security = cli.smtp_security();
+    let (host, port) = match server.rsplit_once(':') {
+      Some((host, port)) => (
+        host,
+        port
+          .parse()
+          .map_err(|_| config_error("the port of the server is invalid"))?,
+      ),
+      None => (server, security.default_port()),
+    };
+    let from = cli
+      .smtp_from()
+      .ok_or_else(|| config_error("the sender is missing"))?
+      .parse::<Mailbox>()
+      .map_err(|_| config_error("the sender is invalid"))?;
+    if cli.smtp_to().is_empty() {
+      return Err(config_error("the recipients are missing"));
+    }
+    let to = cli
+      .smtp_to()
+      .iter()
+      .map(|to| to.parse::<Mailbox>())
+      .collect::<Result<Vec<_>, _>>()
+      .map_err(|_| config_error("a recipient is invalid"))?;
+    let credentials = match (cli.smtp_username(), cli.smtp_password()) {
+      (Some(username), Some(password)) => {
+        Some(Credentials::new(username.to_string(), password.to_string()))
+      }
+      (None, None) => None,
+      _ => {
+        return Err(config_error(
+          "the username and password have to be given together",
+        ))
+      }
+    };
+    if credentials.is_some() && security == Security::None {
+      warn!("Sending the SMTP credentials without TLS");
+    }
+
+    Ok(Some(Self {
+      transport: transport(host, port, security, credentials, &from)?,
+      from,
+      to,
+      interval: Duration::from_secs(cli.smtp_interval()),
+      state: cli.smtp_state(),
+    }))
+  }
+
+  async fn rate_limit(&self) -> error_stack::Result<RateLimit, Errors> {
+    match tokio::fs::read(&self.state).await {
+      Ok(content) => serde_json::from_slice(&content)
+        .into_report()
+        .change_context_lazy(|| Errors::Storage(self.state.display().to_string())),
+      Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(RateLimit::default()),
+      Err(e) => Err(e)
+        .into_report()
+        .change_context_lazy(|| Errors::Storage(self.state.display().to_string())),
+    }
+  }
+
+  async fn save_rate_limit(&self, rate_limit: &RateLimit) -> error_stack::Result<(), Errors> {
+    let storage_error = || Errors::Storage(self.state.display().to_string());
+
+    let content = serde_json::to_vec(rate_limit)
+      .into_report()
+      .change_context_lazy(storage_error)?;
+    if let Some(directory) = self.state.parent() {
+      tokio::fs::create_dir_all(directory)
+        .await
+        .into_report()
+        .change_context_lazy(storage_error)?;
+    }
+    tokio::fs::write(&self.state, content)
+      .await
+      .into_report()
+      .change_context_lazy(storage_error)
+  }
+
+  /// The mail of `digest`, lettre picks a transfer encoding which does not
+  /// need 8BITMIME.
+  fn message(&self, digest: &Digest, suppressed: u32) -> error_stack::Result<Message, Errors> {
+    let id = rand::thread_rng().gen::<u128>();
+
+    let mut message = Message::builder()
+      .from(self.from.clone())
+      .subject(subject(digest))
+      .date_now()
+      .message_id(Some(format!("<{id:032x}@{}>", self.from.email.domain())))
+      .header(ContentType::TEXT_PLAIN);
+    for to in &self.to {
+      message = message.to(to.clone());
+    }
+
+    message
+      .body(body(digest, suppressed))
+      .into_report()
+      .change_context(Errors::Notify(NAME))
+  }
+
+  async fn send(&self, message: Message) -> error_stack::Result<(), Errors> {
+    self
+      .transport
+      .send(message)
+      .await
+      .into_report()
+      .change_context(Errors::Notify(NAME))
+      .map(|_| ())
+  }
+
+  fn recipients(&self) -> String {
+    self
+      .to
+      .iter()
+      .map(ToString::to_string)
+      .collect::<Vec<_>>()
+      .join(", ")
+  }
+}
+
+fn transport(
+  host: &str,
+  port: u16,
+  security: Security,
+  credentials: Option<Credentials>,
+  from: &Mailbox,
+) -> error_stack::Result<AsyncSmtpTransport<Tokio1Executor>, Errors> {
+  let builder = match security {
+    Security::None => Ok(AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(
+      host,
+    )),
+    Security::StartTls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host),
+    Security::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(host),
+  }
+  .into_report()
+  .change_context(Errors::Notify(NAME))?
+  .port(port)
+  .timeout(Some(TIMEOUT))
+  .hello_name(ClientId::Domain(from.email.domain().to_string()));
+
+  Ok(match credentials {
+    Some(credentials) => builder.credentials(credentials).build(),
+    None => builder.build(),
+  })
+}
+
+#[async_trait]
+impl Notifier for Email {
+  fn name(&self) -> &'static str {
+    NAME
+  }
+
+  /// Only the digest is mailed.
+  async fn notify(&self, _event: &Event) -> error_stack::Result<(), Errors> {
+    Ok(())
+  }
+
+  async fn digest(&self, digest: &Digest) -> error_stack::Result<(), Errors> {
+    let mut rate_limit = self.rate_limit().await?;
+    if digest.events().is_empty() && rate_limit.events.is_empty() {
+      return Ok(());
+    }
+
+    let now = SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .unwrap_or_default()
+      .as_secs();
+    if now.saturating_sub(rate_limit.last_sent) < self.interval.as_secs() {
+      if digest.events().is_empty() {
+        return Ok(());
+      }
+      rate_limit.hold(digest);
+      info!(
+        "Holding the digest back, the last one was sent less than {:?} ago",
+        self.interval
+      );
+      return self.save_rate_limit(&rate_limit).await;
+    }
+
+    let digest = rate_limit.with(digest);
+    self
+      .send(self.message(&digest, rate_limit.suppressed)?)
+      .await?;
+    info!("Mailed the digest to {}", self.recipients());
+
+    self
+      .save_rate_limit(&RateLimit {
+        last_sent: now,
+        ..RateLimit::default()
+      })
+      .await
+  }
+}
+
+fn subject(digest: &Digest) -> String {
+  let counts = [
+    (Severity::Change, "change"),
+    (Severity::Warning, "warning"),
+    (Severity::Error, "error"),
+  ]
+  .into_iter()
+  .filter_map(|(severity, name)| match digest.count(severity) {
+    0 => None,
+    1 => Some(format!("1 {name}")),
+    count => Some(format!("{count} {name}s")),
+  })
+  .collect::<Vec<_>>();
+
+  format!("Netcup DDNS: {}", counts.join(", "))
+}
+
+fn body(digest: &Digest, suppressed: u32) -> String {
+  let mut body = format!("Zones processed: {}\n", digest.zones().join(", "));
+
+  for (severity, title) in [
+    (Severity::Change, "Changed records"),
+    (Severity::Warning, "Warnings"),
+    (Severity::Error, "Errors"),
+  ] {
+    let events = digest
+      .events()
+      .iter()
+      .filter(|event| event.severity() == severity)
+      .collect::<Vec<_>>();
+    if events.is_empty() {
+      continue;
+    }
+
+    body.push_str(&format!("\n{title}:\n"));
+    for event in events {
+      body.push_str(&format!("- {}\n", event.message()));
+      for message in event.server_messages() {
+        body.push_str(&format!("  {message}\n"));
+      }
+    }
+  }
+
+  if suppressed > 0 {
+    body.push_str(&format!(
+      "\n{suppressed} digests held back by the rate limit are included.\n"
+    ));
+  }
+
+  body
+}
+
+#[cfg(test)]
+mod test {
+  use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::TcpListener,
+    sync::mpsc,
+  };
+
+  use super::*;
+  use crate::api::netcup::{models::DnsRecord, ServerMessage};
+
+  /// Accepts SMTP sessions and sends what the client sent in each.
+  async fn sink() -> (u16, mpsc::UnboundedReceiver<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (sender, receiver) = mpsc::unbounded_channel();
+
+    tokio::spawn(async move {
+      while let Ok((stream, _)) = listener.accept().await {
+        let mut stream = BufReader::new(stream);
+        let mut transcript = String::new();
+        stream.write_all(b"220 sink ESMTP\r\n").await.unwrap();
+
+        let mut data = false;
+        loop {
+          let mut line = String::new();
+          if stream.read_line(&mut line).await.unwrap_or(0) == 0 {
+            break;
+          }
+          transcript.push_str(&line);
+
+          let reply: &[u8] = match line.trim_end() {
+            "." if data => {
+              data = false;
+              b"250 queued\r\n"
+            }
+            _ if data => continue,
+            "DATA" => {
+              data = true;
+              b"354 go ahead\r\n"
+            }
+            "QUIT" => {
+              stream.write_all(b"221 bye\r\n").await.unwrap();
+              break;
+            }
+            line if line.starts_with("EHLO") => b"250-sink\r\n250 AUTH PLAIN LOGIN\r\n",
+            line if line.starts_with("AUTH") => b"235 ok\r\n",
+            _ => b"250 ok\r\n",
+          };
+          stream.write_all(reply).await.unwrap();
+        }
+
+        let _ = sender.send(transcript);
+      }
+    });
+
+    (port, receiver)
+  }
+
+  #[tokio::test]
+  async fn mail_rate_limited_digests() {
+    let (port, mut sessions) = sink().await;
+    let state = std::env::temp_dir().join(format!("smtp-state-{}.json", std::process::id()));
+    let from = "DDNS <ddns@example.com>".parse::<Mailbox>().unwrap();
+    let credentials = Credentials::new("user".into(), "secret".into());
+    let email = Email {
+      transport: transport("127.0.0.1", port, Security::None, Some(credentials), &from).unwrap(),
+      from,
+      to: vec!["admin@example.com".parse().unwrap()],
+      interval: Duration::from_secs(3600),
+      state: state.clone(),
+    };
+
+    let record = DnsRecord::new("www", "192.0.2.1".parse().unwrap());
+    let error = Report::new(Errors::UpdateDNSRecords("example.org".into())).attach_printable(
+      ServerMessage::new(
+        "Validation Error.",
+        Some("Value in field destination".into()),
+      ),
+    );
+    let digest = Digest {
+      zones: vec!["example.com".into(), "example.org".into()],
+      events: vec![
+        Event::change("example.com", &record, None),
+        Event::error(Some("example.org"), &error),
+      ],
+      ..Digest::default()
+    };
+
+    email.digest(&digest).await.unwrap();
+    let transcript = sessions.recv().await.unwrap();
+
+    assert!(transcript.contains("EHLO example.com\r\n"));
+    assert!(transcript.contains("AUTH PLAIN AHVzZXIAc2VjcmV0\r\n"));
+    assert!(transcript.contains("MAIL FROM:<ddns@example.com>\r\n"));
+    assert!(transcript.contains("RCPT TO:<admin@example.com>\r\n"));
+    assert!(transcript.contains("Subject: Netcup DDNS: 1 change, 1 error\r\n"));
+    assert!(transcript.contains("Zones processed: example.com, example.org\r\n"));
+    assert!(transcript.contains("- Created www A 192.0.2.1 in example.com\r\n"));
+    assert!(transcript.contains("  Netcup: Validation Error.: Value in field destination\r\n"));
+
+    // The next digest is held back with its events.
+    let idn = Digest {
+      zones: vec!["xn--mller-bau-q9a.de".into()],
+      events: vec![Event::change("xn--mller-bau-q9a.de", &record, None)],
+      ..Digest::default()
+    };
+    email.digest(&idn).await.unwrap();
+    let mut rate_limit = email.rate_limit().await.unwrap();
+    assert_eq!(1, rate_limit.suppressed);
+    assert_eq!(idn.events(), &rate_limit.events);
+
+    // Once the interval passed, the held events are mailed even without new
+    // ones, the umlauts without relying on 8BITMIME.
+    rate_limit.last_sent = 0;
+    email.save_rate_limit(&rate_limit).await.unwrap();
+    email.digest(&Digest::default()).await.unwrap();
+    let transcript = sessions.recv().await.unwrap();
+    let rate_limit = email.rate_limit().await.unwrap();
+    std::fs::remove_file(&state).unwrap();
+
+    assert!(transcript.contains("Subject: Netcup DDNS: 1 change\r\n"));
+    assert!(transcript.contains("Content-Transfer-Encoding: quoted-printable\r\n"));
+    assert!(transcript.contains("m=C3=BCller-bau.de"));
+    assert!(transcript.contains("1 digests held back by the rate limit are included."));
+    assert!(!transcript.contains("8bit"));
+    assert!(rate_limit.events.is_empty());
+  }
+}
@@ -0,0 +1,124 @@
+//! Sends events as messages of a [Gotify](https://gotify.net) application,
+//! configured as `gotify://apptoken@host[/path]`.
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use error_stack::Report;
+use reqwest::Url;
+use serde_json::json;
+
+use crate::errors::Errors;
+
+use super::{credentials, send, with_retries, Event, Notifier, Severity, Target, RETRY_DELAY};
+
+const NAME: &str = "gotify";
+
+pub struct Gotify {
+  client: reqwest::Client,
+  url: Url,
+  token: String,
+  retries: u32,
+  retry_delay: Duration,
+}
+
+impl Gotify {
+  pub fn new(target: &Target, retries: u32) -> error_stack::Result<Self, Errors> {
+    let (mut url, token, _) = credentials(&target.url);
+    let token = token.ok_or_else(|| {
+      Report::new(Errors::NotifierConfig(
+        NAME,
+        "the app token is missing".into(),
+      ))
+    })?;
+
+    if let Ok(mut segments) = url.path_segments_mut() {
+      segments.pop_if_empty().push("message");
+    }
+
+    Ok(Self {
+      client: reqwest::Client::new(),
+      url,
+      token,
+      retries,
+      retry_delay: RETRY_DELAY,
+    })
+  }
+}
+
+#[async_trait]
+impl Notifier for Gotify {
+  fn name(&self) -> &'static str {
+    NAME
+  }
+
+  async fn notify(&self, event: &Event) -> error_stack::Result<(), Errors> {
+    let priority = match event.severity() {
+      Severity::Change => 2,
+      Severity::Warning => 5,
+      Severity::Error => 8,
+    };
+    let body = json!({
+      "title": event.title(),
+      "message": event.message(),
+      "priority": priority,
+    });
+
+    with_retries(NAME, self.retries, self.retry_delay, || {
+      send(
+        NAME,
+        self
+          .client
+          .post(self.url.clone())
+          .header("X-Gotify-Key", &self.token)
+          .json(&body),
+      )
+    })
+    .await
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use wiremock::{
+    matchers::{body_json, header, method, path},
+    Mock, MockServer, ResponseTemplate,
+  };
+
+  use super::*;
+
+  #[tokio::test]
+  async fn send_messages() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+      .and(path("/gotify/message"))
+      .and(header("X-Gotify-Key", "app-token"))
+      .and(body_json(json!({
+        "title": "DNS warning (example.com)",
+        "message": "TTL is 86400 and should be 300",
+        "priority": 5
+      })))
+      .respond_with(ResponseTemplate::new(200))
+      .expect(1)
+      .mount(&server)
+      .await;
+
+    let target = format!("gotify+http://app-token@{}/gotify/", server.address())
+      .parse::<Target>()
+      .unwrap();
+    Gotify::new(&target, 0)
+      .unwrap()
+      .notify(&Event::warning(
+        "example.com",
+        "TTL is 86400 and should be 300",
+      ))
+      .await
+      .unwrap();
+
+    let target = format!("gotify+http://{}", server.address())
+      .parse::<Target>()
+      .unwrap();
+    assert!(Gotify::new(&target, 0).is_err());
+  }
+}
@@ -0,0 +1,153 @@
+//! Posts events as notices to a [Matrix](https://matrix.org) room, configured
+//! as `matrix://accesstoken@homeserver?room=!room:example.com`.
+
+use std::{
+  sync::atomic::{AtomicU64, Ordering},
+  time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use async_trait::async_trait;
+use error_stack::Report;
+use reqwest::Url;
+use serde_json::json;
+
+use crate::errors::Errors;
+
+use super::{credentials, send, with_retries, Event, Notifier, Target, RETRY_DELAY};
+
+const NAME: &str = "matrix";
+
+pub struct Matrix {
+  client: reqwest::Client,
+  homeserver: Url,
+  token: String,
+  room: String,
+  /// Makes the transaction ids unique within the process.
+  transactions: AtomicU64,
+  retries: u32,
+  retry_delay: Duration,
+}
+
+impl Matrix {
+  pub fn new(target: &Target, retries: u32) -> error_stack::Result<Self, Errors> {
+    let config_error = |message: &str| Report::new(Errors::NotifierConfig(NAME, message.into()));
+
+    let (mut homeserver, token, _) = credentials(&target.url);
+    let token = token.ok_or_else(|| config_error("the access token is missing"))?;
+    let room = homeserver
+      .query_pairs()
+      .find(|(key, _)| key == "room")
+      .map(|(_, room)| room.into_owned())
+      .ok_or_else(|| config_error("the room is missing"))?;
+    homeserver.set_query(None);
+
+    Ok(Self {
+      client: reqwest::Client::new(),
+      homeserver,
+      token,
+      room,
+      transactions: AtomicU64::new(0),
+      retries,
+      retry_delay: RETRY_DELAY,
+    })
+  }
+
+  /// The URL to send a message with `transaction`, retries reuse it so the
+  /// homeserver doesn't post the message twice.
+  fn url(&self, transaction: &str) -> Url {
+    let mut url = self.homeserver.clone();
+    if let Ok(mut segments) = url.path_segments_mut() {
+      segments.pop_if_empty().extend([
+        "_matrix",
+        "client",
+        "v3",
+        "rooms",
+        &self.room,
+        "send",
+        "m.room.message",
+        transaction,
+      ]);
+    }
+
+    url
+  }
+}
+
+#[async_trait]
+impl Notifier for Matrix {
+  fn name(&self) -> &'static str {
+    NAME
+  }
+
+  async fn notify(&self, event: &Event) -> error_stack::Result<(), Errors> {
+    let started = SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .unwrap_or_default()
+      .as_millis();
+    let transaction = format!(
+      "ddns-{started}-{}",
+      self.transactions.fetch_add(1, Ordering::Relaxed)
+    );
+    let url = self.url(&transaction);
+    let body = json!({
+      "msgtype": "m.notice",
+      "body": format!("{}: {}", event.title(), event.message()),
+    });
+
+    with_retries(NAME, self.retries, self.retry_delay, || {
+      send(
+        NAME,
+        self
+          .client
+          .put(url.clone())
+          .bearer_auth(&self.token)
+          .json(&body),
+      )
+    })
+    .await
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use wiremock::{
+    matchers::{body_json, header, method, path_regex},
+    Mock, MockServer, ResponseTemplate,
+  };
+
+  use super::*;
+
+  #[tokio::test]
+  async fn send_notices() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("PUT"))
+      .and(path_regex(
+        r"^/_matrix/client/v3/rooms/!room:example\.org/send/m\.room\.message/ddns-\d+-0$",
+      ))
+      .and(header("Authorization", "Bearer syt_token"))
+      .and(body_json(json!({
+        "msgtype": "m.notice",
+        "body": "DNS warning (example.com): TTL is 86400 and should be 300"
+      })))
+      .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "event_id": "$1" })))
+      .expect(1)
+      .mount(&server)
+      .await;
+
+    let target = format!(
+      "matrix+http://syt_token@{}?room=!room:example.org",
+      server.address()
+    )
+    .parse::<Target>()
+    .unwrap();
+    Matrix::new(&target, 0)
+      .unwrap()
+      .notify(&Event::warning(
+        "example.com",
+        "TTL is 86400 and should be 300",
+      ))
+      .await
+      .unwrap();
+  }
+}
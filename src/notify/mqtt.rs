@@ -0,0 +1,475 @@
+//! Publishes the state of the updater to an MQTT broker, with Home Assistant
+//! discovery so its sensors show up on their own.
+//!
+//! Below the topic `<topic>` are `status` (online or offline), `ipv4`, `ipv6`,
+//! `last_update`, `last_error` and `records/<fqdn>` with the published value of
+//! every hostname. Publishing `update` to `<topic>/command` starts a run right
+//! away when running with an interval.
+
+use std::{
+  collections::{BTreeMap, HashSet},
+  net::IpAddr,
+  sync::{Arc, Mutex},
+  time::{Duration, SystemTime},
+};
+
+use async_trait::async_trait;
+use error_stack::Report;
+use rumqttc::{
+  AsyncClient, ConnectReturnCode, Event as MqttEvent, EventLoop, LastWill, MqttOptions, Outgoing,
+  Packet, QoS,
+};
+use serde_json::{json, Value};
+use tokio::{sync::Notify, task::JoinHandle};
+use tracing::{info, warn};
+
+use crate::{cli::Cli, errors::Errors};
+
+use super::{Digest, Event, Notifier, Severity};
+
+const NAME: &str = "mqtt";
+const DEFAULT_PORT: u16 = 1883;
+const KEEP_ALIVE: Duration = Duration::from_secs(60);
+const RECONNECT_DELAY: Duration = Duration::from_secs(10);
+/// How long closing waits for the queued messages to be published.
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+/// How many messages are queued while the broker is unreachable.
+const QUEUE: usize = 256;
+const COMMAND_UPDATE: &[u8] = b"update";
+
+/// The latest payload of every topic, published again after reconnecting.
+type Retained = Arc<Mutex<BTreeMap<String, Vec<u8>>>>;
+
+pub struct Mqtt {
+  topic: String,
+  discovery_prefix: String,
+  node_id: String,
+  client: AsyncClient,
+  retained: Retained,
+  connection: Mutex<Option<JoinHandle<()>>>,
+  /// The hostnames which already have a discovered sensor.
+  announced: Mutex<HashSet<String>>,
+}
+
+impl Mqtt {
+  pub fn from_cli(cli: &Cli, trigger: Arc<Notify>) -> error_stack::Result<Option<Self>, Errors> {
+    let Some(broker) = cli.mqtt_broker() else {
+      return Ok(None);
+    };
+    let config_error = |message: &str| Report::new(Errors::NotifierConfig(NAME, message.into()));
+
+    let (host, port) = match broker.rsplit_once(':') {
+      Some((host, port)) => (
+        host,
+        port
+          .parse()
+          .map_err(|_| config_error("the port of the broker is invalid"))?,
+      ),
+      None => (broker, DEFAULT_PORT),
+    };
+    let topic = cli.mqtt_topic().trim_end_matches('/').to_string();
+    let mut options = MqttOptions::new(
+      format!("{}-{}", node_id(&topic), std::process::id()),
+      host,
+      port,
+    );
+    match (cli.mqtt_username(), cli.mqtt_password()) {
+      (Some(username), Some(password)) => {
+        options.set_credentials(username, password);
+      }
+      (None, None) => {}
+      _ => {
+        return Err(config_error(
+          "the username and password have to be given together",
+        ))
+      }
+    }
+
+    Ok(Some(Self::new(
+      options,
+      topic,
+      cli.mqtt_discovery_prefix(),
+      trigger,
+    )))
+  }
+
+  fn new(
+    mut options: MqttOptions,
+    topic: String,
+    discovery_prefix: &str,
+    trigger: Arc<Notify>,
+  ) -> Self {
+    options
+      .set_keep_alive(KEEP_ALIVE)
+      .set_last_will(LastWill::new(
+        format!("{topic}/status"),
+        "offline",
+        QoS::AtLeastOnce,
+        true,
+      ));
+    let (client, events) = AsyncClient::new(options, QUEUE);
+    let retained = Retained::default();
+
+    let mqtt = Self {
+      discovery_prefix: discovery_prefix.trim_end_matches('/').to_string(),
+      node_id: node_id(&topic),
+      connection: Mutex::new(Some(tokio::spawn(connect(
+        client.clone(),
+        events,
+        topic.clone(),
+        retained.clone(),
+        trigger,
+      )))),
+      topic,
+      client,
+      retained,
+      announced: Mutex::default(),
+    };
+    mqtt.announce();
+
+    mqtt
+  }
+
+  fn publish(&self, topic: String, payload: impl Into<Vec<u8>>) {
+    let payload = payload.into();
+    if let Ok(mut retained) = self.retained.lock() {
+      retained.insert(topic.clone(), payload.clone());
+    }
+    if let Err(e) = self
+      .client
+      .try_publish(topic, QoS::AtLeastOnce, true, payload)
+    {
+      warn!("Could not queue the MQTT message: {e}");
+    }
+  }
+
+  /// Publishes the discovery configs of the sensors and the update button.
+  fn announce(&self) {
+    for (object_id, name, extra) in [
+      ("ipv4", "IPv4 address", json!({ "icon": "mdi:ip-network" })),
+      ("ipv6", "IPv6 address", json!({ "icon": "mdi:ip-network" })),
+      (
+        "last_update",
+        "Last update",
+        json!({ "device_class": "timestamp" }),
+      ),
+      ("last_error", "Last error", json!({ "icon": "mdi:alert" })),
+    ] {
+      self.announce_sensor(
+        object_id,
+        name,
+        &format!("{}/{object_id}", self.topic),
+        extra,
+      );
+    }
+
+    let mut config = self.config("update", "Update now");
+    config["command_topic"] = format!("{}/command", self.topic).into();
+    config["payload_press"] = String::from_utf8_lossy(COMMAND_UPDATE).into();
+    self.publish(
+      format!(
+        "{}/button/{}/update/config",
+        self.discovery_prefix, self.node_id
+      ),
+      config.to_string(),
+    );
+  }
+
+  fn announce_sensor(&self, object_id: &str, name: &str, state_topic: &str, extra: Value) {
+    let mut config = self.config(object_id, name);
+    config["state_topic"] = state_topic.into();
+    if let (Some(config), Value::Object(extra)) = (config.as_object_mut(), extra) {
+      config.extend(extra);
+    }
+
+    self.publish(
+      format!(
+        "{}/sensor/{}/{object_id}/config",
+        self.discovery_prefix, self.node_id
+      ),
+      config.to_string(),
+    );
+  }
+
+  fn config(&self, object_id: &str, name: &str) -> Value {
+    json!({
+      "name": name,
+      "unique_id": format!("{}_{object_id}", self.node_id),
+      "availability_topic": format!("{}/status", self.topic),
+      "device": {
+        "identifiers": [self.node_id],
+        "name": "Netcup DDNS",
+      },
+    })
+  }
+}
+
+#[async_trait]
+impl Notifier for Mqtt {
+  fn name(&self) -> &'static str {
+    NAME
+  }
+
+  async fn notify(&self, event: &Event) -> error_stack::Result<(), Errors> {
+    if event.severity() == Severity::Error {
+      self.publish(format!("{}/last_error", self.topic), event.message());
+    }
+
+    Ok(())
+  }
+
+  async fn digest(&self, digest: &Digest) -> error_stack::Result<(), Errors> {
+    let address = |v4: bool| {
+      digest
+        .ips()
+        .iter()
+        .find(|ip| ip.is_ipv4() == v4)
+        .map(IpAddr::to_string)
+        .unwrap_or_default()
+    };
+    self.publish(format!("{}/ipv4", self.topic), address(true));
+    self.publish(format!("{}/ipv6", self.topic), address(false));
+
+    for record in digest.records() {
+      let fqdn = record.fqdn();
+      let state_topic = format!("{}/records/{fqdn}", self.topic);
+
+      let new = self
+        .announced
+        .lock()
+        .map(|mut announced| announced.insert(fqdn.clone()))
+        .unwrap_or(false);
+      if new {
+        self.announce_sensor(
+          &node_id(&fqdn),
+          &fqdn,
+          &state_topic,
+          json!({ "icon": "mdi:dns" }),
+        );
+      }
+
+      self.publish(state_topic, record.value());
+    }
+
+    // The error of an earlier run is cleared by a successful one.
+    if digest.count(Severity::Error) == 0 {
+      self.publish(
+        format!("{}/last_update", self.topic),
+        humantime::format_rfc3339_seconds(SystemTime::now()).to_string(),
+      );
+      self.publish(format!("{}/last_error", self.topic), "");
+    }
+
+    Ok(())
+  }
+
+  async fn close(&self) {
+    self.publish(format!("{}/status", self.topic), "offline");
+    if let Err(e) = self.client.try_disconnect() {
+      warn!("Could not disconnect from the MQTT broker: {e}");
+    }
+    let connection = self
+      .connection
+      .lock()
+      .ok()
+      .and_then(|mut connection| connection.take());
+
+    if let Some(mut connection) = connection {
+      if tokio::time::timeout(CLOSE_TIMEOUT, &mut connection)
+        .await
+        .is_err()
+      {
+        warn!("Could not publish every MQTT message before closing");
+        connection.abort();
+      }
+    }
+  }
+}
+
+/// Keeps the broker connected until the client disconnects. After every
+/// connection the client subscribes to the commands and publishes its status
+/// and, after reconnecting, the latest message of every topic again.
+async fn connect(
+  client: AsyncClient,
+  mut events: EventLoop,
+  topic: String,
+  retained: Retained,
+  trigger: Arc<Notify>,
+) {
+  let (status_topic, command_topic) = (format!("{topic}/status"), format!("{topic}/command"));
+  let mut reconnect = false;
+
+  loop {
+    match events.poll().await {
+      Ok(MqttEvent::Incoming(Packet::ConnAck(connack)))
+        if connack.code == ConnectReturnCode::Success =>
+      {
+        info!("Connected to the MQTT broker");
+        let mut messages = vec![(status_topic.clone(), b"online".to_vec())];
+        if reconnect {
+          if let Ok(retained) = retained.lock() {
+            messages.extend(retained.clone());
+          }
+        }
+        reconnect = true;
+
+        let subscribed = client.try_subscribe(command_topic.as_str(), QoS::AtMostOnce);
+        let published = messages.into_iter().try_for_each(|(topic, payload)| {
+          client.try_publish(topic, QoS::AtLeastOnce, true, payload)
+        });
+        if let Err(e) = subscribed.and(published) {
+          warn!("Could not queue the MQTT messages after connecting: {e}");
+        }
+      }
+      Ok(MqttEvent::Incoming(Packet::Publish(publish))) if publish.topic == command_topic => {
+        if publish.payload.trim_ascii() == COMMAND_UPDATE {
+          info!("Received the MQTT command to update");
+          trigger.notify_one();
+        }
+      }
+      Ok(MqttEvent::Outgoing(Outgoing::Disconnect)) => return,
+      Ok(_) => {}
+      Err(e) => {
+        warn!("The MQTT connection failed, reconnecting in {RECONNECT_DELAY:?}: {e}");
+        tokio::time::sleep(RECONNECT_DELAY).await;
+      }
+    }
+  }
+}
+
+/// `topic` as an identifier Home Assistant accepts.
+fn node_id(topic: &str) -> String {
+  topic
+    .chars()
+    .map(|c| match c.is_ascii_alphanumeric() || c == '-' {
+      true => c.to_ascii_lowercase(),
+      false => '_',
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod test {
+  use bytes::BytesMut;
+  use rumqttc::{mqttbytes, ConnAck, PingResp, PubAck, Publish, SubAck, SubscribeReasonCode};
+  use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+  };
+
+  use super::*;
+  use crate::{
+    api::netcup::models::DnsRecord,
+    notify::{Event, Notifiers},
+  };
+
+  /// Accepts one client, asks it to update once it subscribed and returns the
+  /// messages it published.
+  async fn broker() -> (u16, JoinHandle<Vec<(String, String)>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    let handle = tokio::spawn(async move {
+      let (mut stream, _) = listener.accept().await.unwrap();
+      let mut buffer = BytesMut::new();
+      let mut published = vec![];
+
+      loop {
+        let packet = match mqttbytes::v4::read(&mut buffer, 1 << 20) {
+          Ok(packet) => packet,
+          Err(mqttbytes::Error::InsufficientBytes(_)) => match stream.read_buf(&mut buffer).await {
+            Ok(0) | Err(_) => break,
+            Ok(_) => continue,
+          },
+          Err(e) => panic!("{e}"),
+        };
+
+        let mut reply = BytesMut::new();
+        match packet {
+          Packet::Connect(_) => ConnAck::new(ConnectReturnCode::Success, false).write(&mut reply),
+          Packet::Subscribe(subscribe) => {
+            SubAck::new(
+              subscribe.pkid,
+              vec![SubscribeReasonCode::Success(QoS::AtMostOnce)],
+            )
+            .write(&mut reply)
+            .unwrap();
+            Publish::new("ddns/command", QoS::AtMostOnce, "update").write(&mut reply)
+          }
+          Packet::Publish(publish) => {
+            published.push((
+              publish.topic.clone(),
+              String::from_utf8(publish.payload.to_vec()).unwrap(),
+            ));
+            PubAck::new(publish.pkid).write(&mut reply)
+          }
+          Packet::PingReq => PingResp.write(&mut reply),
+          Packet::Disconnect => break,
+          _ => Ok(0),
+        }
+        .unwrap();
+        stream.write_all(&reply).await.unwrap();
+      }
+
+      published
+    });
+
+    (port, handle)
+  }
+
+  #[tokio::test]
+  async fn publish_state_and_discovery() {
+    let (port, published) = broker().await;
+    let trigger = Arc::new(Notify::new());
+    let mqtt = Mqtt::new(
+      MqttOptions::new("test", "127.0.0.1", port),
+      "ddns".into(),
+      "homeassistant",
+      trigger.clone(),
+    );
+
+    let notifiers = Notifiers::default();
+    notifiers.detected(&["192.0.2.1".parse().unwrap()]);
+    notifiers.published(
+      "example.com",
+      &DnsRecord::new("www", "192.0.2.1".parse().unwrap()),
+    );
+    let error = Report::new(Errors::UpdateDNSRecords("example.com".into()));
+    mqtt
+      .notify(&Event::error(Some("example.com"), &error))
+      .await
+      .unwrap();
+    mqtt.digest(&notifiers.take_digest()).await.unwrap();
+
+    tokio::time::timeout(Duration::from_secs(5), trigger.notified())
+      .await
+      .expect("the command triggers an update");
+    mqtt.close().await;
+
+    let published = published.await.unwrap();
+    let payload = |topic: &str| {
+      published
+        .iter()
+        .rev()
+        .find(|(published, _)| published == topic)
+        .map(|(_, payload)| payload.as_str())
+    };
+
+    assert_eq!(Some("192.0.2.1"), payload("ddns/ipv4"));
+    assert_eq!(Some(""), payload("ddns/ipv6"));
+    assert_eq!(Some("192.0.2.1"), payload("ddns/records/www.example.com"));
+    assert_eq!(Some("offline"), payload("ddns/status"));
+    assert!(payload("ddns/last_update").is_some());
+    // The error is cleared by the successful run.
+    assert_eq!(Some(""), payload("ddns/last_error"));
+
+    let config: Value =
+      serde_json::from_str(payload("homeassistant/sensor/ddns/www_example_com/config").unwrap())
+        .unwrap();
+    assert_eq!("ddns/records/www.example.com", config["state_topic"]);
+    assert_eq!("ddns/status", config["availability_topic"]);
+    let config: Value =
+      serde_json::from_str(payload("homeassistant/button/ddns/update/config").unwrap()).unwrap();
+    assert_eq!("ddns/command", config["command_topic"]);
+  }
+}
@@ -0,0 +1,186 @@
+//! Posts every event as JSON to an URL.
+//!
+//! The body is the event itself or a JSON template whose strings may contain
+//! the placeholders `{{severity}}`, `{{zone}}`, `{{hostname}}`, `{{old_ip}}`,
+//! `{{new_ip}}`, `{{error}}` and `{{message}}`. A string which is only a
+//! placeholder becomes `null` if the event has no such value.
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use error_stack::{IntoReport, ResultExt};
+use serde_json::Value;
+
+use crate::errors::Errors;
+
+use super::{send, with_retries, Event, Notifier, RETRY_DELAY};
+
+const NAME: &str = "webhook";
+
+pub struct Webhook {
+  client: reqwest::Client,
+  url: String,
+  template: Option<Value>,
+  retries: u32,
+  retry_delay: Duration,
+}
+
+impl Webhook {
+  pub fn new(url: &str, template: Option<&str>, retries: u32) -> error_stack::Result<Self, Errors> {
+    let template = template
+      .map(serde_json::from_str)
+      .transpose()
+      .into_report()
+      .change_context(Errors::NotifierConfig(
+        NAME,
+        "the template is not valid JSON".into(),
+      ))?;
+
+    Ok(Self {
+      client: reqwest::Client::new(),
+      url: url.into(),
+      template,
+      retries,
+      retry_delay: RETRY_DELAY,
+    })
+  }
+
+  fn body(&self, event: &Event) -> Value {
+    match &self.template {
+      Some(template) => render(template, event),
+      None => serde_json::to_value(event).unwrap_or_default(),
+    }
+  }
+}
+
+#[async_trait]
+impl Notifier for Webhook {
+  fn name(&self) -> &'static str {
+    NAME
+  }
+
+  async fn notify(&self, event: &Event) -> error_stack::Result<(), Errors> {
+    let body = self.body(event);
+
+    with_retries(NAME, self.retries, self.retry_delay, || {
+      send(NAME, self.client.post(&self.url).json(&body))
+    })
+    .await
+  }
+}
+
+fn render(template: &Value, event: &Event) -> Value {
+  match template {
+    Value::String(template) => {
+      if let Some(name) = template
+        .strip_prefix("{{")
+        .and_then(|name| name.strip_suffix("}}"))
+        .filter(|name| !name.contains("{{"))
+      {
+        return event
+          .field(name.trim())
+          .map(Value::String)
+          .unwrap_or(Value::Null);
+      }
+
+      let mut rendered = template.clone();
+      for name in [
+        "severity", "zone", "hostname", "old_ip", "new_ip", "error", "message",
+      ] {
+        rendered = rendered.replace(
+          &format!("{{{{{name}}}}}"),
+          &event.field(name).unwrap_or_default(),
+        );
+      }
+      Value::String(rendered)
+    }
+    Value::Array(values) => Value::Array(values.iter().map(|value| render(value, event)).collect()),
+    Value::Object(values) => Value::Object(
+      values
+        .iter()
+        .map(|(key, value)| (key.clone(), render(value, event)))
+        .collect(),
+    ),
+    value => value.clone(),
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use serde_json::json;
+  use wiremock::{
+    matchers::{body_json, method, path},
+    Mock, MockServer, ResponseTemplate,
+  };
+
+  use super::*;
+  use crate::api::netcup::models::DnsRecord;
+
+  fn event() -> Event {
+    let old = DnsRecord::new("www", "192.0.2.1".parse().unwrap());
+    let new = DnsRecord::new("www", "192.0.2.2".parse().unwrap());
+
+    Event::change("example.com", &new, Some(&old))
+  }
+
+  #[test]
+  fn render_templates() {
+    let template = json!({
+      "text": "{{hostname}}.{{zone}} is now {{new_ip}}",
+      "old": "{{old_ip}}",
+      "error": "{{error}}",
+      "tags": ["ddns", "{{severity}}"],
+      "priority": 3
+    });
+
+    assert_eq!(
+      json!({
+        "text": "www.example.com is now 192.0.2.2",
+        "old": "192.0.2.1",
+        "error": null,
+        "tags": ["ddns", "change"],
+        "priority": 3
+      }),
+      render(&template, &event())
+    );
+  }
+
+  #[tokio::test]
+  async fn retry_failed_requests() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("POST"))
+      .and(path("/hook"))
+      .respond_with(ResponseTemplate::new(502))
+      .up_to_n_times(2)
+      .expect(2)
+      .mount(&server)
+      .await;
+    Mock::given(method("POST"))
+      .and(path("/hook"))
+      .and(body_json(json!({ "ip": "192.0.2.2" })))
+      .respond_with(ResponseTemplate::new(204))
+      .expect(1)
+      .mount(&server)
+      .await;
+
+    let mut webhook = Webhook::new(
+      &format!("{}/hook", server.uri()),
+      Some(r#"{ "ip": "{{new_ip}}" }"#),
+      2,
+    )
+    .unwrap();
+    webhook.retry_delay = Duration::from_millis(1);
+
+    webhook.notify(&event()).await.unwrap();
+
+    server.reset().await;
+    Mock::given(method("POST"))
+      .respond_with(ResponseTemplate::new(500))
+      .expect(1)
+      .mount(&server)
+      .await;
+    webhook.retries = 0;
+    assert!(webhook.notify(&event()).await.is_err());
+  }
+}
@@ -0,0 +1,431 @@
+//! Waits until the authoritative nameservers of a zone serve changed records.
+//!
+//! The nameservers are looked up with an `NS` query at `--resolver` unless
+//! they are given with `--nameserver`, and are then queried directly so no
+//! cache is involved. A/AAAA records are propagated once a nameserver serves
+//! exactly the expected addresses, other records once the expected values are
+//! among the served ones.
+
+use std::{
+  collections::{BTreeMap, BTreeSet},
+  fmt,
+  net::{IpAddr, SocketAddr},
+  time::Duration,
+};
+
+use error_stack::{IntoReport, Report, ResultExt};
+use hickory_client::{
+  client::{AsyncClient, ClientHandle},
+  rr::{DNSClass, Name, RData, RecordType as HickoryRecordType},
+  udp::UdpClientStream,
+};
+use tokio::{net::UdpSocket, time::Instant};
+use tracing::{debug, info, warn};
+
+use crate::{
+  api::netcup::models::{DnsRecord, RecordType},
+  cli::Cli,
+  errors::Errors,
+};
+
+const QUERY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The propagation state of one nameserver.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NameserverStatus {
+  nameserver: String,
+  missing: Vec<String>,
+}
+
+impl NameserverStatus {
+  pub fn propagated(&self) -> bool {
+    self.missing.is_empty()
+  }
+}
+
+impl fmt::Display for NameserverStatus {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    if self.propagated() {
+      write!(f, "{}: propagated", self.nameserver)
+    } else {
+      write!(
+        f,
+        "{}: missing {}",
+        self.nameserver,
+        self.missing.join(", ")
+      )
+    }
+  }
+}
+
+#[derive(Clone)]
+pub struct Verifier {
+  resolver: String,
+  nameservers: Vec<String>,
+  timeout: Duration,
+  interval: Duration,
+}
+
+impl Verifier {
+  /// The verifier if `--verify-propagation` is turned on.
+  pub fn from_cli(cli: &Cli) -> Option<Self> {
+    cli.verify_propagation().then(|| Self {
+      resolver: cli.resolver().to_string(),
+      nameservers: cli.nameservers().clone(),
+      timeout: Duration::from_secs(cli.propagation_timeout()),
+      interval: Duration::from_secs(cli.propagation_interval()),
+    })
+  }
+
+  /// Polls every authoritative nameserver of `zone` until all serve `records`
+  /// or the timeout is reached, and returns the last status of each.
+  pub async fn wait(
+    &self,
+    zone: &str,
+    records: &[DnsRecord],
+  ) -> error_stack::Result<Vec<NameserverStatus>, Errors> {
+    let expected = expected(zone, records)?;
+    if expected.is_empty() {
+      return Ok(vec![]);
+    }
+
+    let nameservers = self.nameservers(zone).await?;
+    let deadline = Instant::now() + self.timeout;
+
+    loop {
+      let mut statuses = vec![];
+      for (nameserver, address) in &nameservers {
+        let missing = match missing(*address, &expected).await {
+          Ok(missing) => missing,
+          Err(e) => {
+            debug!("{e:?}");
+            vec![format!("no answer from {address}")]
+          }
+        };
+        statuses.push(NameserverStatus {
+          nameserver: nameserver.clone(),
+          missing,
+        });
+      }
+
+      if statuses.iter().all(NameserverStatus::propagated) {
+        statuses.iter().for_each(|status| info!("{status}"));
+        return Ok(statuses);
+      }
+
+      if Instant::now() + self.interval > deadline {
+        statuses.iter().for_each(|status| warn!("{status}"));
+        return Err(
+          Report::new(Errors::Propagation(zone.to_string())).attach_printable(
+            statuses
+              .iter()
+              .map(ToString::to_string)
+              .collect::<Vec<_>>()
+              .join("; "),
+          ),
+        );
+      }
+
+      debug!("Waiting for {zone} to propagate: {statuses:?}");
+      tokio::time::sleep(self.interval).await;
+    }
+  }
+
+  async fn nameservers(
+    &self,
+    zone: &str,
+  ) -> error_stack::Result<Vec<(String, SocketAddr)>, Errors> {
+    let names = if self.nameservers.is_empty() {
+      let resolver = address(&self.resolver).await?;
+      let origin = name(zone)?;
+
+      query(resolver, &origin, HickoryRecordType::NS)
+        .await?
+        .into_iter()
+        .filter_map(|data| match data {
+          RData::NS(ns) => Some(ns.0.to_ascii()),
+          _ => None,
+        })
+        .collect()
+    } else {
+      self.nameservers.clone()
+    };
+
+    let mut nameservers = vec![];
+    for nameserver in names {
+      match address(&nameserver).await {
+        Ok(address) => nameservers.push((nameserver, address)),
+        Err(e) => warn!("{e:?}"),
+      }
+    }
+
+    if nameservers.is_empty() {
+      return Err(Report::new(Errors::QueryNameserver(zone.to_string())));
+    }
+
+    Ok(nameservers)
+  }
+}
+
+/// The expected values of every changed name and type.
+fn expected(
+  zone: &str,
+  records: &[DnsRecord],
+) -> error_stack::Result<BTreeMap<(Name, HickoryRecordType), BTreeSet<String>>, Errors> {
+  let mut expected = BTreeMap::<_, BTreeSet<_>>::new();
+
+  for record in records.iter().filter(|record| !record.delete_record()) {
+    let record_type = match record.record_type() {
+      RecordType::A => HickoryRecordType::A,
+      RecordType::AAAA => HickoryRecordType::AAAA,
+      RecordType::Other(record_type) if record_type.eq_ignore_ascii_case("TXT") => {
+        HickoryRecordType::TXT
+      }
+      _ => continue,
+    };
+
+    let name = match record.host_name() {
+      "" | "@" => name(zone)?,
+      host_name => name(&format!("{host_name}.{zone}"))?,
+    };
+
+    expected
+      .entry((name, record_type))
+      .or_default()
+      .insert(record.destination().to_string());
+  }
+
+  Ok(expected)
+}
+
+/// The expected values `nameserver` doesn't serve yet.
+async fn missing(
+  nameserver: SocketAddr,
+  expected: &BTreeMap<(Name, HickoryRecordType), BTreeSet<String>>,
+) -> error_stack::Result<Vec<String>, Errors> {
+  let mut missing = vec![];
+
+  for ((name, record_type), values) in expected {
+    let served = query(nameserver, name, *record_type)
+      .await?
+      .iter()
+      .filter_map(value)
+      .collect::<BTreeSet<_>>();
+
+    let propagated = match record_type {
+      HickoryRecordType::A | HickoryRecordType::AAAA => &served == values,
+      _ => served.is_superset(values),
+    };
+
+    if !propagated {
+      missing.push(format!(
+        "{name} {record_type} {}",
+        values.iter().cloned().collect::<Vec<_>>().join(" ")
+      ));
+    }
+  }
+
+  Ok(missing)
+}
+
+async fn query(
+  nameserver: SocketAddr,
+  name: &Name,
+  record_type: HickoryRecordType,
+) -> error_stack::Result<Vec<RData>, Errors> {
+  let query_error = || Errors::QueryNameserver(nameserver.to_string());
+
+  let stream = UdpClientStream::<UdpSocket>::with_timeout(nameserver, QUERY_TIMEOUT);
+  let (mut client, background) = AsyncClient::connect(stream)
+    .await
+    .into_report()
+    .change_context_lazy(query_error)?;
+  tokio::spawn(background);
+
+  let response = client
+    .query(name.clone(), DNSClass::IN, record_type)
+    .await
+    .into_report()
+    .change_context_lazy(query_error)?;
+
+  Ok(
+    response
+      .answers()
+      .iter()
+      .filter(|record| record.record_type() == record_type)
+      .filter_map(|record| record.data().cloned())
+      .collect(),
+  )
+}
+
+fn value(data: &RData) -> Option<String> {
+  match data {
+    RData::A(a) => Some(a.0.to_string()),
+    RData::AAAA(aaaa) => Some(aaaa.0.to_string()),
+    RData::TXT(txt) => Some(
+      txt
+        .txt_data()
+        .iter()
+        .map(|data| String::from_utf8_lossy(data))
+        .collect(),
+    ),
+    _ => None,
+  }
+}
+
+fn name(name: &str) -> error_stack::Result<Name, Errors> {
+  let mut name = Name::from_str_relaxed(name)
+    .into_report()
+    .change_context_lazy(|| Errors::DNSZoneNotFound(name.to_string()))?;
+  name.set_fqdn(true);
+
+  Ok(name)
+}
+
+/// Accepts `host`, `host:port`, `ip` and `[ipv6]:port`, the port defaults to 53.
+async fn address(server: &str) -> error_stack::Result<SocketAddr, Errors> {
+  let server = server.trim_end_matches('.');
+  if let Ok(ip) = server.parse::<IpAddr>() {
+    return Ok(SocketAddr::new(ip, 53));
+  }
+  if let Ok(address) = server.parse::<SocketAddr>() {
+    return Ok(address);
+  }
+
+  let mut addresses = tokio::net::lookup_host((server, 53))
+    .await
+    .into_report()
+    .change_context_lazy(|| Errors::QueryNameserver(server.to_string()))?;
+
+  addresses
+    .next()
+    .ok_or_else(|| Report::new(Errors::QueryNameserver(server.to_string())))
+}
+
+#[cfg(test)]
+mod test {
+  use std::{str::FromStr, sync::Arc};
+
+  use hickory_client::{
+    op::{Message, MessageType},
+    rr::{
+      rdata::{A, TXT},
+      Record,
+    },
+  };
+
+  use super::*;
+
+  /// Answers every query with the records of `zone` which match its name and
+  /// type, like an authoritative nameserver would.
+  async fn stub_nameserver(zone: Vec<Record>) -> SocketAddr {
+    let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let address = socket.local_addr().unwrap();
+    let zone = Arc::new(zone);
+
+    tokio::spawn(async move {
+      let mut buffer = [0; 4096];
+      while let Ok((length, peer)) = socket.recv_from(&mut buffer).await {
+        let Ok(request) = Message::from_vec(&buffer[..length]) else {
+          continue;
+        };
+
+        let mut response = Message::new();
+        response
+          .set_id(request.id())
+          .set_message_type(MessageType::Response)
+          .set_op_code(request.op_code())
+          .set_authoritative(true)
+          .add_queries(request.queries().to_vec());
+
+        for query in request.queries() {
+          response.add_answers(
+            zone
+              .iter()
+              .filter(|record| {
+                record.name() == query.name() && record.record_type() == query.query_type()
+              })
+              .cloned(),
+          );
+        }
+
+        socket
+          .send_to(&response.to_vec().unwrap(), peer)
+          .await
+          .unwrap();
+      }
+    });
+
+    address
+  }
+
+  fn verifier(nameservers: Vec<SocketAddr>) -> Verifier {
+    Verifier {
+      resolver: "127.0.0.1".into(),
+      nameservers: nameservers.iter().map(ToString::to_string).collect(),
+      timeout: Duration::from_millis(300),
+      interval: Duration::from_millis(100),
+    }
+  }
+
+  #[tokio::test]
+  async fn wait_for_propagation() {
+    let www = Name::from_str("www.example.com.").unwrap();
+    let challenge = Name::from_str("_acme-challenge.example.com.").unwrap();
+
+    let nameserver = stub_nameserver(vec![
+      Record::from_rdata(www.clone(), 60, RData::A(A::new(192, 0, 2, 2))),
+      Record::from_rdata(
+        challenge.clone(),
+        60,
+        RData::TXT(TXT::new(vec!["other".into()])),
+      ),
+      Record::from_rdata(challenge, 60, RData::TXT(TXT::new(vec!["token".into()]))),
+    ])
+    .await;
+
+    let statuses = verifier(vec![nameserver])
+      .wait(
+        "example.com",
+        &[
+          DnsRecord::new("www", "192.0.2.2".parse().unwrap()),
+          DnsRecord::other("_acme-challenge", RecordType::Other("TXT".into()), "token"),
+        ],
+      )
+      .await
+      .unwrap();
+
+    assert_eq!(1, statuses.len());
+    assert!(statuses[0].propagated());
+  }
+
+  #[tokio::test]
+  async fn report_stale_nameservers() {
+    let www = Name::from_str("www.example.com.").unwrap();
+
+    let current = stub_nameserver(vec![Record::from_rdata(
+      www.clone(),
+      60,
+      RData::A(A::new(192, 0, 2, 2)),
+    )])
+    .await;
+    let stale = stub_nameserver(vec![
+      Record::from_rdata(www.clone(), 60, RData::A(A::new(192, 0, 2, 1))),
+      Record::from_rdata(www, 60, RData::A(A::new(192, 0, 2, 2))),
+    ])
+    .await;
+
+    let verifier = verifier(vec![current, stale]);
+    let records = [DnsRecord::new("www", "192.0.2.2".parse().unwrap())];
+
+    let report = verifier.wait("example.com", &records).await.unwrap_err();
+    assert!(matches!(report.current_context(), Errors::Propagation(_)));
+
+    let expected = expected("example.com", &records).unwrap();
+    assert!(missing(current, &expected).await.unwrap().is_empty());
+    assert_eq!(
+      vec!["www.example.com. A 192.0.2.2".to_string()],
+      missing(stale, &expected).await.unwrap()
+    );
+  }
+}
@@ -0,0 +1,453 @@
+use std::{
+  collections::HashMap,
+  str::FromStr,
+  sync::{Mutex, OnceLock},
+};
+
+use async_trait::async_trait;
+use error_stack::Report;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, error, warn};
+
+use crate::{
+  api::{
+    cloudflare, hetzner,
+    netcup::{
+      self,
+      models::{info_dns_zone, ApiSessionId, DnsRecord},
+      session::{CachedClient, SessionCache},
+    },
+    rfc2136,
+  },
+  cli::{Cli, DNSEntry, SyncTarget},
+  errors::Errors,
+  hostname::candidate_zones,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProviderKind {
+  Netcup,
+  Rfc2136,
+  Hetzner,
+  Cloudflare,
+}
+
+impl FromStr for ProviderKind {
+  type Err = Errors;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.trim().to_lowercase().as_str() {
+      "netcup" => Ok(Self::Netcup),
+      "rfc2136" => Ok(Self::Rfc2136),
+      "hetzner" => Ok(Self::Hetzner),
+      "cloudflare" => Ok(Self::Cloudflare),
+      _ => Err(Errors::ParseProvider(s.to_string())),
+    }
+  }
+}
+
+/// The TTL, SOA timers and DNSSEC status of a zone. Every provider maps its
+/// zone settings to the ones of Netcup.
+pub type ZoneSettings = info_dns_zone::ResponseData;
+
+/// What a provider answered to applied changes.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Applied {
+  request_id: Option<String>,
+  records: Vec<DnsRecord>,
+}
+
+impl Applied {
+  pub fn new(request_id: Option<String>, records: Vec<DnsRecord>) -> Self {
+    Self {
+      request_id,
+      records,
+    }
+  }
+
+  /// The id of the request at the provider, the `serverrequestid` of Netcup.
+  pub fn request_id(&self) -> Option<&str> {
+    self.request_id.as_deref()
+  }
+
+  /// The records of the zone after the changes, empty if the provider does
+  /// not return them.
+  pub fn records(&self) -> &[DnsRecord] {
+    &self.records
+  }
+}
+
+/// Attached to the error of [`DnsProvider::apply`] by the providers which
+/// apply the changes one by one: how many of the changes, in their order, were
+/// applied before the failure.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PartiallyApplied {
+  count: usize,
+  applied: Applied,
+}
+
+impl PartiallyApplied {
+  pub fn new(count: usize, applied: Applied) -> Self {
+    Self { count, applied }
+  }
+
+  pub fn count(&self) -> usize {
+    self.count
+  }
+
+  pub fn applied(&self) -> &Applied {
+    &self.applied
+  }
+}
+
+/// A DNS hosting backend the updater can reconcile records with.
+///
+/// Records are exchanged as Netcup [`DnsRecord`]s: records without an id are
+/// created, records with an id replace the record with that id and records
+/// marked with `deleterecord` are removed.
+#[async_trait]
+pub trait DnsProvider: Send + Sync {
+  /// A short name of the provider used in logs.
+  fn name(&self) -> &'static str;
+
+  async fn records(&self, zone: &str) -> error_stack::Result<Vec<DnsRecord>, Errors>;
+
+  async fn apply(
+    &self,
+    zone: &str,
+    changes: Vec<DnsRecord>,
+  ) -> error_stack::Result<Applied, Errors>;
+
+  async fn zone_settings(&self, zone: &str) -> error_stack::Result<ZoneSettings, Errors>;
+
+  async fn update_zone_settings(
+    &self,
+    zone: &str,
+    settings: ZoneSettings,
+  ) -> error_stack::Result<(), Errors>;
+
+  /// Ends the session with the provider, if there is one.
+  async fn close(self: Box<Self>) -> error_stack::Result<(), Errors> {
+    Ok(())
+  }
+}
+
+async fn connect(
+  cli: &Cli,
+  kind: ProviderKind,
+) -> error_stack::Result<Box<dyn DnsProvider>, Errors> {
+  Ok(match kind {
+    ProviderKind::Netcup => match SessionCache::from_cli(cli) {
+      Some(cache) => Box::new(CachedClient::connect(cli, cache).await?),
+      None => Box::new(netcup::Client::new(cli)?.login().await?),
+    },
+    ProviderKind::Rfc2136 => Box::new(rfc2136::Client::new(cli)?),
+    ProviderKind::Hetzner => Box::new(hetzner::Client::new(cli)?),
+    ProviderKind::Cloudflare => Box::new(cloudflare::Client::new(cli)?),
+  })
+}
+
+/// The zones a provider was asked for, kept for the life of the process so
+/// the daemon does not spend its request budget on them every run.
+fn found_zones() -> &'static Mutex<HashMap<(ProviderKind, String), String>> {
+  static FOUND: OnceLock<Mutex<HashMap<(ProviderKind, String), String>>> = OnceLock::new();
+  FOUND.get_or_init(Mutex::default)
+}
+
+/// The providers of all configured domains, every provider is only connected
+/// once even if it hosts several domains.
+pub struct Providers {
+  default: ProviderKind,
+  providers: HashMap<ProviderKind, Box<dyn DnsProvider>>,
+}
+
+impl Providers {
+  /// Connects the providers of all domains and, if zones are mirrored, Netcup
+  /// and the mirror providers.
+  pub async fn connect(cli: &Cli) -> error_stack::Result<Self, Errors> {
+    let domains = cli
+      .domains()
+      .iter()
+      .map(|domain| domain.provider().unwrap_or(cli.provider()));
+    let mirrors = cli
+      .mirrors()
+      .iter()
+      .filter_map(SyncTarget::provider)
+      .chain(Some(ProviderKind::Netcup))
+      .filter(|_| !cli.mirrors().is_empty());
+
+    Self::connect_kinds(cli, domains.chain(mirrors)).await
+  }
+
+  /// Netcup through the session of `client`, for the commands which run in
+  /// a session of their own. The session is not closed with the providers.
+  pub fn from_client(cli: &Cli, client: &netcup::Client<ApiSessionId>) -> Self {
+    let netcup: Box<dyn DnsProvider> = Box::new(client.clone());
+    Self {
+      default: cli.provider(),
+      providers: HashMap::from([(ProviderKind::Netcup, netcup)]),
+    }
+  }
+
+  pub async fn connect_kinds(
+    cli: &Cli,
+    kinds: impl IntoIterator<Item = ProviderKind>,
+  ) -> error_stack::Result<Self, Errors> {
+    let mut connected = Self {
+      default: cli.provider(),
+      providers: HashMap::new(),
+    };
+
+    for kind in kinds {
+      if connected.providers.contains_key(&kind) {
+        continue;
+      }
+
+      match connect(cli, kind).await {
+        Ok(provider) => {
+          connected.providers.insert(kind, provider);
+        }
+        Err(e) => {
+          // The sessions of the providers connected so far are not leaked.
+          let _ = connected.close().await;
+          return Err(e);
+        }
+      }
+    }
+
+    Ok(connected)
+  }
+
+  /// The provider hosting the zone of `domain`.
+  pub fn get(&self, domain: &DNSEntry) -> error_stack::Result<&dyn DnsProvider, Errors> {
+    self.kind(self.kind_of(domain))
+  }
+
+  pub fn kind_of(&self, domain: &DNSEntry) -> ProviderKind {
+    domain.provider().unwrap_or(self.default)
+  }
+
+  /// The zones of `domains` with the FQDNs of hosts moved into their zone and
+  /// the entries of the same zone merged.
+  pub async fn zones(&self, domains: &[DNSEntry]) -> Vec<DNSEntry> {
+    let mut zones: Vec<DNSEntry> = vec![];
+
+    for domain in domains {
+      // An entry without sub domains is either a zone or the FQDN of a host.
+      let zone = if domain.sub_domains().is_empty() {
+        let kind = self.kind_of(domain);
+        self.zone_of(kind, domain.domain(), domains).await
+      } else {
+        None
+      };
+      let entry = match zone {
+        Some(zone) if zone != domain.domain() => domain.in_zone(&zone),
+        _ => domain.clone(),
+      };
+
+      let kind = self.kind_of(&entry);
+      match zones
+        .iter_mut()
+        .find(|zone| zone.domain() == entry.domain() && self.kind_of(zone) == kind)
+      {
+        Some(zone) => zone.merge(entry),
+        None => zones.push(entry),
+      }
+    }
+
+    zones
+  }
+
+  /// The zone `name` belongs to, which is `name` itself if it is a zone. A
+  /// configured zone is preferred, otherwise the provider is asked for `name`
+  /// and then every parent, the most specific first, so zones below
+  /// multi-label TLDs like `co.uk` are found as well.
+  pub async fn zone_of(
+    &self,
+    kind: ProviderKind,
+    name: &str,
+    domains: &[DNSEntry],
+  ) -> Option<String> {
+    let parents = candidate_zones(name);
+    if parents.is_empty() {
+      return Some(name.to_string());
+    }
+    let candidates = Some(name).into_iter().chain(parents).collect::<Vec<_>>();
+
+    let configured = candidates.iter().find(|zone| {
+      domains.iter().any(|domain| {
+        domain.domain() == **zone
+          && !domain.sub_domains().is_empty()
+          && self.kind_of(domain) == kind
+      })
+    });
+    if let Some(zone) = configured {
+      return Some(zone.to_string());
+    }
+
+    let key = (kind, name.to_string());
+    if let Some(zone) = found_zones().lock().ok()?.get(&key) {
+      return Some(zone.clone());
+    }
+
+    let provider = self.kind(kind).ok()?;
+    for zone in candidates {
+      if provider.zone_settings(zone).await.is_ok() {
+        debug!(zone, "Found the zone of {name}");
+        if let Ok(mut found) = found_zones().lock() {
+          found.insert(key, zone.to_string());
+        }
+        return Some(zone.to_string());
+      }
+    }
+
+    warn!("Could not find the zone of {name}");
+    None
+  }
+
+  pub fn kind(&self, kind: ProviderKind) -> error_stack::Result<&dyn DnsProvider, Errors> {
+    self
+      .providers
+      .get(&kind)
+      .map(AsRef::as_ref)
+      .ok_or_else(|| Report::new(Errors::ProviderConfig("requested", format!("{kind:?}"))))
+  }
+
+  /// Closes every provider and returns the first error.
+  pub async fn close(self) -> error_stack::Result<(), Errors> {
+    let mut result = Ok(());
+
+    for (_, provider) in self.providers {
+      if let Err(e) = provider.close().await {
+        error!("{e:?}");
+        if result.is_ok() {
+          result = Err(e);
+        }
+      }
+    }
+
+    result
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use serde_json::{json, Value};
+  use wiremock::MockServer;
+
+  use super::*;
+  use crate::api::netcup::mock::{self, API_SESSION_ID};
+
+  async fn logouts(server: &MockServer) -> Vec<Value> {
+    server
+      .received_requests()
+      .await
+      .unwrap()
+      .iter()
+      .map(|request| request.body_json::<Value>().unwrap())
+      .filter(|body| body["action"] == "logout")
+      .collect()
+  }
+
+  #[tokio::test]
+  async fn logout_with_the_session_id() {
+    let netcup = mock::server().await;
+    let cli = mock::cli(&netcup, &[]);
+
+    let providers = Providers::connect_kinds(&cli, [ProviderKind::Netcup])
+      .await
+      .unwrap();
+    providers.close().await.unwrap();
+
+    let logouts = logouts(&netcup).await;
+    assert_eq!(1, logouts.len());
+    assert_eq!(API_SESSION_ID, logouts[0]["param"]["apisessionid"]);
+    assert!(logouts[0]["param"].get("apipassword").is_none());
+  }
+
+  #[tokio::test]
+  async fn logout_when_connecting_fails() {
+    let netcup = mock::server().await;
+    let cli = mock::cli(&netcup, &[]);
+
+    let error = Providers::connect_kinds(&cli, [ProviderKind::Netcup, ProviderKind::Hetzner])
+      .await
+      .err()
+      .unwrap();
+    assert!(matches!(
+      error.current_context(),
+      Errors::ProviderConfig("hetzner", _)
+    ));
+
+    assert_eq!(1, logouts(&netcup).await.len());
+  }
+
+  #[tokio::test]
+  async fn find_the_zones_of_hosts() {
+    let netcup = mock::server().await;
+    let cli = mock::cli(
+      &netcup,
+      &["home.example.co.uk: www; nas.home.example.co.uk; NAS.example.co.uk.; example.org"],
+    );
+    mock::action("infoDnsZone", json!({ "domainname": "example.co.uk" }))
+      .respond_with(mock::response(
+        "infoDnsZone",
+        json!({
+          "name": "example.co.uk", "ttl": "300", "serial": "1", "refresh": "28800",
+          "retry": "7200", "expire": "1209600", "dnssecstatus": false
+        }),
+      ))
+      .mount(&netcup)
+      .await;
+
+    let providers = Providers::connect(&cli).await.unwrap();
+    let zones = providers
+      .zones(cli.domains())
+      .await
+      .iter()
+      .map(|zone| {
+        let hosts = zone
+          .sub_domains()
+          .iter()
+          .map(|host| host.to_string())
+          .collect::<Vec<_>>();
+        (zone.domain().to_string(), hosts)
+      })
+      .collect::<Vec<_>>();
+    // The zones found are not looked up again by the next run.
+    let probes = || async {
+      netcup
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .filter(|request| request.body_json::<Value>().unwrap()["action"] == "infoDnsZone")
+        .count()
+    };
+    let before = probes().await;
+    providers.zones(cli.domains()).await;
+    assert_eq!(before, probes().await);
+
+    // A host entry is no zone, e.g. for the challenge of an ACME client.
+    assert_eq!(
+      Some("example.co.uk".to_string()),
+      providers
+        .zone_of(ProviderKind::Netcup, "nas.example.co.uk", cli.domains())
+        .await
+    );
+    providers.close().await.unwrap();
+
+    assert_eq!(
+      vec![
+        (
+          "home.example.co.uk".to_string(),
+          vec!["www".to_string(), "nas".to_string()]
+        ),
+        ("example.co.uk".to_string(), vec!["nas".to_string()]),
+        ("example.org".to_string(), vec![]),
+      ],
+      zones
+    );
+  }
+}
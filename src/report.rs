@@ -0,0 +1,390 @@
+//! What a run did to every zone and host and the exit code it ends with.
+
+use std::{fmt::Write, str::FromStr};
+
+use error_stack::Report;
+use reqwest::StatusCode;
+use serde::Serialize;
+
+use crate::{
+  api::netcup::{models::DnsRecord, ServerMessage},
+  errors::Errors,
+  hostname::to_unicode,
+};
+
+/// Every zone and host was reconciled.
+pub const EXIT_SUCCESS: u8 = 0;
+/// Nothing was reconciled, the exit code of every other error as well.
+pub const EXIT_FAILURE: u8 = 1;
+/// Some zones or hosts failed while others were reconciled.
+pub const EXIT_PARTIAL_FAILURE: u8 = 2;
+/// The credentials were missing or rejected.
+pub const EXIT_AUTH_FAILURE: u8 = 3;
+/// A provider rejected requests because there were too many of them.
+pub const EXIT_RATE_LIMITED: u8 = 4;
+
+/// Ordered by how much happened, so the outcome of a host with several
+/// changes is the largest one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+  Unchanged,
+  Created,
+  Updated,
+  Deleted,
+  Failed,
+}
+
+impl Outcome {
+  /// The outcome of applying `record`, which replaces `old`.
+  pub fn of(record: &DnsRecord, old: Option<&DnsRecord>) -> Self {
+    match old {
+      _ if record.delete_record() => Self::Deleted,
+      Some(_) => Self::Updated,
+      None => Self::Created,
+    }
+  }
+
+  fn as_str(&self) -> &'static str {
+    match self {
+      Self::Unchanged => "unchanged",
+      Self::Created => "created",
+      Self::Updated => "updated",
+      Self::Deleted => "deleted",
+      Self::Failed => "failed",
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Cause {
+  Auth,
+  RateLimit,
+  Other,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Entry {
+  /// `None` if the whole run failed, e.g. because the login failed.
+  zone: Option<String>,
+  /// `None` if the whole zone failed.
+  host: Option<String>,
+  outcome: Outcome,
+  error: Option<String>,
+  #[serde(skip)]
+  cause: Option<Cause>,
+}
+
+/// Whether the changes of a zone reached its nameservers in time.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Propagation {
+  zone: String,
+  propagated: bool,
+  error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+  Table,
+  Json,
+  None,
+}
+
+impl FromStr for ReportFormat {
+  type Err = Errors;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s.trim().to_lowercase().as_str() {
+      "table" => Ok(Self::Table),
+      "json" => Ok(Self::Json),
+      "none" => Ok(Self::None),
+      _ => Err(Errors::ParseReportFormat(s.to_string())),
+    }
+  }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RunReport {
+  entries: Vec<Entry>,
+  propagation: Vec<Propagation>,
+}
+
+impl RunReport {
+  pub fn record(&mut self, zone: &str, host: &str, outcome: Outcome) {
+    self.entries.push(Entry {
+      zone: Some(zone.to_string()),
+      host: Some(host.to_string()),
+      outcome,
+      error: None,
+      cause: None,
+    });
+  }
+
+  /// Records that `host` of `zone`, the whole `zone` or the whole run failed.
+  pub fn failed(&mut self, zone: Option<&str>, host: Option<&str>, error: &Report<Errors>) {
+    let mut message = error.current_context().to_string();
+    for server_message in error
+      .frames()
+      .filter_map(|frame| frame.downcast_ref::<ServerMessage>())
+    {
+      let _ = write!(message, " ({server_message})");
+    }
+
+    // Hetzner and Cloudflare reject a wrong token with HTTP 401 or 403.
+    let auth = error.frames().any(|frame| {
+      frame.downcast_ref::<Errors>().is_some_and(|error| {
+        matches!(
+          error,
+          Errors::Login | Errors::RetrieveAPISesionId | Errors::MissingCredentials
+        )
+      }) || frame
+        .downcast_ref::<StatusCode>()
+        .is_some_and(|status| matches!(*status, StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN))
+    });
+    // Netcup answers a rate limit with the status code of every validation
+    // error, only the message or the HTTP status tells it apart.
+    let rate_limited = error.frames().any(|frame| {
+      frame
+        .downcast_ref::<ServerMessage>()
+        .is_some_and(ServerMessage::is_rate_limit)
+        || frame.downcast_ref::<StatusCode>() == Some(&StatusCode::TOO_MANY_REQUESTS)
+    });
+    let cause = if auth {
+      Cause::Auth
+    } else if rate_limited {
+      Cause::RateLimit
+    } else {
+      Cause::Other
+    };
+
+    self.entries.push(Entry {
+      zone: zone.map(Into::into),
+      host: host.map(Into::into),
+      outcome: Outcome::Failed,
+      error: Some(message),
+      cause: Some(cause),
+    });
+  }
+
+  /// Records whether the changes of `zone` propagated, `error` tells why not.
+  pub fn propagated(&mut self, zone: &str, error: Option<&Report<Errors>>) {
+    let error = error.map(|error| {
+      let mut message = error.current_context().to_string();
+      for statuses in error
+        .frames()
+        .filter_map(|frame| frame.downcast_ref::<String>())
+      {
+        let _ = write!(message, " ({statuses})");
+      }
+      message
+    });
+
+    self.propagation.push(Propagation {
+      zone: zone.to_string(),
+      propagated: error.is_none(),
+      error,
+    });
+  }
+
+  fn causes(&self) -> impl Iterator<Item = Cause> + '_ {
+    self.entries.iter().filter_map(|entry| entry.cause)
+  }
+
+  pub fn exit_code(&self) -> u8 {
+    let failures = self.causes().count();
+
+    if self.causes().any(|cause| cause == Cause::Auth) {
+      EXIT_AUTH_FAILURE
+    } else if self.causes().any(|cause| cause == Cause::RateLimit) {
+      EXIT_RATE_LIMITED
+    } else if failures == 0 && self.propagation.iter().all(|zone| zone.propagated) {
+      EXIT_SUCCESS
+    } else if failures > 0 && failures == self.entries.len() {
+      EXIT_FAILURE
+    } else {
+      EXIT_PARTIAL_FAILURE
+    }
+  }
+
+  pub fn render(&self, format: ReportFormat) -> String {
+    match format {
+      ReportFormat::Table => self.table(),
+      ReportFormat::Json => {
+        let report = serde_json::json!({
+          "exit_code": self.exit_code(),
+          "entries": self.entries,
+          "propagation": self.propagation,
+        });
+        format!("{report:#}\n")
+      }
+      ReportFormat::None => String::new(),
+    }
+  }
+
+  fn table(&self) -> String {
+    let rows = self
+      .entries
+      .iter()
+      .map(|entry| {
+        [
+          entry.zone.as_deref().map_or("*".into(), to_unicode),
+          entry.host.as_deref().map_or("*".into(), to_unicode),
+          entry.outcome.as_str().into(),
+          entry.error.clone().unwrap_or_default(),
+        ]
+      })
+      .collect::<Vec<_>>();
+    let mut table = table(["ZONE", "HOST", "OUTCOME", "ERROR"], &rows);
+
+    if !self.propagation.is_empty() {
+      let rows = self
+        .propagation
+        .iter()
+        .map(|zone| {
+          [
+            to_unicode(&zone.zone),
+            if zone.propagated { "yes" } else { "no" }.into(),
+            zone.error.clone().unwrap_or_default(),
+          ]
+        })
+        .collect::<Vec<_>>();
+      table.push('\n');
+      table.push_str(&self::table(["ZONE", "PROPAGATED", "ERROR"], &rows));
+    }
+    table
+  }
+}
+
+/// Aligns the columns of `rows` below `header`.
+fn table<const N: usize>(header: [&str; N], rows: &[[String; N]]) -> String {
+  let mut widths = header.map(str::len);
+  for row in rows {
+    for (width, cell) in widths.iter_mut().zip(row) {
+      *width = (*width).max(cell.chars().count());
+    }
+  }
+
+  let mut table = String::new();
+  for row in Some(header.map(String::from)).iter().chain(rows) {
+    let line = row
+      .iter()
+      .zip(widths)
+      .map(|(cell, width)| format!("{cell:width$}"))
+      .collect::<Vec<_>>()
+      .join("  ");
+    let _ = writeln!(table, "{}", line.trim_end());
+  }
+  table
+}
+
+impl Extend<RunReport> for RunReport {
+  fn extend<T: IntoIterator<Item = RunReport>>(&mut self, reports: T) {
+    for report in reports {
+      self.entries.extend(report.entries);
+      self.propagation.extend(report.propagation);
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn exit_codes_and_table() {
+    let mut report = RunReport::default();
+    assert_eq!(EXIT_SUCCESS, report.exit_code());
+
+    report.record("example.com", "www", Outcome::Created);
+    report.record("example.com", "@", Outcome::Unchanged);
+    assert_eq!(EXIT_SUCCESS, report.exit_code());
+
+    report.failed(
+      Some("example.org"),
+      None,
+      &Report::new(Errors::DNSZoneNotFound("example.org".into())),
+    );
+    assert_eq!(EXIT_PARTIAL_FAILURE, report.exit_code());
+    assert_eq!(
+      "ZONE         HOST  OUTCOME    ERROR\n\
+       example.com  www   created\n\
+       example.com  @     unchanged\n\
+       example.org  *     failed     Could not find DNS Zone example.org\n",
+      report.table()
+    );
+
+    report.record("xn--mller-bau-q9a.de", "xn--bro-hoa", Outcome::Updated);
+    assert!(report.table().contains("müller-bau.de  büro  updated"));
+
+    report.failed(
+      Some("example.net"),
+      None,
+      &Report::new(Errors::ValidationError)
+        .attach_printable(ServerMessage::new("Validation Error.", None))
+        .change_context(Errors::UpdateDNSRecords("example.net".into())),
+    );
+    assert_eq!(EXIT_PARTIAL_FAILURE, report.exit_code());
+    assert_eq!(
+      Some("Could not update dns records example.net (Netcup: Validation Error.)"),
+      report.entries[4].error.as_deref()
+    );
+
+    report.failed(
+      Some("example.net"),
+      None,
+      &Report::new(Errors::ValidationError).attach_printable(ServerMessage::new(
+        "Validation Error.",
+        Some("More than 180 requests per minute. Please wait and retry later.".into()),
+      )),
+    );
+    assert_eq!(EXIT_RATE_LIMITED, report.exit_code());
+
+    let mut throttled = RunReport::default();
+    throttled.failed(
+      Some("example.com"),
+      None,
+      &Report::new(Errors::ProviderRequest("Hetzner")).attach(StatusCode::TOO_MANY_REQUESTS),
+    );
+    assert_eq!(EXIT_RATE_LIMITED, throttled.exit_code());
+
+    for status in [StatusCode::UNAUTHORIZED, StatusCode::FORBIDDEN] {
+      let mut rejected = RunReport::default();
+      rejected.failed(
+        Some("example.com"),
+        None,
+        &Report::new(Errors::ProviderRequest("Cloudflare")).attach(status),
+      );
+      assert_eq!(EXIT_AUTH_FAILURE, rejected.exit_code());
+    }
+
+    report.failed(None, None, &Report::new(Errors::Login));
+    assert_eq!(EXIT_AUTH_FAILURE, report.exit_code());
+
+    let mut failed = RunReport::default();
+    failed.failed(None, None, &Report::new(Errors::SendRequest));
+    assert_eq!(EXIT_FAILURE, failed.exit_code());
+  }
+
+  #[test]
+  fn propagation() {
+    let mut report = RunReport::default();
+    report.record("example.com", "www", Outcome::Updated);
+    report.propagated("example.com", None);
+    assert_eq!(EXIT_SUCCESS, report.exit_code());
+
+    report.record("example.org", "www", Outcome::Updated);
+    report.propagated(
+      "example.org",
+      Some(
+        &Report::new(Errors::Propagation("example.org".into()))
+          .attach_printable("ns1.example.net: missing www A 192.0.2.2".to_string()),
+      ),
+    );
+    assert_eq!(EXIT_PARTIAL_FAILURE, report.exit_code());
+    assert!(report.table().ends_with(
+      "\nZONE         PROPAGATED  ERROR\n\
+       example.com  yes\n\
+       example.org  no          The changes of zone example.org did not propagate in time (ns1.example.net: missing www A 192.0.2.2)\n"
+    ), "{}", report.table());
+  }
+}
@@ -1,7 +1,7 @@
 use std::{fmt, marker::PhantomData};
 
 use serde::{
-  de::{self, IntoDeserializer, MapAccess, Visitor},
+  de::{self, IntoDeserializer, MapAccess, SeqAccess, Visitor},
   Deserialize, Deserializer,
 };
 
@@ -32,7 +32,7 @@ where
     type Value = Option<T>;
 
     fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-      formatter.write_str("string or map")
+      formatter.write_str("string, map or sequence")
     }
 
     fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
@@ -51,6 +51,13 @@ where
     {
       Deserialize::deserialize(de::value::MapAccessDeserializer::new(map)).map(Some)
     }
+
+    fn visit_seq<S>(self, seq: S) -> Result<Self::Value, S::Error>
+    where
+      S: SeqAccess<'de>,
+    {
+      Deserialize::deserialize(de::value::SeqAccessDeserializer::new(seq)).map(Some)
+    }
   }
 
   deserializer.deserialize_any(StringOrStruct(PhantomData))
@@ -70,7 +77,7 @@ where
     type Value = Option<T>;
 
     fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-      formatter.write_str("a nul, a string or map")
+      formatter.write_str("a nul, a string, map or sequence")
     }
 
     fn visit_none<E>(self) -> Result<Self::Value, E>
@@ -0,0 +1,82 @@
+//! The HTTP servers of the updater.
+
+use std::{convert::Infallible, future::Future, net::IpAddr, net::SocketAddr, sync::Arc};
+
+use async_trait::async_trait;
+use error_stack::{IntoReport, ResultExt};
+use hyper::{
+  header::CONTENT_TYPE,
+  server::conn::AddrStream,
+  service::{make_service_fn, service_fn},
+  Body, Request, Response, Server, StatusCode,
+};
+use serde::Serialize;
+use tracing::info;
+
+use crate::errors::Errors;
+
+pub mod acme_dns;
+pub mod metrics;
+
+#[async_trait]
+pub trait Handler: Send + Sync + 'static {
+  /// Answers `request` of the client at `remote`.
+  async fn handle(&self, request: Request<Body>, remote: IpAddr) -> Response<Body>;
+}
+
+/// Serves `handler` on `listen` until the server fails.
+pub async fn serve(listen: SocketAddr, handler: impl Handler) -> error_stack::Result<(), Errors> {
+  let (address, server) = bind(listen, handler)?;
+  info!("Listening on http://{address}");
+
+  server.await
+}
+
+/// Binds the server without running it, `listen` may use port 0.
+pub fn bind(
+  listen: SocketAddr,
+  handler: impl Handler,
+) -> error_stack::Result<
+  (
+    SocketAddr,
+    impl Future<Output = error_stack::Result<(), Errors>>,
+  ),
+  Errors,
+> {
+  let handler = Arc::new(handler);
+
+  let make_service = make_service_fn(move |connection: &AddrStream| {
+    let handler = handler.clone();
+    let remote = connection.remote_addr().ip();
+
+    async move {
+      Ok::<_, Infallible>(service_fn(move |request| {
+        let handler = handler.clone();
+        async move { Ok::<_, Infallible>(handler.handle(request, remote).await) }
+      }))
+    }
+  });
+
+  let server = Server::try_bind(&listen)
+    .into_report()
+    .change_context(Errors::Server(listen.to_string()))?
+    .serve(make_service);
+  let address = server.local_addr();
+
+  Ok((address, async move {
+    server
+      .await
+      .into_report()
+      .change_context(Errors::Server(address.to_string()))
+  }))
+}
+
+pub(crate) fn json(status: StatusCode, body: &impl Serialize) -> Response<Body> {
+  let mut response = Response::new(Body::from(serde_json::to_vec(body).unwrap_or_default()));
+  *response.status_mut() = status;
+  response
+    .headers_mut()
+    .insert(CONTENT_TYPE, "application/json".parse().unwrap());
+
+  response
+}
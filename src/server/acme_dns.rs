@@ -0,0 +1,411 @@
+//! An [acme-dns](https://github.com/joohoi/acme-dns) compatible API, so ACME
+//! clients like Traefik, cert-manager or acme.sh can validate domains through
+//! a Netcup zone.
+//!
+//! Every registration gets its own credentials and subdomain and may only
+//! update the TXT record `_acme-challenge.<subdomain>` of the configured zone.
+//! The domain to validate delegates its challenge with a CNAME from
+//! `_acme-challenge.<domain>` to the `fulldomain` of the registration. New
+//! registrations are only accepted while they are enabled.
+
+use std::net::IpAddr;
+
+use async_trait::async_trait;
+use error_stack::Report;
+use hyper::{header::CONTENT_LENGTH, Body, Method, Request, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::sync::Mutex;
+use tracing::{error, info, warn};
+
+use http_body::{LengthLimitError, Limited};
+
+use crate::{
+  api::netcup::{
+    self,
+    models::{DnsRecord, NoApiSessionId, RecordType},
+  },
+  errors::Errors,
+};
+
+use self::registrations::{is_network, Registration, Registrations};
+
+use super::{json, Handler};
+
+pub mod registrations;
+
+const CHALLENGE_LABEL: &str = "_acme-challenge";
+/// The length of a base64url encoded SHA-256 digest.
+const TXT_LENGTH: usize = 43;
+
+#[derive(Debug, Default, Deserialize)]
+struct RegisterRequest {
+  #[serde(default, rename = "allowfrom")]
+  allow_from: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct RegisterResponse<'a> {
+  username: &'a str,
+  password: &'a str,
+  #[serde(rename = "fulldomain")]
+  full_domain: String,
+  subdomain: &'a str,
+  #[serde(rename = "allowfrom")]
+  allow_from: &'a Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UpdateRequest {
+  subdomain: String,
+  txt: String,
+}
+
+/// The largest request body, registrations and updates are far smaller.
+const MAX_BODY: usize = 8 * 1024;
+
+pub struct AcmeDns {
+  client: netcup::Client<NoApiSessionId>,
+  zone: String,
+  registration: bool,
+  /// Also serializes the updates of the zone.
+  registrations: Mutex<Registrations>,
+}
+
+impl AcmeDns {
+  pub fn new(
+    client: netcup::Client<NoApiSessionId>,
+    zone: impl Into<String>,
+    registration: bool,
+    registrations: Registrations,
+  ) -> Self {
+    Self {
+      client,
+      zone: zone.into().trim_end_matches('.').to_lowercase(),
+      registration,
+      registrations: Mutex::new(registrations),
+    }
+  }
+
+  async fn register(&self, body: &[u8]) -> Response<Body> {
+    if !self.registration {
+      return error(StatusCode::FORBIDDEN, "registration_disabled");
+    }
+
+    let request = if body.iter().all(u8::is_ascii_whitespace) {
+      RegisterRequest::default()
+    } else {
+      match serde_json::from_slice::<RegisterRequest>(body) {
+        Ok(request) => request,
+        Err(_) => return error(StatusCode::BAD_REQUEST, "malformed_json_payload"),
+      }
+    };
+
+    if !request.allow_from.iter().all(|network| is_network(network)) {
+      return error(StatusCode::BAD_REQUEST, "invalid_allowfrom_cidr");
+    }
+
+    let (registration, password) = Registration::new(request.allow_from);
+
+    let mut registrations = self.registrations.lock().await;
+    registrations.add(registration.clone());
+    if let Err(e) = registrations.save().await {
+      error!("{e:?}");
+      return error(StatusCode::INTERNAL_SERVER_ERROR, "db_error");
+    }
+
+    info!("Registered {}", registration.subdomain());
+
+    json(
+      StatusCode::CREATED,
+      &RegisterResponse {
+        username: registration.username(),
+        password: &password,
+        full_domain: self.full_domain(registration.subdomain()),
+        subdomain: registration.subdomain(),
+        allow_from: registration.allow_from(),
+      },
+    )
+  }
+
+  async fn update(&self, request: &Request<Body>, body: &[u8], remote: IpAddr) -> Response<Body> {
+    let header = |name: &str| {
+      request
+        .headers()
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+    };
+    let (username, password) = (header("X-Api-User"), header("X-Api-Key"));
+
+    let Ok(update) = serde_json::from_slice::<UpdateRequest>(body) else {
+      return error(StatusCode::BAD_REQUEST, "malformed_json_payload");
+    };
+
+    let mut registrations = self.registrations.lock().await;
+    let Some(registration) = registrations
+      .find_mut(username)
+      .filter(|registration| registration.verify(password) && registration.allows(remote))
+    else {
+      warn!("Rejected the update of {username} from {remote}");
+      return error(StatusCode::UNAUTHORIZED, "forbidden");
+    };
+
+    if registration.subdomain() != update.subdomain {
+      return error(StatusCode::UNAUTHORIZED, "forbidden");
+    }
+
+    if update.txt.len() != TXT_LENGTH
+      || !update
+        .txt
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+      return error(StatusCode::BAD_REQUEST, "bad_txt");
+    }
+
+    let txt = registration.with_txt(&update.txt);
+    let host_name = format!("{CHALLENGE_LABEL}.{}", registration.subdomain());
+
+    if let Err(e) = self.write_challenge(&host_name, &txt).await {
+      error!("{e:?}");
+      return error(StatusCode::INTERNAL_SERVER_ERROR, "netcup_error");
+    }
+
+    registration.txt_mut(txt);
+    if let Err(e) = registrations.save().await {
+      error!("{e:?}");
+    }
+
+    info!("Updated the challenge of {}", update.subdomain);
+
+    json(StatusCode::OK, &json!({ "txt": update.txt }))
+  }
+
+  /// Replaces the TXT values of `host_name` with `txt` in a session of its own,
+  /// as sessions expire long before the server stops.
+  async fn write_challenge(
+    &self,
+    host_name: &str,
+    txt: &[String],
+  ) -> error_stack::Result<(), Errors> {
+    let client = self.client.clone().login().await?;
+
+    let result = async {
+      let existing = client
+        .info_dns_records(self.zone.as_str())
+        .await?
+        .response_data()
+        .map(|data| data.dns_records().clone())
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|record| {
+          record.host_name().eq_ignore_ascii_case(host_name)
+            && matches!(record.record_type(), RecordType::Other(record_type) if record_type.eq_ignore_ascii_case("TXT"))
+        })
+        .collect::<Vec<_>>();
+
+      let mut changes = existing
+        .iter()
+        .filter(|record| !txt.contains(&record.destination().to_string()))
+        .cloned()
+        .map(|mut record| {
+          record.delete_record_mut(true);
+          record
+        })
+        .collect::<Vec<_>>();
+      changes.extend(
+        txt
+          .iter()
+          .filter(|value| {
+            !existing
+              .iter()
+              .any(|record| record.destination().to_string() == **value)
+          })
+          .map(|value| DnsRecord::other(host_name, RecordType::Other("TXT".into()), value)),
+      );
+
+      if changes.is_empty() {
+        return Ok(());
+      }
+
+      let update_dns_records_response = client
+        .update_dns_records(self.zone.as_str(), changes)
+        .await?;
+      if update_dns_records_response.status_code() != netcup::StatusCode::Success {
+        return Err(Report::new(Errors::UpdateDNSRecords(self.zone.clone())));
+      }
+
+      Ok(())
+    }
+    .await;
+
+    if let Err(e) = client.logout().await {
+      error!("{e:?}");
+    }
+
+    result
+  }
+
+  fn full_domain(&self, subdomain: &str) -> String {
+    format!("{CHALLENGE_LABEL}.{subdomain}.{}", self.zone)
+  }
+}
+
+#[async_trait]
+impl Handler for AcmeDns {
+  async fn handle(&self, request: Request<Body>, remote: IpAddr) -> Response<Body> {
+    let (parts, body) = request.into_parts();
+    let too_large = parts
+      .headers
+      .get(CONTENT_LENGTH)
+      .and_then(|length| length.to_str().ok()?.parse::<usize>().ok())
+      .is_some_and(|length| length > MAX_BODY);
+    if too_large {
+      return error(StatusCode::PAYLOAD_TOO_LARGE, "payload_too_large");
+    }
+
+    let body = match hyper::body::to_bytes(Limited::new(body, MAX_BODY)).await {
+      Ok(body) => body,
+      Err(e) if e.is::<LengthLimitError>() => {
+        return error(StatusCode::PAYLOAD_TOO_LARGE, "payload_too_large")
+      }
+      Err(_) => return error(StatusCode::BAD_REQUEST, "malformed_json_payload"),
+    };
+    let request = Request::from_parts(parts, Body::empty());
+
+    match (request.method(), request.uri().path()) {
+      (&Method::POST, "/register") => self.register(&body).await,
+      (&Method::POST, "/update") => self.update(&request, &body, remote).await,
+      (&Method::GET, "/health") => json(StatusCode::OK, &json!({})),
+      _ => error(StatusCode::NOT_FOUND, "not_found"),
+    }
+  }
+}
+
+fn error(status: StatusCode, error: &str) -> Response<Body> {
+  json(status, &json!({ "error": error }))
+}
+
+#[cfg(test)]
+mod test {
+  use serde_json::Value;
+  use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+  use super::*;
+  use crate::{
+    api::netcup::mock::{self, API_SESSION_ID},
+    server::bind,
+  };
+
+  const TXT: &str = "0123456789012345678901234567890123456789abc";
+
+  #[tokio::test]
+  async fn register_and_update() {
+    let netcup = mock::server().await;
+    let cli = mock::cli(&netcup, &[]);
+
+    let storage = std::env::temp_dir().join(format!("acme-dns-{}.json", std::process::id()));
+    let acme_dns = AcmeDns::new(
+      netcup::Client::new(&cli).unwrap(),
+      "Example.com.",
+      true,
+      Registrations::load(&storage).await.unwrap(),
+    );
+    let (address, server) = bind("127.0.0.1:0".parse().unwrap(), acme_dns).unwrap();
+    tokio::spawn(server);
+    let url = |path: &str| format!("http://{address}{path}");
+    let client = reqwest::Client::new();
+
+    let registration = client
+      .post(url("/register"))
+      .json(&json!({ "allowfrom": ["127.0.0.0/8"] }))
+      .send()
+      .await
+      .unwrap();
+    assert_eq!(201, registration.status().as_u16());
+    let registration = registration.json::<Value>().await.unwrap();
+    let subdomain = registration["subdomain"].as_str().unwrap();
+    let host_name = format!("_acme-challenge.{subdomain}");
+    assert_eq!(
+      format!("{host_name}.example.com"),
+      registration["fulldomain"].as_str().unwrap()
+    );
+
+    mock::action(
+      "infoDnsRecords",
+      json!({ "domainname": "example.com", "apisessionid": API_SESSION_ID }),
+    )
+    .respond_with(mock::response(
+      "infoDnsRecords",
+      json!({ "dnsrecords": [{
+        "id": "1", "hostname": host_name, "type": "TXT", "priority": "0",
+        "destination": "old", "deleterecord": false, "state": "yes"
+      }] }),
+    ))
+    .mount(&netcup)
+    .await;
+    mock::action(
+      "updateDnsRecords",
+      json!({ "dnsrecordset": { "dnsrecords": [
+        { "id": "1", "destination": "old", "deleterecord": true },
+        { "hostname": host_name, "type": "TXT", "destination": TXT }
+      ] } }),
+    )
+    .respond_with(mock::response(
+      "updateDnsRecords",
+      json!({ "dnsrecords": [] }),
+    ))
+    .expect(1)
+    .mount(&netcup)
+    .await;
+
+    let update = |user: &str, key: &str, subdomain: &str, txt: &str| {
+      client
+        .post(url("/update"))
+        .header("X-Api-User", user)
+        .header("X-Api-Key", key)
+        .json(&json!({ "subdomain": subdomain, "txt": txt }))
+        .send()
+    };
+    let user = registration["username"].as_str().unwrap();
+    let key = registration["password"].as_str().unwrap();
+
+    let response = update(user, key, subdomain, TXT).await.unwrap();
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!(
+      json!({ "txt": TXT }),
+      response.json::<Value>().await.unwrap()
+    );
+
+    let response = update(user, "wrong", subdomain, TXT).await.unwrap();
+    assert_eq!(401, response.status().as_u16());
+    let response = update(user, key, "other", TXT).await.unwrap();
+    assert_eq!(401, response.status().as_u16());
+    let response = update(user, key, subdomain, "short").await.unwrap();
+    assert_eq!(400, response.status().as_u16());
+    let response = update(user, key, subdomain, &"x".repeat(MAX_BODY))
+      .await
+      .unwrap();
+    assert_eq!(413, response.status().as_u16());
+
+    // A chunked body has no Content-Length to check up front.
+    let mut stream = tokio::net::TcpStream::connect(address).await.unwrap();
+    let chunk = format!("400\r\n{}\r\n", " ".repeat(1024));
+    let request = format!(
+      "POST /register HTTP/1.1\r\nHost: {address}\r\nTransfer-Encoding: chunked\r\n\r\n{}0\r\n\r\n",
+      chunk.repeat(MAX_BODY / 1024 + 1)
+    );
+    stream.write_all(request.as_bytes()).await.unwrap();
+    let mut response = [0; 12];
+    stream.read_exact(&mut response).await.unwrap();
+    assert_eq!(b"HTTP/1.1 413", &response);
+
+    let mut stored = Registrations::load(&storage).await.unwrap();
+    std::fs::remove_file(&storage).unwrap();
+    assert_eq!(
+      vec![TXT.to_string(), "next".to_string()],
+      stored.find_mut(user).unwrap().with_txt("next")
+    );
+  }
+}
@@ -0,0 +1,288 @@
+use std::{
+  net::IpAddr,
+  num::NonZeroU32,
+  path::{Path, PathBuf},
+};
+
+use base64::Engine;
+use error_stack::{IntoReport, ResultExt};
+use rand::{distributions::Alphanumeric, Rng};
+use ring::pbkdf2;
+use serde::{Deserialize, Serialize};
+
+use crate::errors::Errors;
+
+/// The passwords are 40 random characters, so a moderate iteration count is
+/// enough to not store them in plain text.
+const PBKDF2_ITERATIONS: u32 = 10_000;
+const PASSWORD_LENGTH: usize = 40;
+/// acme-dns keeps the two latest values so a certificate for a domain and its
+/// wildcard can be validated at the same time.
+const MAX_TXT_VALUES: usize = 2;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Registration {
+  username: String,
+  password_hash: String,
+  subdomain: String,
+  #[serde(default)]
+  allow_from: Vec<String>,
+  #[serde(default)]
+  txt: Vec<String>,
+}
+
+impl Registration {
+  /// A registration with random credentials and subdomain and its password.
+  pub fn new(allow_from: Vec<String>) -> (Self, String) {
+    let password = rand::thread_rng()
+      .sample_iter(&Alphanumeric)
+      .take(PASSWORD_LENGTH)
+      .map(char::from)
+      .collect::<String>();
+
+    let registration = Self {
+      username: uuid(),
+      password_hash: hash(&password),
+      subdomain: uuid(),
+      allow_from,
+      txt: vec![],
+    };
+
+    (registration, password)
+  }
+
+  pub fn username(&self) -> &str {
+    &self.username
+  }
+
+  pub fn subdomain(&self) -> &str {
+    &self.subdomain
+  }
+
+  pub fn allow_from(&self) -> &Vec<String> {
+    &self.allow_from
+  }
+
+  /// The TXT values after `value` was added.
+  pub fn with_txt(&self, value: &str) -> Vec<String> {
+    let mut txt = self
+      .txt
+      .iter()
+      .filter(|txt| *txt != value)
+      .cloned()
+      .collect::<Vec<_>>();
+    txt.push(value.to_string());
+
+    txt.split_off(txt.len().saturating_sub(MAX_TXT_VALUES))
+  }
+
+  pub fn txt_mut(&mut self, txt: Vec<String>) {
+    self.txt = txt
+  }
+
+  pub fn verify(&self, password: &str) -> bool {
+    let Some((salt, hash)) = self.password_hash.split_once('$') else {
+      return false;
+    };
+    let engine = base64::engine::general_purpose::STANDARD;
+    let (Ok(salt), Ok(hash)) = (engine.decode(salt), engine.decode(hash)) else {
+      return false;
+    };
+
+    pbkdf2::verify(
+      pbkdf2::PBKDF2_HMAC_SHA256,
+      iterations(),
+      &salt,
+      password.as_bytes(),
+      &hash,
+    )
+    .is_ok()
+  }
+
+  /// Whether updates from `ip` are allowed, every address is allowed if no
+  /// networks are configured.
+  pub fn allows(&self, ip: IpAddr) -> bool {
+    self.allow_from.is_empty()
+      || self
+        .allow_from
+        .iter()
+        .any(|network| contains(network, ip).unwrap_or(false))
+  }
+}
+
+/// The registrations, stored as JSON in a file.
+#[derive(Debug)]
+pub struct Registrations {
+  path: PathBuf,
+  registrations: Vec<Registration>,
+}
+
+impl Registrations {
+  /// Loads the registrations of `path`, which doesn't need to exist yet.
+  pub async fn load(path: impl AsRef<Path>) -> error_stack::Result<Self, Errors> {
+    let path = path.as_ref().to_path_buf();
+    let storage_error = || Errors::Storage(path.display().to_string());
+
+    let registrations = match tokio::fs::read(&path).await {
+      Ok(content) => serde_json::from_slice(&content)
+        .into_report()
+        .change_context_lazy(storage_error)?,
+      Err(e) if e.kind() == std::io::ErrorKind::NotFound => vec![],
+      Err(e) => return Err(e).into_report().change_context_lazy(storage_error),
+    };
+
+    Ok(Self {
+      path,
+      registrations,
+    })
+  }
+
+  pub async fn save(&self) -> error_stack::Result<(), Errors> {
+    let storage_error = || Errors::Storage(self.path.display().to_string());
+
+    let content = serde_json::to_vec_pretty(&self.registrations)
+      .into_report()
+      .change_context_lazy(storage_error)?;
+
+    if let Some(directory) = self.path.parent() {
+      tokio::fs::create_dir_all(directory)
+        .await
+        .into_report()
+        .change_context_lazy(storage_error)?;
+    }
+    tokio::fs::write(&self.path, content)
+      .await
+      .into_report()
+      .change_context_lazy(storage_error)
+  }
+
+  pub fn find_mut(&mut self, username: &str) -> Option<&mut Registration> {
+    self
+      .registrations
+      .iter_mut()
+      .find(|registration| registration.username == username)
+  }
+
+  pub fn add(&mut self, registration: Registration) {
+    self.registrations.push(registration)
+  }
+}
+
+/// Whether `network` is a valid address or CIDR network.
+pub fn is_network(network: &str) -> bool {
+  contains(network, IpAddr::from([0, 0, 0, 0])).is_some()
+}
+
+/// Whether `ip` is in the CIDR `network`, `None` if the network is invalid.
+fn contains(network: &str, ip: IpAddr) -> Option<bool> {
+  let (address, prefix) = match network.split_once('/') {
+    Some((address, prefix)) => (
+      address.parse::<IpAddr>().ok()?,
+      Some(prefix.parse::<u32>().ok()?),
+    ),
+    None => (network.parse::<IpAddr>().ok()?, None),
+  };
+
+  let (address, ip, bits) = match (address, ip) {
+    (IpAddr::V4(address), IpAddr::V4(ip)) => {
+      (u32::from(address) as u128, u32::from(ip) as u128, 32)
+    }
+    (IpAddr::V6(address), IpAddr::V6(ip)) => (u128::from(address), u128::from(ip), 128),
+    (IpAddr::V4(_), _) => return prefix.filter(|prefix| *prefix <= 32).map(|_| false),
+    (IpAddr::V6(_), _) => return prefix.filter(|prefix| *prefix <= 128).map(|_| false),
+  };
+
+  let prefix = prefix.unwrap_or(bits);
+  if prefix > bits {
+    return None;
+  }
+  let mask = u128::MAX.checked_shl(bits - prefix).unwrap_or(0) & (u128::MAX >> (128 - bits));
+
+  Some(address & mask == ip & mask)
+}
+
+fn iterations() -> NonZeroU32 {
+  NonZeroU32::new(PBKDF2_ITERATIONS).expect("the iterations are not zero")
+}
+
+fn hash(password: &str) -> String {
+  let salt = rand::thread_rng().gen::<[u8; 16]>();
+  let mut hash = [0; 32];
+  pbkdf2::derive(
+    pbkdf2::PBKDF2_HMAC_SHA256,
+    iterations(),
+    &salt,
+    password.as_bytes(),
+    &mut hash,
+  );
+
+  let engine = base64::engine::general_purpose::STANDARD;
+  format!("{}${}", engine.encode(salt), engine.encode(hash))
+}
+
+/// A random version 4 UUID.
+fn uuid() -> String {
+  let mut bytes = rand::thread_rng().gen::<[u8; 16]>();
+  bytes[6] = (bytes[6] & 0x0f) | 0x40;
+  bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+  let hex = bytes
+    .iter()
+    .map(|byte| format!("{byte:02x}"))
+    .collect::<String>();
+
+  format!(
+    "{}-{}-{}-{}-{}",
+    &hex[..8],
+    &hex[8..12],
+    &hex[12..16],
+    &hex[16..20],
+    &hex[20..]
+  )
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn verify_passwords() {
+    let (registration, password) = Registration::new(vec![]);
+
+    assert_eq!(PASSWORD_LENGTH, password.len());
+    assert_eq!(36, registration.subdomain().len());
+    assert!(registration.verify(&password));
+    assert!(!registration.verify("wrong"));
+  }
+
+  #[test]
+  fn match_networks() {
+    let ip = |ip: &str| ip.parse::<IpAddr>().unwrap();
+
+    assert_eq!(Some(true), contains("192.0.2.0/24", ip("192.0.2.42")));
+    assert_eq!(Some(false), contains("192.0.2.0/24", ip("198.51.100.1")));
+    assert_eq!(Some(true), contains("192.0.2.1", ip("192.0.2.1")));
+    assert_eq!(Some(true), contains("2001:db8::/32", ip("2001:db8::1")));
+    assert_eq!(Some(false), contains("2001:db8::/32", ip("192.0.2.1")));
+    assert_eq!(Some(true), contains("0.0.0.0/0", ip("203.0.113.1")));
+    assert!(!is_network("192.0.2.0/33"));
+    assert!(!is_network("example.com"));
+  }
+
+  #[test]
+  fn keep_latest_txt_values() {
+    let (mut registration, _) = Registration::new(vec![]);
+
+    registration.txt_mut(registration.with_txt("a"));
+    registration.txt_mut(registration.with_txt("b"));
+    assert_eq!(vec!["a".to_string(), "b".to_string()], registration.txt);
+    assert_eq!(
+      vec!["b".to_string(), "c".to_string()],
+      registration.with_txt("c")
+    );
+    assert_eq!(
+      vec!["b".to_string(), "a".to_string()],
+      registration.with_txt("a")
+    );
+  }
+}
@@ -0,0 +1,31 @@
+//! Serves the metrics of the updater to Prometheus.
+
+use std::net::IpAddr;
+
+use async_trait::async_trait;
+use hyper::{header::CONTENT_TYPE, Body, Method, Request, Response, StatusCode};
+
+use crate::metrics::metrics;
+
+use super::Handler;
+
+pub struct Metrics;
+
+#[async_trait]
+impl Handler for Metrics {
+  async fn handle(&self, request: Request<Body>, _remote: IpAddr) -> Response<Body> {
+    let mut response = match (request.method(), request.uri().path()) {
+      (&Method::GET, "/metrics") => Response::new(Body::from(metrics().render())),
+      _ => {
+        let mut response = Response::new(Body::from("not found"));
+        *response.status_mut() = StatusCode::NOT_FOUND;
+        return response;
+      }
+    };
+
+    response
+      .headers_mut()
+      .insert(CONTENT_TYPE, "text/plain; version=0.0.4".parse().unwrap());
+    response
+  }
+}
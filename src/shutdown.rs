@@ -0,0 +1,97 @@
+//! Ends sessions cleanly when a command fails, panics or the process is asked
+//! to stop with Ctrl-C or SIGTERM.
+
+use std::{future::Future, panic::AssertUnwindSafe, sync::OnceLock};
+
+use error_stack::Report;
+use futures::FutureExt;
+use tokio::sync::watch;
+use tracing::warn;
+
+use crate::errors::Errors;
+
+/// The exit code of a process stopped by a signal a second time.
+const EXIT_INTERRUPTED: i32 = 130;
+
+/// Whether a shutdown was requested, the signals are only handled once the
+/// first guard installs the listener.
+fn state() -> &'static watch::Sender<bool> {
+  static STATE: OnceLock<watch::Sender<bool>> = OnceLock::new();
+
+  STATE.get_or_init(|| {
+    let (sender, _) = watch::channel(false);
+    tokio::spawn(async {
+      received().await;
+      warn!("Shutting down, the sessions are closed first. Interrupt again to exit immediately");
+      if let Some(state) = STATE.get() {
+        state.send_replace(true);
+      }
+      received().await;
+      std::process::exit(EXIT_INTERRUPTED);
+    });
+    sender
+  })
+}
+
+async fn received() {
+  #[cfg(unix)]
+  {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    if let Ok(mut terminate) = signal(SignalKind::terminate()) {
+      tokio::select! {
+        _ = tokio::signal::ctrl_c() => {}
+        _ = terminate.recv() => {}
+      }
+      return;
+    }
+  }
+
+  let _ = tokio::signal::ctrl_c().await;
+}
+
+pub fn requested() -> bool {
+  *state().borrow()
+}
+
+/// Completes once a shutdown was requested.
+pub async fn signal() {
+  let mut receiver = state().subscribe();
+  while !*receiver.borrow_and_update() {
+    if receiver.changed().await.is_err() {
+      futures::future::pending::<()>().await;
+    }
+  }
+}
+
+/// Runs `future` and turns a panic or a requested shutdown into an error, so
+/// the caller can close its sessions on every path.
+pub async fn guard<T>(
+  future: impl Future<Output = error_stack::Result<T, Errors>>,
+) -> error_stack::Result<T, Errors> {
+  tokio::select! {
+    result = AssertUnwindSafe(future).catch_unwind() => result.unwrap_or_else(|panic| {
+      let message = panic
+        .downcast_ref::<&str>()
+        .map(ToString::to_string)
+        .or_else(|| panic.downcast_ref::<String>().cloned())
+        .unwrap_or_default();
+      Err(Report::new(Errors::Panicked).attach_printable(message))
+    }),
+    _ = signal() => Err(Report::new(Errors::Interrupted)),
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[tokio::test]
+  async fn turn_panics_into_errors() {
+    assert_eq!(2, guard(async { Ok(2) }).await.unwrap());
+
+    let error = guard::<()>(async { panic!("broken") }).await.unwrap_err();
+    assert!(matches!(error.current_context(), Errors::Panicked));
+    assert!(format!("{error:?}").contains("broken"));
+  }
+}